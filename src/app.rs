@@ -1,4 +1,10 @@
-use std::str::FromStr;
+use core::fmt;
+use std::{
+    fmt::{Display, Formatter},
+    path::Path,
+    str::FromStr,
+    time::Duration,
+};
 
 use anyhow::{Context, Result};
 use crossterm::event::{Event, KeyCode, KeyEventKind};
@@ -6,18 +12,35 @@ use ratatui::{
     prelude::{Backend, Constraint, Layout},
     text::Text,
 };
+use serde::{Deserialize, Serialize};
 
 use crate::{
+    campaign::{self, Campaign, SavedEncounter},
+    combat::{
+        transcript, ActionEconomy, CombatEvent, CombatLogEntry, CombatPrompt, Combatant,
+        Concentration, EconomyResource, GameTime, HitPoints, LegendaryActions, Stopwatch,
+        TimerStatus, TranscriptFormat,
+    },
     commands::{AppCommand, StateInducer},
-    theme::THEME,
+    compendium::{self, HitPointsMode, Monster, SRD_MONSTERS},
+    dice::DiceExpression,
+    difficulty::{ChallengeRating, MAX_LEVEL},
+    export::{ExportFormat, ExportedOrder},
+    import::{self, ImportReport},
+    initiative::InitiativeSystemKind,
+    library::{self, CreatureTemplate, Library},
+    party::{Party, PartyMember},
+    quick_entry::{self, QuickEntry},
+    summary::CombatSummary,
+    theme::Themes,
     ui::elements,
-    FRAMES_PER_SECOND,
+    FRAMES_PER_SECOND, FRAME_WAIT_DURATION,
 };
 
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub enum AppMode {
     Initializing(InitializeState),
-    Running(RunMode),
+    Running(Tabs),
     Quitting(QuittingState),
 }
 
@@ -30,12 +53,27 @@ impl Default for AppMode {
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct InitializeState {
     pub splash_screen_frames_remaining: u32,
+    /// Participants to start the encounter with, such as those imported on the command line.
+    pub participants: Vec<Participant>,
+    /// What the command line imports left out, to show once the editor opens.
+    pub report: Option<ImportReport>,
+    /// The campaign, library and party, as loaded before the app started.
+    pub workspace: Workspace,
+    /// Why some of the workspace couldn't be loaded, if it couldn't.
+    pub message: Option<String>,
+    /// The themes to draw with, and the one picked to start with.
+    pub themes: Themes,
 }
 
 impl Default for InitializeState {
     fn default() -> Self {
         InitializeState {
-            splash_screen_frames_remaining: (FRAMES_PER_SECOND * 1) as u32,
+            splash_screen_frames_remaining: FRAMES_PER_SECOND as u32,
+            participants: vec![],
+            report: None,
+            workspace: Workspace::default(),
+            message: None,
+            themes: Themes::default(),
         }
     }
 }
@@ -43,12 +81,14 @@ impl Default for InitializeState {
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct QuittingState {
     pub quitting_screen_frames_remaining: u32,
+    pub themes: Themes,
 }
 
 impl Default for QuittingState {
     fn default() -> Self {
         QuittingState {
-            quitting_screen_frames_remaining: (FRAMES_PER_SECOND * 1) as u32,
+            quitting_screen_frames_remaining: FRAMES_PER_SECOND as u32,
+            themes: Themes::default(),
         }
     }
 }
@@ -66,13 +106,37 @@ impl AppMode {
                 if state.splash_screen_frames_remaining != 0 {
                     Ok(Some(AppMode::Initializing(InitializeState {
                         splash_screen_frames_remaining: state.splash_screen_frames_remaining - 1,
+                        ..state.clone()
                     })))
                 } else {
                     // We're done initializing, move to the next state.
                     // (For now, we're just going to move to the next state, but in the future
                     // we might want to do some cleanup here.)
-                    Ok(Some(AppMode::Running(RunMode::EditingEncounter(
-                        EditingEncounterState::default(),
+                    // Participants from the command line go straight into an encounter. Otherwise
+                    // the campaign's encounters are shown to pick from.
+                    if state.participants.is_empty() && state.report.is_none() {
+                        let browser = EncounterBrowserState {
+                            message: state.message.clone(),
+                            ..Default::default()
+                        };
+                        return Ok(Some(AppMode::Running(Tabs::new(
+                            RunMode::BrowsingEncounters(Box::new(browser)),
+                            state.workspace.clone(),
+                            state.themes.clone(),
+                        ))));
+                    }
+
+                    let encounter = EditingEncounterState {
+                        focused_row: (!state.participants.is_empty()).then_some(0),
+                        participants: state.participants.clone(),
+                        report: state.report.clone(),
+                        message: state.message.clone(),
+                        ..Default::default()
+                    };
+                    Ok(Some(AppMode::Running(Tabs::new(
+                        RunMode::EditingEncounter(Box::new(encounter)),
+                        state.workspace.clone(),
+                        state.themes.clone(),
                     ))))
                 }
             }
@@ -86,8 +150,8 @@ impl AppMode {
                 // // If the pressed key corresponds to a command, run command against state.
                 let next = StateInducer::from(command)(self);
 
-                // // TODO - Other stuff while running.
-                Ok(Some(next))
+                // Every pass through here is one frame, whether or not a key was pressed.
+                Ok(Some(next.tick()))
             }
             AppMode::Quitting(quitting_state) => {
                 // If our state says we're quitting, we're gonna quit. Break out of
@@ -97,6 +161,7 @@ impl AppMode {
                         quitting_screen_frames_remaining: quitting_state
                             .quitting_screen_frames_remaining
                             - 1,
+                        ..quitting_state.clone()
                     })))
                 } else {
                     Ok(None)
@@ -105,41 +170,89 @@ impl AppMode {
         }
     }
 
+    /// Advance anything that runs in real time by one frame.
+    fn tick(mut self) -> Self {
+        // Only the combat being looked at is timed
+        if let AppMode::Running(tabs) = &mut self {
+            if let RunMode::RunningCombat(state) = tabs.current_mut() {
+                state.tick();
+            }
+        }
+        self
+    }
+
     pub fn draw(&self, terminal: &mut crate::Terminal<impl Backend>) -> Result<()> {
         match self {
-            AppMode::Initializing(_state) => {
+            AppMode::Initializing(state) => {
                 terminal.draw(|frame| {
-                    elements::splash_screen(frame);
+                    elements::splash_screen(frame, state.themes.current());
                 })?;
 
                 Ok(())
             }
-            AppMode::Running(run_mode) => {
-                match run_mode {
-                    RunMode::EditingEncounter(_state) => {
-                        terminal.draw(|frame| {
-                            let rects =
-                                Layout::vertical([Constraint::Min(5), Constraint::Length(3)])
-                                    .split(frame.size());
-
-                            elements::controls_panel(frame, run_mode, rects[1]);
-                        })?;
-
-                        Ok(())
-                    } // RunMode::RunningCombat(_running_combat_state) => {
-                      //     terminal.draw(|frame| {
-                      //         let style = THEME.root;
-
-                      //         frame.render_widget(Text::styled("Running Combat", style), frame.size());
-                      //     })?;
-                      //     Ok(())
-                      // }
-                }
+            AppMode::Running(tabs) => {
+                let theme = tabs.themes.current();
+                terminal.draw(|frame| {
+                    let rects = Layout::vertical([
+                        Constraint::Length(1),
+                        Constraint::Min(5),
+                        Constraint::Length(elements::CONTROLS_PANEL_HEIGHT),
+                    ])
+                    .split(frame.size());
+
+                    // Anything left uncovered, like the status line, takes the theme's background
+                    let area = frame.size();
+                    frame.buffer_mut().set_style(area, theme.root);
+
+                    elements::tab_bar(frame, tabs, rects[0]);
+                    match tabs.current() {
+                        RunMode::BrowsingEncounters(state) => {
+                            elements::encounter_browser_tab(
+                                frame,
+                                theme,
+                                state,
+                                &tabs.workspace.campaign,
+                                rects[1],
+                            );
+                        }
+                        RunMode::EditingEncounter(state) => {
+                            let screen =
+                                Layout::vertical([Constraint::Min(5), Constraint::Length(1)])
+                                    .split(rects[1]);
+
+                            elements::editing_encounter_tab(frame, theme, state, screen[0]);
+                            if let Some(picker) = &state.picker {
+                                elements::monster_picker(
+                                    frame,
+                                    theme,
+                                    picker,
+                                    &tabs.workspace.library,
+                                    state.hit_points_mode,
+                                    screen[0],
+                                );
+                            }
+                            elements::difficulty_status_line(frame, theme, state, screen[1]);
+                        }
+                        RunMode::RunningCombat(state) => {
+                            elements::running_combat_tab(frame, theme, state, rects[1]);
+                        }
+                        RunMode::CombatSummary(state) => {
+                            elements::combat_summary_tab(frame, theme, state, rects[1]);
+                        }
+                    }
+                    if let Some(warning) = tabs.close_warning().filter(|_| tabs.is_closing()) {
+                        let title = format!("Close {}?", tabs.tabs[tabs.selected].name);
+                        elements::prompt_popup(frame, theme, &title, warning, rects[1]);
+                    }
+                    elements::controls_panel(frame, tabs, rects[2]);
+                })?;
+
+                Ok(())
             }
 
-            AppMode::Quitting(_quitting_state) => {
+            AppMode::Quitting(quitting_state) => {
                 terminal.draw(|frame| {
-                    let style = THEME.root;
+                    let style = quitting_state.themes.current().root;
 
                     frame.render_widget(Text::styled("Bye for now!", style), frame.size());
                 })?;
@@ -150,7 +263,12 @@ impl AppMode {
     }
 }
 
-fn poll_for_keypress() -> Result<Option<KeyCode>> {
+/// Wait up to a frame for a keypress, so the main loop keeps ticking while nobody is typing.
+pub fn poll_for_keypress() -> Result<Option<KeyCode>> {
+    if !crossterm::event::poll(*FRAME_WAIT_DURATION).context("Could not poll for events.")? {
+        return Ok(None);
+    }
+
     if let Event::Key(key) = crossterm::event::read().context("Could not read event.")? {
         if key.kind == KeyEventKind::Press {
             Ok(Some(key.code))
@@ -164,285 +282,3866 @@ fn poll_for_keypress() -> Result<Option<KeyCode>> {
 
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub enum RunMode {
-    EditingEncounter(EditingEncounterState),
+    BrowsingEncounters(Box<EncounterBrowserState>),
+    EditingEncounter(Box<EditingEncounterState>),
+    RunningCombat(Box<RunningCombatState>),
+    CombatSummary(Box<CombatSummaryState>),
 }
 
+/// An encounter open in its own tab, at whatever stage it's at.
 #[derive(Clone, Debug, Eq, PartialEq)]
-pub struct EditingEncounterState {
-    pub participants: Vec<Participant>,
-    pub focused_row: Option<usize>,
+pub struct Tab {
+    pub name: String,
+    pub mode: RunMode,
 }
 
-impl Default for EditingEncounterState {
-    fn default() -> Self {
-        EditingEncounterState {
-            participants: vec![],
-            focused_row: None,
+/// The campaign, the creature library and the party roster. There's one for the whole app, so
+/// whatever one tab saves or changes is there in all the others.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Workspace {
+    pub campaign: Campaign,
+    /// The creatures saved from earlier encounters.
+    pub library: Library,
+    /// The player characters who show up to every session.
+    pub party: Party,
+}
+
+impl Workspace {
+    /// Load everything from its usual place, along with why anything couldn't be, one file at a
+    /// time. Files that can't be read are left out, and a campaign or library that can't be read
+    /// at all starts out empty so it can still be saved to. A party that can't be read is kept
+    /// from being saved over.
+    pub fn open() -> (Self, Option<String>) {
+        let mut workspace = Workspace::default();
+        let mut warnings = vec![];
+
+        if let Some(dir) = Campaign::default_dir() {
+            workspace.campaign = match Campaign::open(&dir) {
+                Ok((campaign, skipped)) => {
+                    warnings.extend(skipped);
+                    campaign
+                }
+                Err(error) => {
+                    warnings.push(format!("{:#}", error));
+                    Campaign {
+                        dir: Some(dir),
+                        encounters: vec![],
+                    }
+                }
+            };
+        }
+        if let Some(dir) = Library::default_dir() {
+            workspace.library = match Library::open(&dir) {
+                Ok((library, skipped)) => {
+                    warnings.extend(skipped);
+                    library
+                }
+                Err(error) => {
+                    warnings.push(format!("{:#}", error));
+                    Library {
+                        dir: Some(dir),
+                        templates: vec![],
+                    }
+                }
+            };
+        }
+        if let Some(path) = Party::default_path() {
+            workspace.party = Party::open(&path).unwrap_or_else(|error| {
+                warnings.push(format!("{:#}", error));
+                Party::unreadable(&path)
+            });
         }
+
+        let message = (!warnings.is_empty()).then(|| warnings.join("; "));
+        (workspace, message)
     }
 }
 
+/// Every open encounter, with the one being shown.
 #[derive(Clone, Debug, Eq, PartialEq)]
-pub struct AppState {
-    mode: AppMode,
+pub struct Tabs {
+    /// Never empty, since the last tab can't be closed.
+    pub tabs: Vec<Tab>,
+    pub selected: usize,
+    /// The new name being typed for the selected tab.
+    pub rename_input: Option<String>,
+    /// Set while asking whether to close the selected tab, and lose what's in it.
+    pub closing: bool,
+    /// What every tab works from.
+    pub workspace: Workspace,
+    /// The themes to draw with, and the one picked.
+    pub themes: Themes,
 }
 
-impl Default for AppState {
-    fn default() -> Self {
-        AppState {
-            mode: AppMode::Initializing(InitializeState::default()),
-        }
+impl From<RunMode> for Tabs {
+    fn from(mode: RunMode) -> Self {
+        Tabs::new(mode, Workspace::default(), Themes::default())
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
-pub struct Participant {
-    name: String,
-    initiative_rolls: [Option<u8>; 4],
-}
+impl Tabs {
+    pub const BROWSER_TAB_NAME: &'static str = "Encounters";
 
-impl Participant {
-    pub fn new(name: &str) -> Self {
-        Participant {
-            name: String::from(name),
-            initiative_rolls: [None; 4],
+    pub fn new(mode: RunMode, workspace: Workspace, themes: Themes) -> Self {
+        let name = match mode {
+            RunMode::BrowsingEncounters(_) => Tabs::BROWSER_TAB_NAME,
+            _ => "Encounter 1",
+        };
+        Tabs {
+            tabs: vec![Tab {
+                name: String::from(name),
+                mode,
+            }],
+            selected: 0,
+            rename_input: None,
+            closing: false,
+            workspace,
+            themes,
         }
     }
-}
 
-impl FromStr for Participant {
-    type Err = ();
+    pub fn current(&self) -> &RunMode {
+        &self.tabs[self.selected].mode
+    }
 
-    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
-        Ok(Participant {
-            name: s.to_string(),
-            initiative_rolls: [None; 4],
-        })
+    pub fn current_mut(&mut self) -> &mut RunMode {
+        &mut self.tabs[self.selected].mode
     }
-}
 
-#[derive(Clone, Debug, PartialEq, Eq)]
-pub struct CombatTurn {
-    name: String,
-    initiative_roll_value: u8,
-    unconscious: bool,
-    dead: bool,
-}
+    /// The tab being shown, along with the workspace, to change both at once.
+    pub fn current_mut_with_workspace(&mut self) -> (&mut RunMode, &mut Workspace) {
+        (&mut self.tabs[self.selected].mode, &mut self.workspace)
+    }
 
-impl From<Participant> for Vec<CombatTurn> {
-    fn from(value: Participant) -> Self {
-        let mut turns = Vec::new();
-        for roll in value.initiative_rolls.iter().filter_map(|x| *x) {
-            turns.push(CombatTurn {
-                name: value.name.clone(),
-                initiative_roll_value: roll,
-                unconscious: false,
-                dead: false,
-            });
-        }
-        turns
+    /// The combat the players should be following: the one in the selected tab, or else the
+    /// first tab with combat running, so the GM can look elsewhere without the players noticing.
+    pub fn running_combat(&self) -> Option<&RunningCombatState> {
+        std::iter::once(&self.tabs[self.selected])
+            .chain(&self.tabs)
+            .find_map(|tab| match &tab.mode {
+                RunMode::RunningCombat(combat) => Some(combat.as_ref()),
+                _ => None,
+            })
     }
-}
 
-pub fn participants_to_ordered_combat_turns(participants: Vec<Participant>) -> Vec<CombatTurn> {
-    let mut result =
-        participants
-            .into_iter()
-            .fold(Vec::new(), |mut acc: Vec<CombatTurn>, p: Participant| {
-                let turns: Vec<CombatTurn> = p.into();
-                acc.extend(turns);
-                acc
-            });
+    /// Open a new, empty encounter after the others and switch to it.
+    pub fn new_tab(&mut self) {
+        self.tabs.push(Tab {
+            name: self.unused_name(),
+            mode: RunMode::EditingEncounter(Box::default()),
+        });
+        self.selected = self.tabs.len() - 1;
+    }
 
-    result.sort_by(|a, b| b.initiative_roll_value.cmp(&a.initiative_roll_value));
+    /// Open the campaign's encounters in a new tab.
+    pub fn open_browser(&mut self) {
+        self.tabs.push(Tab {
+            name: String::from(Tabs::BROWSER_TAB_NAME),
+            mode: RunMode::BrowsingEncounters(Box::default()),
+        });
+        self.selected = self.tabs.len() - 1;
+    }
 
-    result
-}
+    /// Replace the encounter browser in the current tab with `encounter`, named after it.
+    pub fn open_encounter(&mut self, encounter: EditingEncounterState) {
+        let name = match &encounter.saved_as {
+            Some(name) => name.clone(),
+            None => self.unused_name(),
+        };
+        self.tabs[self.selected] = Tab {
+            name,
+            mode: RunMode::EditingEncounter(Box::new(encounter)),
+        };
+    }
 
-mod tests {
-    use super::*;
+    /// The first "Encounter" number not taken, so names stay unique after tabs are closed.
+    fn unused_name(&self) -> String {
+        (1..)
+            .map(|number| format!("Encounter {}", number))
+            .find(|name| self.tabs.iter().all(|tab| tab.name != *name))
+            .unwrap_or_default()
+    }
 
-    #[test]
-    fn test_participant_constructor() {
-        let result = Participant::new("Goku");
+    /// Close the selected tab, unless it's the only one. If that would lose a combat, a summary
+    /// or an encounter that hasn't been saved, ask first.
+    pub fn close_tab(&mut self) {
+        if self.tabs.len() < 2 {
+            return;
+        }
 
-        assert_eq!(result.name, "Goku");
-        assert_eq!(result.initiative_rolls, [None; 4]);
+        match self.close_warning() {
+            Some(_) => self.closing = true,
+            None => self.remove_selected(),
+        }
     }
 
-    #[test]
-    fn test_single_participant_to_combat_turns() {
-        let participant = Participant {
-            name: "Gandalf".to_string(),
-            initiative_rolls: [Some(1), Some(2), Some(3), Some(4)],
-        };
+    pub fn is_closing(&self) -> bool {
+        self.closing
+    }
 
-        let turns: Vec<CombatTurn> = participant.into();
+    /// Close the selected tab after all, once it's been asked about.
+    pub fn confirm_close(&mut self) {
+        if self.closing {
+            self.closing = false;
+            self.remove_selected();
+        }
+    }
 
-        assert_eq!(turns.len(), 4);
-        assert_eq!(turns[0].name, "Gandalf");
-        assert_eq!(turns[0].initiative_roll_value, 1);
-        assert_eq!(turns[1].name, "Gandalf");
-        assert_eq!(turns[1].initiative_roll_value, 2);
-        assert_eq!(turns[2].name, "Gandalf");
-        assert_eq!(turns[2].initiative_roll_value, 3);
-        assert_eq!(turns[3].name, "Gandalf");
-        assert_eq!(turns[3].initiative_roll_value, 4);
+    pub fn cancel_close(&mut self) {
+        self.closing = false;
     }
 
-    #[test]
-    fn test_multiple_participants_to_combat_turns_sorted() {
-        let gandalf = Participant {
-            name: "Gandalf".to_string(),
-            initiative_rolls: [Some(20), Some(17), None, None],
-        };
+    /// What closing the selected tab would lose, if anything.
+    pub fn close_warning(&self) -> Option<&'static str> {
+        match self.current() {
+            RunMode::BrowsingEncounters(_) => None,
+            RunMode::EditingEncounter(state) if state.is_clean(&self.workspace.campaign) => None,
+            RunMode::EditingEncounter(_) => Some("The encounter has changes that aren't saved."),
+            RunMode::RunningCombat(_) => Some("Combat is still going, and will be lost."),
+            RunMode::CombatSummary(state) if state.saved => None,
+            RunMode::CombatSummary(_) => Some("The combat summary hasn't been saved."),
+        }
+    }
 
-        let legolas = Participant {
-            name: "Legolas".to_string(),
-            initiative_rolls: [Some(15), None, None, None],
-        };
+    fn remove_selected(&mut self) {
+        self.tabs.remove(self.selected);
+        self.selected = self.selected.min(self.tabs.len() - 1);
+    }
 
-        let gimli = Participant {
-            name: "Gimli".to_string(),
-            initiative_rolls: [Some(7), None, None, None],
-        };
+    pub fn next_tab(&mut self) {
+        self.selected = (self.selected + 1) % self.tabs.len();
+    }
 
-        let balrog = Participant {
-            name: "Balrog".to_string(),
-            initiative_rolls: [Some(25), Some(22), None, None],
-        };
+    pub fn prev_tab(&mut self) {
+        self.selected = (self.selected + self.tabs.len() - 1) % self.tabs.len();
+    }
 
-        let result = participants_to_ordered_combat_turns(vec![gandalf, legolas, gimli, balrog]);
+    pub fn is_renaming(&self) -> bool {
+        self.rename_input.is_some()
+    }
 
-        assert_eq!(result.len(), 6);
+    pub fn begin_rename(&mut self) {
+        self.rename_input = Some(self.tabs[self.selected].name.clone());
+    }
 
-        assert_eq!(
-            result[0],
-            CombatTurn {
-                name: "Balrog".to_string(),
-                initiative_roll_value: 25,
-                unconscious: false,
-                dead: false
-            }
-        );
-        assert_eq!(
-            result[1],
-            CombatTurn {
-                name: "Balrog".to_string(),
-                initiative_roll_value: 22,
-                unconscious: false,
-                dead: false
-            }
-        );
-        assert_eq!(
-            result[2],
-            CombatTurn {
-                name: "Gandalf".to_string(),
-                initiative_roll_value: 20,
-                unconscious: false,
-                dead: false
-            }
-        );
-        assert_eq!(
-            result[3],
-            CombatTurn {
-                name: "Gandalf".to_string(),
-                initiative_roll_value: 17,
-                unconscious: false,
-                dead: false
-            }
-        );
-        assert_eq!(
-            result[4],
-            CombatTurn {
-                name: "Legolas".to_string(),
-                initiative_roll_value: 15,
-                unconscious: false,
-                dead: false
-            }
-        );
-        assert_eq!(
-            result[5],
-            CombatTurn {
-                name: "Gimli".to_string(),
-                initiative_roll_value: 7,
-                unconscious: false,
-                dead: false
+    /// Give the selected tab the name typed in. A blank name leaves it as it was.
+    pub fn submit_rename(&mut self) {
+        if let Some(name) = self.rename_input.take() {
+            if !name.trim().is_empty() {
+                self.tabs[self.selected].name = name.trim().to_string();
             }
-        );
+        }
     }
 
-    #[test]
-    fn test_app_mode_initial_state() {
-        let app = AppMode::default();
+    pub fn cancel_rename(&mut self) {
+        self.rename_input = None;
+    }
 
-        let result = app.next_state().unwrap();
+    pub fn rename_input_char(&mut self, c: char) {
+        if let Some(input) = self.rename_input.as_mut() {
+            input.push(c);
+        }
+    }
 
-        assert_eq!(
-            result,
-            Some(AppMode::Initializing(InitializeState {
-                splash_screen_frames_remaining: (FRAMES_PER_SECOND * 2) as u32 - 1,
-            }))
-        );
+    pub fn rename_input_backspace(&mut self) {
+        if let Some(input) = self.rename_input.as_mut() {
+            input.pop();
+        }
     }
+}
 
-    #[test]
-    fn test_app_mode_initial_state_to_running() {
-        let app = AppMode::Initializing(InitializeState {
-            splash_screen_frames_remaining: 0,
-        });
+/// The columns of the participants table, in display order. Every participant has one
+/// `InitiativeRoll` column per initiative slot, so the number of columns varies.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ParticipantColumn {
+    Name,
+    Side,
+    HitPoints,
+    ArmorClass,
+    InitiativeModifier,
+    Level,
+    ChallengeRating,
+    LegendaryActions,
+    InitiativeRoll(usize),
+}
 
-        let result = app.next_state().unwrap();
+impl ParticipantColumn {
+    /// The columns every participant has, which come before their initiative rolls.
+    pub const FIXED: [ParticipantColumn; 8] = [
+        ParticipantColumn::Name,
+        ParticipantColumn::Side,
+        ParticipantColumn::HitPoints,
+        ParticipantColumn::ArmorClass,
+        ParticipantColumn::InitiativeModifier,
+        ParticipantColumn::Level,
+        ParticipantColumn::ChallengeRating,
+        ParticipantColumn::LegendaryActions,
+    ];
+}
 
-        assert_eq!(
-            result,
-            Some(AppMode::Running(RunMode::EditingEncounter(
-                EditingEncounterState {
-                    participants: vec![],
-                    focused_row: None,
-                }
-            )))
-        );
-    }
+/// The turn time limits that can be chosen in the editor.
+pub const TURN_TIME_LIMITS: [Option<Duration>; 5] = [
+    None,
+    Some(Duration::from_secs(30)),
+    Some(Duration::from_secs(60)),
+    Some(Duration::from_secs(90)),
+    Some(Duration::from_secs(120)),
+];
 
-    #[test]
-    fn test_app_mode_running_state_decrements_per_frame() {
-        let app = AppMode::Quitting(QuittingState {
-            quitting_screen_frames_remaining: 1,
-        });
+/// The encounters saved in the campaign, to pick one to run or build another from.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct EncounterBrowserState {
+    pub focused_row: usize,
+    /// The outcome of the last thing duplicated, or why the campaign couldn't be read.
+    pub message: Option<String>,
+}
 
-        let result = app.next_state().unwrap();
+impl EncounterBrowserState {
+    pub fn focused_encounter<'a>(&self, campaign: &'a Campaign) -> Option<&'a SavedEncounter> {
+        campaign.encounters.get(self.focused_row)
+    }
 
-        assert_eq!(
-            result,
-            Some(AppMode::Quitting(QuittingState {
-                quitting_screen_frames_remaining: 0,
-            }))
-        );
+    pub fn focus_next_row(&mut self, campaign: &Campaign) {
+        if self.focused_row + 1 < campaign.encounters.len() {
+            self.focused_row += 1;
+        }
     }
 
-    #[test]
-    fn test_app_mode_quitting_state_to_none() {
-        let app = AppMode::Quitting(QuittingState {
-            quitting_screen_frames_remaining: 0,
-        });
+    pub fn focus_prev_row(&mut self) {
+        self.focused_row = self.focused_row.saturating_sub(1);
+    }
 
-        let result = app.next_state().unwrap();
+    /// An empty encounter that can be saved to the campaign.
+    pub fn new_encounter(&self) -> EditingEncounterState {
+        EditingEncounterState {
+            message: self.message.clone(),
+            ..Default::default()
+        }
+    }
 
-        assert_eq!(result, None);
+    /// The focused encounter, ready to edit or run. `None` if there are no saved encounters.
+    pub fn open_focused(&self, campaign: &Campaign) -> Option<EditingEncounterState> {
+        let saved = self.focused_encounter(campaign)?;
+
+        Some(EditingEncounterState {
+            participants: saved.participants.clone(),
+            focused_row: (!saved.participants.is_empty()).then_some(0),
+            initiative_system: saved.initiative_system,
+            turn_time_limit: saved.turn_time_limit(),
+            saved_as: Some(saved.name.clone()),
+            message: None,
+            ..self.new_encounter()
+        })
     }
 
-    #[test]
-    fn test_quitting_command_induce_state_change() {
-        let app = AppMode::Running(RunMode::EditingEncounter(EditingEncounterState::default()));
+    /// Save a copy of the focused encounter to build a new one from, and focus the copy.
+    pub fn duplicate_focused(&mut self, campaign: &mut Campaign) {
+        let Some(name) = self
+            .focused_encounter(campaign)
+            .map(|saved| saved.name.clone())
+        else {
+            return;
+        };
 
-        let func = StateInducer::from(AppCommand::Quit);
-        let result = func(&app);
+        self.message = Some(match campaign.duplicate(&name) {
+            Ok(copy) => {
+                self.focused_row = campaign
+                    .encounters
+                    .iter()
+                    .position(|saved| saved.name == copy)
+                    .unwrap_or_default();
+                format!("Saved a copy of {} as {}", name, copy)
+            }
+            Err(error) => format!("{:#}", error),
+        });
+    }
+}
 
-        assert_eq!(
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct EditingEncounterState {
+    pub participants: Vec<Participant>,
+    pub focused_row: Option<usize>,
+    pub focused_col: usize,
+    /// Text typed into the focused cell. `Some` while a cell is being edited.
+    pub input: Option<String>,
+    /// Set while a new participant is being typed in, which can use the quick entry grammar.
+    pub adding: bool,
+    pub initiative_system: InitiativeSystemKind,
+    /// How long each combatant's turn should take, if there's a limit.
+    pub turn_time_limit: Option<Duration>,
+    /// The monster compendium, while it's open to pick from.
+    pub picker: Option<MonsterPicker>,
+    /// Whether monsters from the compendium get rolled or average hit points.
+    pub hit_points_mode: HitPointsMode,
+    /// Text being typed into the status line, and what it's for.
+    pub prompt: Option<EditorPrompt>,
+    /// The outcome of the last thing saved or loaded.
+    pub message: Option<String>,
+    /// What the last import left out, shown until it's dismissed.
+    pub report: Option<ImportReport>,
+    /// The name the encounter is saved under in the campaign, once it's been saved.
+    pub saved_as: Option<String>,
+}
+
+/// A question the editor asks in its status line.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EditorPrompt {
+    /// Tags for saving the focused participant to the library.
+    SaveTemplate { name: String, tags: String },
+    /// The file to import participants from.
+    Import { path: String },
+    /// The name to save the encounter to the campaign as.
+    SaveEncounter { name: String },
+}
+
+impl EditorPrompt {
+    pub fn input(&self) -> &str {
+        match self {
+            EditorPrompt::SaveTemplate { tags, .. } => tags,
+            EditorPrompt::Import { path } => path,
+            EditorPrompt::SaveEncounter { name } => name,
+        }
+    }
+
+    pub fn input_mut(&mut self) -> &mut String {
+        match self {
+            EditorPrompt::SaveTemplate { tags, .. } => tags,
+            EditorPrompt::Import { path } => path,
+            EditorPrompt::SaveEncounter { name } => name,
+        }
+    }
+}
+
+impl Display for EditorPrompt {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            EditorPrompt::SaveTemplate { name, .. } => {
+                write!(f, "Tags for {} (comma separated)", name)
+            }
+            EditorPrompt::Import { .. } => write!(f, "Import from (CSV or one name per line)"),
+            EditorPrompt::SaveEncounter { .. } => write!(f, "Save encounter as"),
+        }
+    }
+}
+
+/// A search through the creature library and the monster compendium.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct MonsterPicker {
+    pub query: String,
+    /// Index into the search results.
+    pub selected: usize,
+}
+
+/// A search result in the picker.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PickerEntry<'a> {
+    Template(&'a CreatureTemplate),
+    Monster(&'static Monster),
+}
+
+impl MonsterPicker {
+    /// Matching library templates first, then monsters from the compendium.
+    pub fn results<'a>(&self, library: &'a Library) -> Vec<PickerEntry<'a>> {
+        library
+            .search(&self.query)
+            .into_iter()
+            .map(PickerEntry::Template)
+            .chain(
+                compendium::search(&SRD_MONSTERS, &self.query)
+                    .into_iter()
+                    .map(PickerEntry::Monster),
+            )
+            .collect()
+    }
+}
+
+impl EditingEncounterState {
+    /// How many initiative roll columns the table needs in order to show every slot of every
+    /// participant. Always at least one, so the table has a roll column to focus.
+    pub fn initiative_slot_columns(&self) -> usize {
+        self.participants
+            .iter()
+            .map(|p| p.initiative_rolls.len())
+            .max()
+            .unwrap_or(1)
+            .max(1)
+    }
+
+    pub fn column_count(&self) -> usize {
+        ParticipantColumn::FIXED.len() + self.initiative_slot_columns()
+    }
+
+    pub fn column_at(&self, index: usize) -> ParticipantColumn {
+        ParticipantColumn::FIXED
+            .get(index)
+            .copied()
+            .unwrap_or_else(|| {
+                ParticipantColumn::InitiativeRoll(index - ParticipantColumn::FIXED.len())
+            })
+    }
+
+    pub fn column_index(&self, column: ParticipantColumn) -> usize {
+        match column {
+            ParticipantColumn::InitiativeRoll(slot) => ParticipantColumn::FIXED.len() + slot,
+            fixed => ParticipantColumn::FIXED
+                .iter()
+                .position(|c| *c == fixed)
+                .unwrap_or_default(),
+        }
+    }
+
+    pub fn focused_column(&self) -> ParticipantColumn {
+        self.column_at(self.focused_col)
+    }
+
+    pub fn focus_column(&mut self, column: ParticipantColumn) {
+        self.focused_col = self.column_index(column);
+        self.clamp_focused_col();
+    }
+
+    pub fn is_editing(&self) -> bool {
+        self.input.is_some()
+    }
+
+    pub fn is_picking(&self) -> bool {
+        self.picker.is_some()
+    }
+
+    pub fn open_picker(&mut self) {
+        self.picker = Some(MonsterPicker::default());
+    }
+
+    /// Whether there's nothing to lose by closing the encounter: it's empty, or just as it was
+    /// saved to `campaign`.
+    pub fn is_clean(&self, campaign: &Campaign) -> bool {
+        match self.saved_as.as_deref().and_then(|name| campaign.get(name)) {
+            Some(saved) => {
+                saved.participants == self.participants
+                    && saved.initiative_system == self.initiative_system
+                    && saved.turn_time_limit() == self.turn_time_limit
+            }
+            None => self.participants.is_empty(),
+        }
+    }
+
+    /// Start typing the name to save the encounter to the campaign as. An encounter that's
+    /// been saved before starts with its name.
+    pub fn begin_save_encounter(&mut self) {
+        self.prompt = Some(EditorPrompt::SaveEncounter {
+            name: self.saved_as.clone().unwrap_or_default(),
+        });
+        self.message = None;
+    }
+
+    /// Save the encounter to the campaign with the name that was typed in.
+    fn save_encounter(&mut self, campaign: &mut Campaign) {
+        let Some(EditorPrompt::SaveEncounter { name }) = self.prompt.take() else {
+            return;
+        };
+        let name = name.trim().to_string();
+        if name.is_empty() {
+            self.message = Some(String::from("Give the encounter a name to save it."));
+            return;
+        }
+
+        let encounter = SavedEncounter {
+            name: name.clone(),
+            participants: self.participants.clone(),
+            initiative_system: self.initiative_system,
+            turn_time_limit: self.turn_time_limit.map(|limit| limit.as_secs()),
+            // Saving over an encounter doesn't make it any less played
+            last_played: campaign
+                .get(&name)
+                .and_then(|existing| existing.last_played),
+        };
+        self.message = Some(match campaign.save(encounter) {
+            Ok(path) => {
+                self.saved_as = Some(name.clone());
+                format!("Saved {} to {}", name, path.display())
+            }
+            Err(error) => format!("{:#}", error),
+        });
+    }
+
+    /// Note in the campaign that the encounter is being played now, if it has been saved.
+    pub fn mark_played(&mut self, campaign: &mut Campaign) {
+        let Some(name) = &self.saved_as else {
+            return;
+        };
+
+        if let Err(error) = campaign.mark_played(name, campaign::now()) {
+            self.message = Some(format!("{:#}", error));
+        }
+    }
+
+    /// Add every party member who isn't in the encounter already.
+    pub fn add_party(&mut self, party: &Party) {
+        if party.members.is_empty() {
+            self.message = Some(String::from(
+                "No party saved yet. Give the characters a level and press [P] to save them.",
+            ));
+            return;
+        }
+
+        let newcomers: Vec<Participant> = party
+            .members
+            .iter()
+            .filter(|member| !self.participants.iter().any(|p| p.name == member.name))
+            .map(PartyMember::to_participant)
+            .collect();
+        if newcomers.is_empty() {
+            return;
+        }
+
+        self.participants.extend(newcomers);
+        self.focused_row = Some(self.participants.len() - 1);
+    }
+
+    /// Hide the focused participant from the players, or stop hiding them.
+    pub fn toggle_hidden(&mut self) {
+        if let Some(participant) = self
+            .focused_row
+            .and_then(|row| self.participants.get_mut(row))
+        {
+            participant.hidden = !participant.hidden;
+        }
+    }
+
+    /// Save everyone in the encounter with a level as the party, replacing the saved roster.
+    pub fn save_party(&mut self, party: &mut Party) {
+        let members: Vec<PartyMember> = self
+            .participants
+            .iter()
+            .filter(|p| p.kind == TurnKind::Combatant && p.level.is_some())
+            .map(PartyMember::new)
+            .collect();
+        if members.is_empty() {
+            self.message = Some(String::from(
+                "Give the party's characters a level to save them as the party.",
+            ));
+            return;
+        }
+
+        let count = members.len();
+        self.message = Some(match party.save(members) {
+            Ok(path) => format!("Saved {} party members to {}", count, path.display()),
+            Err(error) => format!("{:#}", error),
+        });
+    }
+
+    pub fn is_prompting(&self) -> bool {
+        self.prompt.is_some()
+    }
+
+    /// Answer whichever question the status line is asking.
+    pub fn submit_prompt(&mut self, workspace: &mut Workspace) {
+        match &self.prompt {
+            Some(EditorPrompt::SaveTemplate { .. }) => self.save_template(&mut workspace.library),
+            Some(EditorPrompt::Import { .. }) => self.import(),
+            Some(EditorPrompt::SaveEncounter { .. }) => {
+                self.save_encounter(&mut workspace.campaign)
+            }
+            None => {}
+        }
+    }
+
+    pub fn is_reporting(&self) -> bool {
+        self.report.is_some()
+    }
+
+    pub fn dismiss_report(&mut self) {
+        self.report = None;
+    }
+
+    pub fn begin_import(&mut self) {
+        self.prompt = Some(EditorPrompt::Import {
+            path: String::new(),
+        });
+        self.message = None;
+    }
+
+    /// Add the participants from the file that was typed in. Nobody is added if any line of
+    /// the file is wrong.
+    fn import(&mut self) {
+        let Some(EditorPrompt::Import { path }) = self.prompt.take() else {
+            return;
+        };
+
+        self.message = Some(match import::import(Path::new(path.trim())) {
+            Ok(imported) => {
+                if !imported.warnings.is_empty() {
+                    self.report = Some(ImportReport::new(path.trim(), &imported));
+                }
+                let count = imported.participants.len();
+                add_participants(&mut self.participants, imported.participants);
+                if count > 0 {
+                    self.focused_row = Some(self.participants.len() - 1);
+                }
+                format!("Imported {} participants from {}", count, path.trim())
+            }
+            Err(error) => format!("{:#}", error),
+        });
+    }
+
+    /// Start typing tags for saving the focused participant to the library. A participant
+    /// that's already in the library starts with its existing tags.
+    pub fn begin_save_template(&mut self, library: &Library) {
+        let Some(participant) = self.focused_participant() else {
+            return;
+        };
+        if participant.kind != TurnKind::Combatant {
+            return;
+        }
+
+        let tags = library
+            .get(&participant.name)
+            .map(|template| template.tags.join(", "))
+            .unwrap_or_default();
+        self.prompt = Some(EditorPrompt::SaveTemplate {
+            name: participant.name.clone(),
+            tags,
+        });
+        self.message = None;
+    }
+
+    /// Save the focused participant to the library with the tags that were typed in.
+    fn save_template(&mut self, library: &mut Library) {
+        let (Some(EditorPrompt::SaveTemplate { tags, .. }), Some(participant)) =
+            (self.prompt.take(), self.focused_participant())
+        else {
+            return;
+        };
+
+        let template = CreatureTemplate::new(participant, library::parse_tags(&tags));
+        let name = template.name.clone();
+        self.message = Some(match library.save(template) {
+            Ok(path) => format!("Saved {} to {}", name, path.display()),
+            Err(error) => format!("{:#}", error),
+        });
+    }
+
+    fn focused_participant(&self) -> Option<&Participant> {
+        self.focused_row.and_then(|row| self.participants.get(row))
+    }
+
+    pub fn toggle_hit_points_mode(&mut self) {
+        self.hit_points_mode = self.hit_points_mode.toggle();
+    }
+
+    /// Add the creature selected in the picker, and keep the picker open to add more.
+    pub fn add_picked_monster(&mut self, library: &Library) {
+        let Some(entry) = self
+            .picker
+            .as_ref()
+            .and_then(|picker| picker.results(library).get(picker.selected).copied())
+        else {
+            return;
+        };
+
+        let participant = match entry {
+            PickerEntry::Template(template) => template.to_participant(
+                &unique_name(&self.participants, &template.name),
+                &mut rand::thread_rng(),
+            ),
+            PickerEntry::Monster(monster) => monster.to_participant(
+                &unique_name(&self.participants, &monster.name),
+                self.hit_points_mode,
+                &mut rand::thread_rng(),
+            ),
+        };
+        self.participants.push(participant);
+        self.focused_row = Some(self.participants.len() - 1);
+    }
+
+    pub fn focus_next_row(&mut self, library: &Library) {
+        if let Some(picker) = self.picker.as_mut() {
+            let results = picker.results(library).len();
+            picker.selected = (picker.selected + 1).min(results.saturating_sub(1));
+            return;
+        }
+
+        if self.participants.is_empty() {
+            return;
+        }
+
+        self.focused_row = Some(match self.focused_row {
+            Some(row) => (row + 1).min(self.participants.len() - 1),
+            None => 0,
+        });
+    }
+
+    pub fn focus_prev_row(&mut self) {
+        if let Some(picker) = self.picker.as_mut() {
+            picker.selected = picker.selected.saturating_sub(1);
+            return;
+        }
+
+        if self.participants.is_empty() {
+            return;
+        }
+
+        self.focused_row = Some(self.focused_row.map_or(0, |row| row.saturating_sub(1)));
+    }
+
+    pub fn focus_next_column(&mut self) {
+        self.focused_col = (self.focused_col + 1).min(self.column_count() - 1);
+    }
+
+    pub fn focus_prev_column(&mut self) {
+        self.focused_col = self.focused_col.saturating_sub(1);
+    }
+
+    /// Append a new participant, focus its name and start editing it. The name can be written
+    /// like `Orc x3 +1 hp:15`, to fill in the rest of the row or add a whole group.
+    pub fn add_participant(&mut self) {
+        let number = self.participants.len() + 1;
+        self.participants
+            .push(Participant::new(&format!("Participant {}", number)));
+
+        self.focused_row = Some(self.participants.len() - 1);
+        self.focused_col = 0;
+        self.input = Some(String::new());
+        self.adding = true;
+    }
+
+    /// Replace the participant being added with what was typed in. Input that can't be parsed
+    /// stays in the cell, with the reason in the status message.
+    fn commit_quick_entry(&mut self, row: usize, input: &str) {
+        let entry = match input.parse::<QuickEntry>() {
+            Ok(entry) => entry,
+            Err(error) => {
+                self.message = Some(format!("Couldn't add {}: {}", input, error));
+                return;
+            }
+        };
+
+        // Each member is named as they're added, so they're numbered after anyone already here
+        self.participants.remove(row);
+        for (offset, mut participant) in entry.participants().into_iter().enumerate() {
+            participant.name = unique_name(&self.participants, &participant.name);
+            self.participants.insert(row + offset, participant);
+        }
+
+        self.focused_row = Some(row + entry.count as usize - 1);
+        self.input = None;
+        self.adding = false;
+        self.message = None;
+    }
+
+    pub fn remove_focused_participant(&mut self) {
+        let Some(row) = self.focused_row else {
+            return;
+        };
+
+        self.participants.remove(row);
+        self.focused_row = match self.participants.len() {
+            0 => None,
+            len => Some(row.min(len - 1)),
+        };
+        self.clamp_focused_col();
+    }
+
+    /// Give the focused participant another initiative slot, and focus it.
+    pub fn add_initiative_slot(&mut self) {
+        let Some(participant) = self
+            .focused_row
+            .and_then(|row| self.participants.get_mut(row))
+        else {
+            return;
+        };
+
+        participant.add_initiative_slot();
+        let slot = participant.initiative_rolls.len() - 1;
+        self.focus_column(ParticipantColumn::InitiativeRoll(slot));
+    }
+
+    /// Remove the focused initiative slot from the focused participant, or their last slot if
+    /// the name column is focused. A participant always keeps at least one slot.
+    pub fn remove_initiative_slot(&mut self) {
+        let focused_column = self.focused_column();
+        let Some(participant) = self
+            .focused_row
+            .and_then(|row| self.participants.get_mut(row))
+        else {
+            return;
+        };
+
+        let slot = match focused_column {
+            ParticipantColumn::InitiativeRoll(slot) => slot,
+            _ => participant.initiative_rolls.len().saturating_sub(1),
+        };
+
+        participant.remove_initiative_slot(slot);
+        self.clamp_focused_col();
+    }
+
+    /// Start editing the focused cell, or commit the edit if one is in progress.
+    pub fn toggle_editing(&mut self) {
+        if self.input.is_some() {
+            self.commit_input();
+            return;
+        }
+
+        let column = self.focused_column();
+        let Some(participant) = self.focused_row.and_then(|row| self.participants.get(row)) else {
+            return;
+        };
+
+        // A lair action has nothing but its name and initiative count to edit
+        if participant.kind == TurnKind::LairAction
+            && !matches!(
+                column,
+                ParticipantColumn::Name | ParticipantColumn::InitiativeRoll(_)
+            )
+        {
+            return;
+        }
+
+        self.input = match column {
+            ParticipantColumn::Name => Some(participant.name.clone()),
+            ParticipantColumn::Side => Some(
+                participant
+                    .side
+                    .map(|side| side.to_string())
+                    .unwrap_or_default(),
+            ),
+            ParticipantColumn::HitPoints => Some(
+                participant
+                    .hit_points
+                    .map(|hp| hp.to_string())
+                    .unwrap_or_default(),
+            ),
+            ParticipantColumn::ArmorClass => Some(
+                participant
+                    .armor_class
+                    .map(|ac| ac.to_string())
+                    .unwrap_or_default(),
+            ),
+            ParticipantColumn::InitiativeModifier => {
+                Some(participant.initiative_modifier.to_string())
+            }
+            ParticipantColumn::Level => Some(
+                participant
+                    .level
+                    .map(|level| level.to_string())
+                    .unwrap_or_default(),
+            ),
+            ParticipantColumn::ChallengeRating => Some(
+                participant
+                    .challenge_rating
+                    .map(|rating| rating.to_string())
+                    .unwrap_or_default(),
+            ),
+            ParticipantColumn::LegendaryActions => Some(participant.legendary_actions.to_string()),
+            ParticipantColumn::InitiativeRoll(slot) => participant
+                .initiative_rolls
+                .get(slot)
+                .map(|roll| roll.map(|r| r.to_string()).unwrap_or_default()),
+        };
+    }
+
+    /// Add a lair action to the encounter, unless it already has one.
+    pub fn add_lair_action(&mut self) {
+        if self
+            .participants
+            .iter()
+            .any(|p| p.kind == TurnKind::LairAction)
+        {
+            return;
+        }
+
+        self.participants.push(Participant::lair_action());
+        self.focused_row = Some(self.participants.len() - 1);
+    }
+
+    pub fn cycle_initiative_system(&mut self) {
+        self.initiative_system = self.initiative_system.cycle();
+    }
+
+    /// Step through the preset turn time limits, then back to no limit.
+    pub fn cycle_turn_time_limit(&mut self) {
+        let index = TURN_TIME_LIMITS
+            .iter()
+            .position(|limit| *limit == self.turn_time_limit)
+            .unwrap_or(0);
+        self.turn_time_limit = TURN_TIME_LIMITS[(index + 1) % TURN_TIME_LIMITS.len()];
+    }
+
+    pub fn cancel_editing(&mut self) {
+        self.input = None;
+        self.adding = false;
+        self.picker = None;
+        self.prompt = None;
+    }
+
+    pub fn input_char(&mut self, c: char) {
+        if let Some(picker) = self.picker.as_mut() {
+            picker.query.push(c);
+            picker.selected = 0;
+        } else if let Some(prompt) = self.prompt.as_mut() {
+            prompt.input_mut().push(c);
+        } else if let Some(input) = self.input.as_mut() {
+            input.push(c);
+        }
+    }
+
+    pub fn input_backspace(&mut self) {
+        if let Some(picker) = self.picker.as_mut() {
+            picker.query.pop();
+            picker.selected = 0;
+        } else if let Some(prompt) = self.prompt.as_mut() {
+            prompt.input_mut().pop();
+        } else if let Some(input) = self.input.as_mut() {
+            input.pop();
+        }
+    }
+
+    /// Write the input into the focused cell. Input that isn't valid for the cell leaves the
+    /// cell in editing mode so it can be corrected.
+    fn commit_input(&mut self) {
+        let column = self.focused_column();
+        if let (true, ParticipantColumn::Name, Some(row), Some(input)) =
+            (self.adding, column, self.focused_row, &self.input)
+        {
+            let input = input.trim().to_string();
+            if !input.is_empty() {
+                self.commit_quick_entry(row, &input);
+                return;
+            }
+        }
+
+        let (Some(input), Some(participant)) = (
+            self.input.as_ref(),
+            self.focused_row
+                .and_then(|row| self.participants.get_mut(row)),
+        ) else {
+            self.input = None;
+            return;
+        };
+
+        let input = input.trim();
+        match column {
+            // Combat tells participants apart by name, so a name someone else has is numbered.
+            // The participant's own name is cleared first so keeping it doesn't renumber it.
+            ParticipantColumn::Name => {
+                if !input.is_empty() {
+                    participant.name.clear();
+                    let name = unique_name(&self.participants, input);
+                    if let Some(participant) = self
+                        .focused_row
+                        .and_then(|row| self.participants.get_mut(row))
+                    {
+                        participant.name = name;
+                    }
+                }
+            }
+            ParticipantColumn::Side => match input {
+                "" => participant.side = None,
+                value => match value.parse::<Side>() {
+                    Ok(side) => participant.side = Some(side),
+                    Err(_) => return,
+                },
+            },
+            // Setting the maximum also restores the participant to full health
+            ParticipantColumn::HitPoints => match input {
+                "" => {
+                    participant.hit_points = None;
+                    participant.current_hit_points = None;
+                }
+                value => match value.parse::<u16>() {
+                    Ok(hit_points) => {
+                        participant.hit_points = Some(hit_points);
+                        participant.current_hit_points = None;
+                    }
+                    Err(_) => return,
+                },
+            },
+            ParticipantColumn::ArmorClass => match input {
+                "" => participant.armor_class = None,
+                value => match value.parse::<u8>() {
+                    Ok(armor_class) => participant.armor_class = Some(armor_class),
+                    Err(_) => return,
+                },
+            },
+            ParticipantColumn::InitiativeModifier => match input {
+                "" => participant.initiative_modifier = 0,
+                value => match value.trim_start_matches('+').parse::<i8>() {
+                    Ok(modifier) => participant.initiative_modifier = modifier,
+                    Err(_) => return,
+                },
+            },
+            ParticipantColumn::Level => match input {
+                "" => participant.level = None,
+                value => match value.parse::<u8>() {
+                    Ok(level) if (1..=MAX_LEVEL).contains(&level) => {
+                        participant.level = Some(level)
+                    }
+                    _ => return,
+                },
+            },
+            ParticipantColumn::ChallengeRating => match input {
+                "" => participant.challenge_rating = None,
+                value => match value.parse::<ChallengeRating>() {
+                    Ok(rating) => participant.challenge_rating = Some(rating),
+                    Err(_) => return,
+                },
+            },
+            ParticipantColumn::LegendaryActions => match input {
+                "" => participant.legendary_actions = 0,
+                value => match value.parse::<u8>() {
+                    Ok(count) => participant.legendary_actions = count,
+                    Err(_) => return,
+                },
+            },
+            ParticipantColumn::InitiativeRoll(slot) => {
+                let roll = match input {
+                    "" => None,
+                    value => match value.parse::<u8>() {
+                        Ok(roll) => Some(roll),
+                        Err(_) => return,
+                    },
+                };
+                participant.set_initiative_roll(slot, roll);
+            }
+        }
+
+        self.input = None;
+        self.adding = false;
+    }
+
+    fn clamp_focused_col(&mut self) {
+        self.focused_col = self.focused_col.min(self.column_count() - 1);
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RunningCombatState {
+    /// The participants as they were set up in the editor, so the encounter can be returned to.
+    pub participants: Vec<Participant>,
+    /// The name the encounter is saved under in the campaign, if it was saved.
+    pub saved_as: Option<String>,
+    pub initiative_system: InitiativeSystemKind,
+    pub turns: Vec<CombatTurn>,
+    /// Index into `turns` of the combatant who is currently acting.
+    pub current_turn: usize,
+    pub round: u32,
+    /// Index of the focused row. Rows past the end of `turns` are the delayed combatants.
+    pub focused_row: usize,
+    /// Combatants who have delayed their turn and not yet chosen to act.
+    pub delayed: Vec<CombatTurn>,
+    pub log: Vec<CombatLogEntry>,
+    pub prompt: Option<CombatPrompt>,
+    pub legendary_actions: Vec<LegendaryActions>,
+    pub combatants: Vec<Combatant>,
+    /// Real time spent on the current turn.
+    pub turn_timer: Stopwatch,
+    pub turn_time_limit: Option<Duration>,
+    /// How many entries the log panel is scrolled back from the newest.
+    pub log_scroll: usize,
+    /// Set once the end of combat has been confirmed.
+    pub finished: bool,
+}
+
+impl RunningCombatState {
+    pub const DEFAULT_EXPORT_PATH: &'static str = "initiative.md";
+
+    /// Order the participants' turns using the encounter's initiative system. Returns `None` if
+    /// nobody has rolled initiative, since there would be no turns to take.
+    pub fn new(encounter: &EditingEncounterState) -> Option<Self> {
+        let turns = encounter
+            .initiative_system
+            .system()
+            .order_turns(&encounter.participants);
+
+        if turns.is_empty() {
+            return None;
+        }
+
+        let mut state = RunningCombatState {
+            participants: encounter.participants.clone(),
+            saved_as: encounter.saved_as.clone(),
+            initiative_system: encounter.initiative_system,
+            turns,
+            current_turn: 0,
+            round: 1,
+            focused_row: 0,
+            delayed: vec![],
+            log: vec![],
+            prompt: None,
+            legendary_actions: encounter
+                .participants
+                .iter()
+                .filter(|p| p.kind == TurnKind::Combatant && p.legendary_actions > 0)
+                .map(|p| LegendaryActions {
+                    name: p.name.clone(),
+                    max: p.legendary_actions,
+                    remaining: p.legendary_actions,
+                })
+                .collect(),
+            combatants: encounter
+                .participants
+                .iter()
+                .filter(|p| p.kind == TurnKind::Combatant)
+                .map(|p| Combatant {
+                    name: p.name.clone(),
+                    hit_points: p.hit_points.map(|max| HitPoints {
+                        current: p.current_hit_points.unwrap_or(max).min(max),
+                        max,
+                    }),
+                    concentration: None,
+                    time_spent: Stopwatch::default(),
+                    conditions: vec![],
+                    reaction_used: false,
+                    reaction_round: None,
+                })
+                .collect(),
+            turn_timer: Stopwatch::default(),
+            turn_time_limit: encounter.turn_time_limit,
+            log_scroll: 0,
+            finished: false,
+        };
+        // Anyone who was knocked out in an earlier encounter is still down
+        let downed: Vec<String> = state
+            .combatants
+            .iter()
+            .filter(|c| c.hit_points.is_some_and(|hp| hp.current == 0))
+            .map(|c| c.name.clone())
+            .collect();
+        for name in downed {
+            state.set_unconscious(&name, true);
+        }
+
+        state.log(CombatEvent::RoundStarted);
+        state.log(CombatEvent::TurnStarted {
+            name: state.current().name.clone(),
+        });
+        state.restore_reaction();
+        state.focus_next_candidate();
+
+        Some(state)
+    }
+
+    pub fn current(&self) -> &CombatTurn {
+        &self.turns[self.current_turn]
+    }
+
+    pub fn row_count(&self) -> usize {
+        self.turns.len() + self.delayed.len()
+    }
+
+    /// Advance the clocks by one frame. Time is counted against the current combatant.
+    pub fn tick(&mut self) {
+        self.turn_timer.tick();
+
+        let name = self.current().name.clone();
+        if let Some(combatant) = self.combatant_mut(&name) {
+            combatant.time_spent.tick();
+        }
+    }
+
+    /// How close the current turn is to its time limit. Lair actions aren't timed.
+    pub fn turn_timer_status(&self) -> Option<TimerStatus> {
+        match self.current().kind {
+            TurnKind::Combatant => self
+                .turn_time_limit
+                .map(|limit| self.turn_timer.status(limit)),
+            TurnKind::LairAction => None,
+        }
+    }
+
+    /// How many turns are still to come in this round, after the current one.
+    pub fn turns_remaining_in_round(&self) -> usize {
+        self.turns.len() - self.current_turn - 1
+    }
+
+    /// The in-game time that has passed since combat started, as of the start of this round.
+    pub fn elapsed(&self) -> GameTime {
+        GameTime::from_rounds(self.round - 1)
+    }
+
+    /// The delayed combatant in the focused row, if the focus is on one.
+    pub fn focused_delayed(&self) -> Option<&CombatTurn> {
+        self.focused_row
+            .checked_sub(self.turns.len())
+            .and_then(|index| self.delayed.get(index))
+    }
+
+    /// The turn in the focused row, whether it's in the order or delayed.
+    pub fn focused_turn(&self) -> Option<&CombatTurn> {
+        self.turns
+            .get(self.focused_row)
+            .or_else(|| self.focused_delayed())
+    }
+
+    pub fn combatant(&self, name: &str) -> Option<&Combatant> {
+        self.combatants.iter().find(|c| c.name == name)
+    }
+
+    fn combatant_mut(&mut self, name: &str) -> Option<&mut Combatant> {
+        self.combatants.iter_mut().find(|c| c.name == name)
+    }
+
+    pub fn focus_next_row(&mut self) {
+        self.focused_row = (self.focused_row + 1).min(self.row_count() - 1);
+    }
+
+    pub fn focus_prev_row(&mut self) {
+        self.focused_row = self.focused_row.saturating_sub(1);
+    }
+
+    /// Whether the turn at `index` could be the next one taken. Delayed combatants are never
+    /// picked, since they come back in by acting now instead.
+    pub fn is_next_turn_candidate(&self, index: usize) -> bool {
+        if index >= self.turns.len() {
+            return false;
+        }
+        if !self
+            .initiative_system
+            .system()
+            .active_combatant_picks_next()
+        {
+            return index == (self.current_turn + 1) % self.turns.len();
+        }
+
+        // Whoever acts last in a round picks anyone to start the next one, even themselves.
+        self.is_last_turn_of_round() || index > self.current_turn
+    }
+
+    /// End the current turn and start the next one, moving to the next round after the last
+    /// turn. When the active combatant picks who goes next, the focused turn is the one picked.
+    ///
+    /// Legendary creatures get the chance to spend a legendary action in between.
+    pub fn next_turn(&mut self) {
+        let picks_next = self
+            .initiative_system
+            .system()
+            .active_combatant_picks_next();
+        if picks_next && !self.is_next_turn_candidate(self.focused_row) {
+            return;
+        }
+
+        self.ask_about_legendary_actions(self.legendary_action_takers());
+    }
+
+    /// Who could take a legendary action now, at the end of the current turn. Legendary actions
+    /// can only be taken at the end of another creature's turn.
+    pub fn legendary_action_takers(&self) -> Vec<String> {
+        let current = self.current();
+        if current.kind != TurnKind::Combatant {
+            return vec![];
+        }
+
+        self.legendary_actions
+            .iter()
+            .filter(|pool| pool.remaining > 0)
+            .filter(|pool| pool.name != current.name)
+            .filter(|pool| !self.is_down(&pool.name))
+            .map(|pool| pool.name.clone())
+            .collect()
+    }
+
+    /// Whether every turn belonging to `name` is unconscious or dead.
+    pub fn is_down(&self, name: &str) -> bool {
+        let mut turns = self
+            .turns
+            .iter()
+            .filter(|turn| turn.name == name)
+            .peekable();
+        turns.peek().is_some() && turns.all(|turn| turn.unconscious || turn.dead)
+    }
+
+    /// Ask whether the first of `names` takes a legendary action, or move on to the next turn
+    /// if there's nobody left to ask.
+    fn ask_about_legendary_actions(&mut self, names: Vec<String>) {
+        if names.is_empty() {
+            self.advance_turn();
+        } else {
+            self.prompt = Some(CombatPrompt::SpendLegendaryAction { names });
+        }
+    }
+
+    fn advance_turn(&mut self) {
+        if self
+            .initiative_system
+            .system()
+            .active_combatant_picks_next()
+        {
+            // Keep the turns that have been taken this round at the front of the list.
+            let starts_new_round = self.is_last_turn_of_round();
+            let picked = self.turns.remove(self.focused_row);
+            if starts_new_round {
+                self.turns.insert(0, picked);
+            } else {
+                self.turns.insert(self.current_turn + 1, picked);
+            }
+        }
+
+        if self.is_last_turn_of_round() {
+            self.current_turn = 0;
+            self.start_round();
+        } else {
+            self.current_turn += 1;
+        }
+
+        self.start_turn();
+    }
+
+    /// Take the current combatant out of the turn order until they choose to re-enter it.
+    /// The last combatant left in the order can't delay, since nobody else could act.
+    pub fn delay_current_turn(&mut self) {
+        if self.turns.len() <= 1 || self.current().kind != TurnKind::Combatant {
+            return;
+        }
+
+        let delayed = self.turns.remove(self.current_turn);
+        self.log(CombatEvent::Delayed {
+            name: delayed.name.clone(),
+        });
+        self.delayed.push(delayed);
+
+        // Whoever was after the delayed combatant is now at the same index.
+        if self.current_turn == self.turns.len() {
+            self.current_turn = 0;
+            self.start_round();
+        }
+
+        self.start_turn();
+    }
+
+    /// Bring the focused delayed combatant back into the order, acting right now before the
+    /// current combatant. They keep this new place in the order for the rest of combat.
+    pub fn re_enter_focused_delayed(&mut self) {
+        let Some(index) = self
+            .focused_row
+            .checked_sub(self.turns.len())
+            .filter(|index| *index < self.delayed.len())
+        else {
+            return;
+        };
+
+        let mut turn = self.delayed.remove(index);
+        let before = self.current();
+        turn.initiative_roll_value = before.initiative_roll_value;
+
+        self.log(CombatEvent::ReEntered {
+            name: turn.name.clone(),
+            before: before.name.clone(),
+        });
+        self.turns.insert(self.current_turn, turn);
+
+        self.start_turn();
+    }
+
+    /// Ask what the current combatant is readying their action for.
+    pub fn begin_ready(&mut self) {
+        if self.current().kind != TurnKind::Combatant {
+            return;
+        }
+
+        self.prompt = Some(CombatPrompt::ReadyTrigger {
+            input: String::new(),
+        });
+    }
+
+    /// Use the readied action of the combatant in the focused row, interrupting the turn.
+    pub fn trigger_focused_readied_action(&mut self) {
+        let Some(turn) = self.turns.get_mut(self.focused_row) else {
+            return;
+        };
+        let Some(trigger) = turn.readied.take() else {
+            return;
+        };
+
+        let name = turn.name.clone();
+        self.log(CombatEvent::ReadiedActionTriggered { name, trigger });
+    }
+
+    /// Mark part of the focused combatant's turn as used, or as available again.
+    pub fn toggle_focused_economy(&mut self, resource: EconomyResource) {
+        let Some(turn) = self
+            .turns
+            .get_mut(self.focused_row)
+            .filter(|turn| turn.kind == TurnKind::Combatant)
+        else {
+            return;
+        };
+
+        if resource != EconomyResource::Reaction {
+            turn.economy.toggle(resource);
+            return;
+        }
+        let name = turn.name.clone();
+        if let Some(combatant) = self.combatant_mut(&name) {
+            combatant.reaction_used = !combatant.reaction_used;
+        }
+    }
+
+    /// What's been used of `turn`, with the reaction of the creature taking it.
+    pub fn economy(&self, turn: &CombatTurn) -> ActionEconomy {
+        ActionEconomy {
+            reaction_used: self
+                .combatant(&turn.name)
+                .is_some_and(|combatant| combatant.reaction_used),
+            ..turn.economy
+        }
+    }
+
+    /// Ask how much damage the focused combatant takes.
+    pub fn begin_damage(&mut self) {
+        if let Some(name) = self.focused_combatant_name() {
+            self.prompt = Some(CombatPrompt::Damage {
+                name,
+                input: String::new(),
+            });
+        }
+    }
+
+    /// Ask how much the focused combatant is healed by.
+    pub fn begin_heal(&mut self) {
+        if let Some(name) = self.focused_combatant_name() {
+            self.prompt = Some(CombatPrompt::Heal {
+                name,
+                input: String::new(),
+            });
+        }
+    }
+
+    /// Ask what the focused combatant is concentrating on, or stop them concentrating if they
+    /// already are.
+    pub fn toggle_focused_concentration(&mut self) {
+        let Some(name) = self.focused_combatant_name() else {
+            return;
+        };
+
+        match self
+            .combatant_mut(&name)
+            .and_then(|combatant| combatant.concentration.take())
+        {
+            Some(concentration) => self.log(CombatEvent::ConcentrationEnded {
+                name,
+                spell: concentration.spell,
+            }),
+            None => {
+                self.prompt = Some(CombatPrompt::Concentration {
+                    name,
+                    input: String::new(),
+                })
+            }
+        }
+    }
+
+    /// Take `amount` damage off the named combatant's hit points, knocking them out at zero.
+    /// A combatant who is concentrating has to make a Constitution save to keep it up, unless
+    /// they're down, which ends concentration outright.
+    pub fn damage(&mut self, name: &str, amount: u16) {
+        let Some(combatant) = self.combatant_mut(name) else {
+            return;
+        };
+
+        let knocked_out = match combatant.hit_points.as_mut() {
+            Some(hit_points) if hit_points.current > 0 => {
+                hit_points.current = hit_points.current.saturating_sub(amount);
+                hit_points.current == 0
+            }
+            _ => false,
+        };
+        let down = combatant.hit_points.is_some_and(|hp| hp.current == 0);
+        let concentration = match down {
+            true => combatant.concentration.take(),
+            false => None,
+        };
+        let concentrating = combatant.concentration.is_some();
+
+        self.log(CombatEvent::Damaged {
+            name: name.to_string(),
+            amount,
+        });
+
+        if knocked_out {
+            self.set_unconscious(name, true);
+            self.log(CombatEvent::KnockedOut {
+                name: name.to_string(),
+            });
+        }
+
+        if let Some(concentration) = concentration {
+            self.log(CombatEvent::ConcentrationBroken {
+                name: name.to_string(),
+                spell: concentration.spell,
+            });
+        }
+        if concentrating && amount > 0 {
+            self.prompt = Some(CombatPrompt::ConcentrationSave {
+                name: name.to_string(),
+                dc: Concentration::save_dc(amount),
+                input: String::new(),
+            });
+        }
+    }
+
+    /// Restore up to `amount` of the named combatant's hit points, waking them if they were
+    /// knocked out.
+    pub fn heal(&mut self, name: &str, amount: u16) {
+        let Some(combatant) = self.combatant_mut(name) else {
+            return;
+        };
+
+        let woken = match combatant.hit_points.as_mut() {
+            Some(hit_points) => {
+                let was_down = hit_points.current == 0;
+                hit_points.current = hit_points
+                    .current
+                    .saturating_add(amount)
+                    .min(hit_points.max);
+                was_down && hit_points.current > 0
+            }
+            None => false,
+        };
+
+        self.log(CombatEvent::Healed {
+            name: name.to_string(),
+            amount,
+        });
+
+        if woken {
+            self.set_unconscious(name, false);
+        }
+    }
+
+    /// Record the result of a concentration save, ending concentration if it failed.
+    pub fn concentration_save(&mut self, name: &str, dc: u16, total: i32) {
+        self.log(CombatEvent::ConcentrationSave {
+            name: name.to_string(),
+            dc,
+            total,
+        });
+
+        if total >= dc as i32 {
+            return;
+        }
+
+        if let Some(concentration) = self
+            .combatant_mut(name)
+            .and_then(|combatant| combatant.concentration.take())
+        {
+            self.log(CombatEvent::ConcentrationBroken {
+                name: name.to_string(),
+                spell: concentration.spell,
+            });
+        }
+    }
+
+    pub fn submit_prompt(&mut self) {
+        let Some(prompt) = self.prompt.take() else {
+            return;
+        };
+
+        match prompt {
+            CombatPrompt::ReadyTrigger { input } => {
+                let trigger = input.trim().to_string();
+                if trigger.is_empty() {
+                    self.prompt = Some(CombatPrompt::ReadyTrigger { input });
+                    return;
+                }
+
+                let turn = &mut self.turns[self.current_turn];
+                turn.readied = Some(trigger.clone());
+
+                let name = turn.name.clone();
+                self.log(CombatEvent::Readied { name, trigger });
+                self.next_turn();
+            }
+            CombatPrompt::SpendLegendaryAction { mut names } => {
+                let name = names.remove(0);
+                if let Some(pool) = self
+                    .legendary_actions
+                    .iter_mut()
+                    .find(|pool| pool.name == name)
+                {
+                    pool.remaining = pool.remaining.saturating_sub(1);
+
+                    let remaining = pool.remaining;
+                    self.log(CombatEvent::LegendaryActionSpent { name, remaining });
+                }
+
+                self.ask_about_legendary_actions(names);
+            }
+            CombatPrompt::Damage { name, input } => match input.trim().parse::<u16>() {
+                Ok(amount) => self.damage(&name, amount),
+                Err(_) => self.prompt = Some(CombatPrompt::Damage { name, input }),
+            },
+            CombatPrompt::Heal { name, input } => match input.trim().parse::<u16>() {
+                Ok(amount) => self.heal(&name, amount),
+                Err(_) => self.prompt = Some(CombatPrompt::Heal { name, input }),
+            },
+            CombatPrompt::Concentration { name, input } => match input.parse::<Concentration>() {
+                Ok(concentration) => {
+                    let concentration = Concentration {
+                        started_round: self.round,
+                        ..concentration
+                    };
+                    let spell = concentration.spell.clone();
+                    if let Some(combatant) = self.combatant_mut(&name) {
+                        combatant.concentration = Some(concentration);
+                    }
+                    self.log(CombatEvent::ConcentrationStarted { name, spell });
+                }
+                Err(_) => self.prompt = Some(CombatPrompt::Concentration { name, input }),
+            },
+            CombatPrompt::ConcentrationSave { name, dc, input } => {
+                // A modifier, or nothing at all, means the save is rolled here
+                let total = match input.trim() {
+                    "" => Ok(DiceExpression::D20.roll(&mut rand::thread_rng())),
+                    modifier if modifier.starts_with(['+', '-']) => modifier
+                        .trim_start_matches('+')
+                        .parse::<i32>()
+                        .map(|modifier| {
+                            DiceExpression::D20
+                                .with_modifier(modifier)
+                                .roll(&mut rand::thread_rng())
+                        }),
+                    total => total.parse::<i32>(),
+                };
+
+                match total {
+                    Ok(total) => self.concentration_save(&name, dc, total),
+                    Err(_) => {
+                        self.prompt = Some(CombatPrompt::ConcentrationSave { name, dc, input })
+                    }
+                }
+            }
+            CombatPrompt::Condition { name, input } => match input.trim() {
+                "" => self.prompt = Some(CombatPrompt::Condition { name, input }),
+                condition => self.toggle_condition(&name, condition),
+            },
+            CombatPrompt::EndCombat { input, .. } => match input.trim() {
+                "" => self.finished = true,
+                path => match self.save_transcript(Path::new(path)) {
+                    Ok(()) => self.finished = true,
+                    Err(error) => {
+                        self.prompt = Some(CombatPrompt::EndCombat {
+                            input,
+                            error: Some(error.to_string()),
+                        })
+                    }
+                },
+            },
+            CombatPrompt::ExportOrder { input, .. } => {
+                if let Err(error) = self.export_order(Path::new(input.trim())) {
+                    self.prompt = Some(CombatPrompt::ExportOrder {
+                        input,
+                        error: Some(error.to_string()),
+                    })
+                }
+            }
+        }
+    }
+
+    /// Ask where to export the initiative order.
+    pub fn begin_export(&mut self) {
+        self.prompt = Some(CombatPrompt::ExportOrder {
+            input: String::from(Self::DEFAULT_EXPORT_PATH),
+            error: None,
+        });
+    }
+
+    /// Write the initiative order to `path`, in the format its extension suggests.
+    pub fn export_order(&self, path: &Path) -> Result<()> {
+        let order = ExportedOrder::new(self).render(ExportFormat::from_path(path));
+        std::fs::write(path, order).with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Ask whether to save a transcript, before ending combat.
+    pub fn begin_end_combat(&mut self) {
+        self.prompt = Some(CombatPrompt::EndCombat {
+            input: String::new(),
+            error: None,
+        });
+    }
+
+    /// Write the combat log to `path`, as Markdown or plain text depending on its extension.
+    pub fn save_transcript(&self, path: &Path) -> Result<()> {
+        let transcript = transcript(
+            &self.log,
+            TranscriptFormat::from_path(path),
+            &self.hidden_names(),
+        );
+        std::fs::write(path, transcript)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Ask for a condition to apply to, or remove from, the focused combatant.
+    pub fn begin_condition(&mut self) {
+        if let Some(name) = self.focused_combatant_name() {
+            self.prompt = Some(CombatPrompt::Condition {
+                name,
+                input: String::new(),
+            });
+        }
+    }
+
+    /// Apply `condition` to the named combatant, or remove it if they already have it.
+    /// Conditions are matched ignoring case.
+    pub fn toggle_condition(&mut self, name: &str, condition: &str) {
+        let Some(combatant) = self.combatant_mut(name) else {
+            return;
+        };
+
+        let event = match combatant
+            .conditions
+            .iter()
+            .position(|existing| existing.eq_ignore_ascii_case(condition))
+        {
+            Some(index) => CombatEvent::ConditionRemoved {
+                name: name.to_string(),
+                condition: combatant.conditions.remove(index),
+            },
+            None => {
+                combatant.conditions.push(condition.to_string());
+                CombatEvent::ConditionApplied {
+                    name: name.to_string(),
+                    condition: condition.to_string(),
+                }
+            }
+        };
+
+        self.log(event);
+    }
+
+    /// Mark the focused combatant as dead, or undo it if they already are.
+    pub fn toggle_focused_dead(&mut self) {
+        let Some(name) = self.focused_combatant_name() else {
+            return;
+        };
+
+        let dead = !self.focused_turn().is_some_and(|turn| turn.dead);
+        for turn in self.turns.iter_mut().chain(self.delayed.iter_mut()) {
+            if turn.name == name {
+                turn.dead = dead;
+            }
+        }
+
+        match dead {
+            true => self.log(CombatEvent::Died { name }),
+            false => self.log(CombatEvent::Revived { name }),
+        }
+    }
+
+    /// Everyone the players still don't know about.
+    pub fn hidden_names(&self) -> Vec<String> {
+        self.participants
+            .iter()
+            .filter(|participant| participant.hidden)
+            .map(|participant| participant.name.clone())
+            .collect()
+    }
+
+    /// Let the players see the focused hidden participant. Their turns were in the order all
+    /// along, so they show up wherever their initiative puts them.
+    pub fn reveal_focused(&mut self) {
+        let Some(name) = self
+            .focused_turn()
+            .filter(|turn| turn.hidden)
+            .map(|turn| turn.name.clone())
+        else {
+            return;
+        };
+
+        for turn in self.turns.iter_mut().chain(self.delayed.iter_mut()) {
+            if turn.name == name {
+                turn.hidden = false;
+            }
+        }
+        if let Some(participant) = self.participants.iter_mut().find(|p| p.name == name) {
+            participant.hidden = false;
+        }
+
+        self.log(CombatEvent::Revealed { name });
+    }
+
+    /// Scroll the log panel back towards older entries, keeping at least one in view.
+    pub fn scroll_log_back(&mut self, entries: usize) {
+        self.log_scroll = (self.log_scroll + entries).min(self.log.len().saturating_sub(1));
+    }
+
+    pub fn scroll_log_forward(&mut self, entries: usize) {
+        self.log_scroll = self.log_scroll.saturating_sub(entries);
+    }
+
+    /// Dismiss the prompt. Declining a legendary action moves on to the next creature's.
+    pub fn cancel_prompt(&mut self) {
+        if let Some(CombatPrompt::SpendLegendaryAction { mut names }) = self.prompt.take() {
+            names.remove(0);
+            self.ask_about_legendary_actions(names);
+        }
+    }
+
+    pub fn input_char(&mut self, c: char) {
+        if let Some(input) = self.prompt.as_mut().and_then(CombatPrompt::input_mut) {
+            input.push(c);
+        }
+    }
+
+    pub fn input_backspace(&mut self) {
+        if let Some(input) = self.prompt.as_mut().and_then(CombatPrompt::input_mut) {
+            input.pop();
+        }
+    }
+
+    /// Bookkeeping for the start of the current combatant's turn.
+    fn start_turn(&mut self) {
+        self.log(CombatEvent::TurnStarted {
+            name: self.current().name.clone(),
+        });
+
+        // A readied action that wasn't used is lost when the combatant's next turn comes around.
+        let turn = &mut self.turns[self.current_turn];
+        if turn.readied.take().is_some() {
+            let name = turn.name.clone();
+            self.log(CombatEvent::ReadiedActionExpired { name });
+        }
+
+        self.turns[self.current_turn].economy.reset();
+        self.restore_reaction();
+        self.turn_timer = Stopwatch::default();
+
+        let name = &self.turns[self.current_turn].name;
+        for pool in self.legendary_actions.iter_mut() {
+            if pool.name == *name {
+                pool.remaining = pool.max;
+            }
+        }
+
+        self.focus_next_candidate();
+    }
+
+    /// Give the current creature its reaction back, if this is its first turn of the round.
+    fn restore_reaction(&mut self) {
+        let round = self.round;
+        let name = self.current().name.clone();
+        if let Some(combatant) = self
+            .combatant_mut(&name)
+            .filter(|combatant| combatant.reaction_round != Some(round))
+        {
+            combatant.reaction_used = false;
+            combatant.reaction_round = Some(round);
+        }
+    }
+
+    /// Bookkeeping for the start of a new round.
+    fn start_round(&mut self) {
+        self.round += 1;
+        self.log(CombatEvent::RoundStarted);
+
+        // Spells with a duration end once enough rounds have passed
+        let round = self.round;
+        let mut expired = Vec::new();
+        for combatant in self.combatants.iter_mut() {
+            if combatant
+                .concentration
+                .as_ref()
+                .and_then(|concentration| concentration.rounds_remaining(round))
+                == Some(0)
+            {
+                if let Some(concentration) = combatant.concentration.take() {
+                    expired.push((combatant.name.clone(), concentration.spell));
+                }
+            }
+        }
+
+        for (name, spell) in expired {
+            self.log(CombatEvent::ConcentrationEnded { name, spell });
+        }
+    }
+
+    fn set_unconscious(&mut self, name: &str, unconscious: bool) {
+        for turn in self.turns.iter_mut().chain(self.delayed.iter_mut()) {
+            if turn.name == name {
+                turn.unconscious = unconscious;
+            }
+        }
+    }
+
+    /// The name of the creature in the focused row, unless it's the lair.
+    fn focused_combatant_name(&self) -> Option<String> {
+        self.focused_turn()
+            .filter(|turn| turn.kind == TurnKind::Combatant)
+            .map(|turn| turn.name.clone())
+    }
+
+    fn log(&mut self, event: CombatEvent) {
+        self.log.push(CombatLogEntry {
+            round: self.round,
+            event,
+        });
+    }
+
+    /// The encounter combat was started from, to go back to editing once it's over. Anyone who
+    /// died is left out, and everyone else keeps the hit points they have left, as do the
+    /// characters in `party`.
+    pub fn end_combat(&self, party: &mut Party) -> EditingEncounterState {
+        for combatant in &self.combatants {
+            party.set_current_hit_points(&combatant.name, self.hit_points_left(&combatant.name));
+        }
+
+        EditingEncounterState {
+            participants: self
+                .participants
+                .iter()
+                .filter(|participant| !self.is_dead(&participant.name))
+                .map(|participant| {
+                    let mut participant = participant.clone();
+                    participant.current_hit_points = self.hit_points_left(&participant.name);
+                    participant
+                })
+                .collect(),
+            focused_row: Some(0),
+            initiative_system: self.initiative_system,
+            turn_time_limit: self.turn_time_limit,
+            saved_as: self.saved_as.clone(),
+            ..Default::default()
+        }
+    }
+
+    /// A combatant's current hit points, or `None` if they're unhurt or not being tracked.
+    fn hit_points_left(&self, name: &str) -> Option<u16> {
+        self.combatant(name)
+            .and_then(|combatant| combatant.hit_points)
+            .filter(|hit_points| hit_points.current < hit_points.max)
+            .map(|hit_points| hit_points.current)
+    }
+
+    fn is_dead(&self, name: &str) -> bool {
+        self.turns
+            .iter()
+            .chain(self.delayed.iter())
+            .any(|turn| turn.name == name && turn.dead)
+    }
+
+    fn is_last_turn_of_round(&self) -> bool {
+        self.current_turn + 1 == self.turns.len()
+    }
+
+    fn focus_next_candidate(&mut self) {
+        self.focused_row = match self
+            .initiative_system
+            .system()
+            .active_combatant_picks_next()
+        {
+            true => (self.current_turn + 1) % self.turns.len(),
+            false => self.current_turn,
+        };
+    }
+}
+
+/// The statistics for a combat that has just ended, and the encounter to go back to.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CombatSummaryState {
+    pub summary: CombatSummary,
+    /// The encounter with the survivors, to go back to editing.
+    pub encounter: EditingEncounterState,
+    /// Where to save the summary. `Some` while it is being typed in.
+    pub input: Option<String>,
+    /// What happened the last time the summary was saved.
+    pub message: Option<String>,
+    /// Whether the summary has been saved anywhere yet.
+    pub saved: bool,
+    /// Where to save the summary unless told otherwise: next to the encounter, if it was saved
+    /// to the campaign.
+    pub default_path: String,
+}
+
+impl CombatSummaryState {
+    /// Where to save the summary of an encounter that was never saved.
+    pub const DEFAULT_SUMMARY_PATH: &'static str = "combat-summary.md";
+
+    /// Sum up `combat`, leaving the party in `workspace` with the hit points they have left.
+    pub fn new(combat: &RunningCombatState, workspace: &mut Workspace) -> Self {
+        let default_path = combat
+            .saved_as
+            .as_ref()
+            .and_then(|name| workspace.campaign.summary_path(name).ok())
+            .map(|path| path.display().to_string())
+            .unwrap_or_else(|| Self::DEFAULT_SUMMARY_PATH.to_string());
+
+        CombatSummaryState {
+            summary: CombatSummary::new(&combat.log, &combat.combatants, &combat.hidden_names()),
+            encounter: combat.end_combat(&mut workspace.party),
+            input: None,
+            message: None,
+            saved: false,
+            default_path,
+        }
+    }
+
+    pub fn begin_save(&mut self) {
+        self.input = Some(self.default_path.clone());
+    }
+
+    /// Save the summary as Markdown to the path typed in.
+    pub fn submit_save(&mut self) {
+        let Some(path) = self.input.take() else {
+            return;
+        };
+
+        let path = path.trim();
+        self.message = Some(match std::fs::write(path, self.summary.to_markdown()) {
+            Ok(()) => {
+                self.saved = true;
+                format!("Saved to {}", path)
+            }
+            Err(error) => format!("Couldn't save to {}: {}", path, error),
+        });
+    }
+
+    pub fn cancel_save(&mut self) {
+        self.input = None;
+    }
+
+    pub fn input_char(&mut self, c: char) {
+        if let Some(input) = self.input.as_mut() {
+            input.push(c);
+        }
+    }
+
+    pub fn input_backspace(&mut self) {
+        if let Some(input) = self.input.as_mut() {
+            input.pop();
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Participant {
+    name: String,
+    initiative_rolls: Vec<Option<u8>>,
+    kind: TurnKind,
+    /// How many legendary actions the participant can take each round.
+    legendary_actions: u8,
+    side: Option<Side>,
+    /// Maximum hit points, if they are being tracked.
+    hit_points: Option<u16>,
+    /// Hit points left over from an earlier encounter. `None` means full health. Not saved,
+    /// like the party's.
+    #[serde(skip)]
+    current_hit_points: Option<u16>,
+    armor_class: Option<u8>,
+    /// Added to a d20 when rolling initiative.
+    initiative_modifier: i8,
+    /// A player character's level.
+    level: Option<u8>,
+    /// A monster's challenge rating.
+    challenge_rating: Option<ChallengeRating>,
+    /// Only the GM knows about hidden participants until they're revealed, so they're left out
+    /// of exports and the player view.
+    hidden: bool,
+}
+
+impl Participant {
+    /// Lair actions happen on initiative count 20, after anyone else who rolled 20.
+    pub const LAIR_ACTION_INITIATIVE: u8 = 20;
+
+    pub fn new(name: &str) -> Self {
+        Participant {
+            name: String::from(name),
+            initiative_rolls: vec![None],
+            ..Default::default()
+        }
+    }
+
+    /// The lair's turn, for encounters in a legendary creature's lair.
+    pub fn lair_action() -> Self {
+        Participant {
+            name: String::from("Lair Action"),
+            initiative_rolls: vec![Some(Self::LAIR_ACTION_INITIATIVE)],
+            kind: TurnKind::LairAction,
+            ..Default::default()
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn set_name(&mut self, name: &str) {
+        self.name = String::from(name);
+    }
+
+    pub fn initiative_rolls(&self) -> &[Option<u8>] {
+        &self.initiative_rolls
+    }
+
+    pub fn kind(&self) -> TurnKind {
+        self.kind
+    }
+
+    pub fn legendary_actions(&self) -> u8 {
+        self.legendary_actions
+    }
+
+    pub fn set_legendary_actions(&mut self, legendary_actions: u8) {
+        self.legendary_actions = legendary_actions;
+    }
+
+    pub fn side(&self) -> Option<Side> {
+        self.side
+    }
+
+    pub fn set_side(&mut self, side: Option<Side>) {
+        self.side = side;
+    }
+
+    pub fn hidden(&self) -> bool {
+        self.hidden
+    }
+
+    pub fn set_hidden(&mut self, hidden: bool) {
+        self.hidden = hidden;
+    }
+
+    pub fn hit_points(&self) -> Option<u16> {
+        self.hit_points
+    }
+
+    pub fn set_hit_points(&mut self, hit_points: Option<u16>) {
+        self.hit_points = hit_points;
+    }
+
+    pub fn current_hit_points(&self) -> Option<u16> {
+        self.current_hit_points
+    }
+
+    pub fn set_current_hit_points(&mut self, current_hit_points: Option<u16>) {
+        self.current_hit_points = current_hit_points;
+    }
+
+    pub fn armor_class(&self) -> Option<u8> {
+        self.armor_class
+    }
+
+    pub fn set_armor_class(&mut self, armor_class: Option<u8>) {
+        self.armor_class = armor_class;
+    }
+
+    pub fn initiative_modifier(&self) -> i8 {
+        self.initiative_modifier
+    }
+
+    pub fn set_initiative_modifier(&mut self, initiative_modifier: i8) {
+        self.initiative_modifier = initiative_modifier;
+    }
+
+    pub fn level(&self) -> Option<u8> {
+        self.level
+    }
+
+    pub fn set_level(&mut self, level: Option<u8>) {
+        self.level = level;
+    }
+
+    pub fn challenge_rating(&self) -> Option<ChallengeRating> {
+        self.challenge_rating
+    }
+
+    pub fn set_challenge_rating(&mut self, challenge_rating: Option<ChallengeRating>) {
+        self.challenge_rating = challenge_rating;
+    }
+
+    pub fn add_initiative_slot(&mut self) {
+        self.initiative_rolls.push(None);
+    }
+
+    /// Remove the slot at `slot`, unless it is the participant's only slot.
+    pub fn remove_initiative_slot(&mut self, slot: usize) {
+        if self.initiative_rolls.len() > 1 && slot < self.initiative_rolls.len() {
+            self.initiative_rolls.remove(slot);
+        }
+    }
+
+    pub fn set_initiative_roll(&mut self, slot: usize, roll: Option<u8>) {
+        if let Some(existing) = self.initiative_rolls.get_mut(slot) {
+            *existing = roll;
+        }
+    }
+}
+
+/// Read a participant written in the quick entry grammar. A group like `Orc x3` is an error,
+/// since it's more than one participant.
+impl FromStr for Participant {
+    type Err = ParseParticipantError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.parse::<QuickEntry>()? {
+            QuickEntry {
+                participant,
+                count: 1,
+            } => Ok(participant),
+            QuickEntry { count, .. } => Err(ParseParticipantError::Group(count)),
+        }
+    }
+}
+
+/// Why some text couldn't be read as a participant.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseParticipantError {
+    EmptyName,
+    InvalidSide(String),
+    InvalidValue {
+        field: &'static str,
+        value: String,
+    },
+    UnknownField(String),
+    RepeatedField(String),
+    /// A word of the name after the options, which have to come last.
+    UnexpectedWord(String),
+    InvalidCount(String),
+    Group(u8),
+}
+
+impl Display for ParseParticipantError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            ParseParticipantError::EmptyName => write!(f, "the name is empty"),
+            ParseParticipantError::InvalidSide(side) => {
+                write!(f, "\"{}\" isn't a side (ally, enemy or neutral)", side)
+            }
+            ParseParticipantError::InvalidValue { field, value } => {
+                write!(f, "\"{}\" isn't a valid {}", value, field)
+            }
+            ParseParticipantError::UnknownField(field) => write!(
+                f,
+                "\"{}\" isn't a field ({})",
+                field,
+                quick_entry::FIELDS.join(", ")
+            ),
+            ParseParticipantError::RepeatedField(field) => {
+                write!(f, "{} is given more than once", field)
+            }
+            ParseParticipantError::UnexpectedWord(word) => write!(
+                f,
+                "\"{}\" comes after the options, but the whole name has to come first",
+                word
+            ),
+            ParseParticipantError::InvalidCount(count) => write!(
+                f,
+                "\"{}\" isn't a group size from x1 to x{}",
+                count,
+                quick_entry::MAX_GROUP_SIZE
+            ),
+            ParseParticipantError::Group(count) => {
+                write!(f, "this is a group of {}, not a single participant", count)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseParticipantError {}
+
+/// Which side of the fight a participant is on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Side {
+    Ally,
+    Enemy,
+    Neutral,
+}
+
+impl Display for Side {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Side::Ally => write!(f, "Ally"),
+            Side::Enemy => write!(f, "Enemy"),
+            Side::Neutral => write!(f, "Neutral"),
+        }
+    }
+}
+
+impl FromStr for Side {
+    type Err = ParseParticipantError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "ally" | "party" | "pc" => Ok(Side::Ally),
+            "enemy" | "foe" | "monster" => Ok(Side::Enemy),
+            "neutral" => Ok(Side::Neutral),
+            _ => Err(ParseParticipantError::InvalidSide(s.trim().to_string())),
+        }
+    }
+}
+
+/// What takes a turn. Turns are ordered by `TurnKind` after initiative, so a lair action loses
+/// ties with the combatants.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum TurnKind {
+    #[default]
+    Combatant,
+    LairAction,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CombatTurn {
+    name: String,
+    initiative_roll_value: u8,
+    kind: TurnKind,
+    unconscious: bool,
+    dead: bool,
+    /// The trigger for an action readied on this turn, until it is used or expires.
+    readied: Option<String>,
+    economy: ActionEconomy,
+    hidden: bool,
+}
+
+impl CombatTurn {
+    pub fn new(name: &str, initiative_roll_value: u8) -> Self {
+        CombatTurn {
+            name: String::from(name),
+            initiative_roll_value,
+            kind: TurnKind::Combatant,
+            unconscious: false,
+            dead: false,
+            readied: None,
+            economy: ActionEconomy::default(),
+            hidden: false,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn initiative_roll_value(&self) -> u8 {
+        self.initiative_roll_value
+    }
+
+    pub fn kind(&self) -> TurnKind {
+        self.kind
+    }
+
+    pub fn unconscious(&self) -> bool {
+        self.unconscious
+    }
+
+    pub fn dead(&self) -> bool {
+        self.dead
+    }
+
+    pub fn readied(&self) -> Option<&str> {
+        self.readied.as_deref()
+    }
+
+    pub fn hidden(&self) -> bool {
+        self.hidden
+    }
+
+    pub fn set_hidden(&mut self, hidden: bool) {
+        self.hidden = hidden;
+    }
+}
+
+impl From<Participant> for Vec<CombatTurn> {
+    fn from(value: Participant) -> Self {
+        let mut turns = Vec::new();
+        for roll in value.initiative_rolls.iter().filter_map(|x| *x) {
+            turns.push(CombatTurn {
+                kind: value.kind,
+                hidden: value.hidden,
+                ..CombatTurn::new(&value.name, roll)
+            });
+        }
+        turns
+    }
+}
+
+pub fn participants_to_ordered_combat_turns(participants: Vec<Participant>) -> Vec<CombatTurn> {
+    let mut result =
+        participants
+            .into_iter()
+            .fold(Vec::new(), |mut acc: Vec<CombatTurn>, p: Participant| {
+                let turns: Vec<CombatTurn> = p.into();
+                acc.extend(turns);
+                acc
+            });
+
+    result.sort_by_key(|turn| (std::cmp::Reverse(turn.initiative_roll_value), turn.kind));
+
+    result
+}
+
+/// `name`, or `name` with the lowest number after it that nobody in `participants` is using.
+/// Combat tells creatures apart by name, so two goblins become "Goblin" and "Goblin 2", and
+/// another "Goblin 2" carries on to "Goblin 3".
+pub fn unique_name(participants: &[Participant], name: &str) -> String {
+    let taken = |candidate: &str| participants.iter().any(|p| p.name == candidate);
+
+    if !taken(name) {
+        return name.to_string();
+    }
+
+    let base = match name.rsplit_once(' ') {
+        Some((base, number)) if number.parse::<u32>().is_ok() => base,
+        _ => name,
+    };
+    (2..)
+        .map(|number| format!("{} {}", base, number))
+        .find(|candidate| !taken(candidate))
+        .unwrap_or_default()
+}
+
+/// Add `newcomers` after `participants`, renumbering anyone whose name is already taken.
+pub fn add_participants(participants: &mut Vec<Participant>, newcomers: Vec<Participant>) {
+    for mut participant in newcomers {
+        participant.name = unique_name(participants, &participant.name);
+        participants.push(participant);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_participant_constructor() {
+        let result = Participant::new("Goku");
+
+        assert_eq!(result.name, "Goku");
+        assert_eq!(result.initiative_rolls, vec![None]);
+    }
+
+    #[test]
+    fn test_single_participant_to_combat_turns() {
+        let participant = Participant {
+            name: "Gandalf".to_string(),
+            initiative_rolls: vec![Some(1), Some(2), Some(3), Some(4)],
+            ..Default::default()
+        };
+
+        let turns: Vec<CombatTurn> = participant.into();
+
+        assert_eq!(turns.len(), 4);
+        assert_eq!(turns[0].name, "Gandalf");
+        assert_eq!(turns[0].initiative_roll_value, 1);
+        assert_eq!(turns[1].name, "Gandalf");
+        assert_eq!(turns[1].initiative_roll_value, 2);
+        assert_eq!(turns[2].name, "Gandalf");
+        assert_eq!(turns[2].initiative_roll_value, 3);
+        assert_eq!(turns[3].name, "Gandalf");
+        assert_eq!(turns[3].initiative_roll_value, 4);
+    }
+
+    #[test]
+    fn test_multiple_participants_to_combat_turns_sorted() {
+        let gandalf = Participant {
+            name: "Gandalf".to_string(),
+            initiative_rolls: vec![Some(20), Some(17)],
+            ..Default::default()
+        };
+
+        let legolas = Participant {
+            name: "Legolas".to_string(),
+            initiative_rolls: vec![Some(15)],
+            ..Default::default()
+        };
+
+        let gimli = Participant {
+            name: "Gimli".to_string(),
+            initiative_rolls: vec![Some(7), None],
+            ..Default::default()
+        };
+
+        let balrog = Participant {
+            name: "Balrog".to_string(),
+            initiative_rolls: vec![Some(25), Some(22), None],
+            ..Default::default()
+        };
+
+        let result = participants_to_ordered_combat_turns(vec![gandalf, legolas, gimli, balrog]);
+
+        assert_eq!(result.len(), 6);
+
+        assert_eq!(
+            result[0],
+            CombatTurn {
+                name: "Balrog".to_string(),
+                initiative_roll_value: 25,
+                unconscious: false,
+                dead: false,
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            result[1],
+            CombatTurn {
+                name: "Balrog".to_string(),
+                initiative_roll_value: 22,
+                unconscious: false,
+                dead: false,
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            result[2],
+            CombatTurn {
+                name: "Gandalf".to_string(),
+                initiative_roll_value: 20,
+                unconscious: false,
+                dead: false,
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            result[3],
+            CombatTurn {
+                name: "Gandalf".to_string(),
+                initiative_roll_value: 17,
+                unconscious: false,
+                dead: false,
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            result[4],
+            CombatTurn {
+                name: "Legolas".to_string(),
+                initiative_roll_value: 15,
+                unconscious: false,
+                dead: false,
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            result[5],
+            CombatTurn {
+                name: "Gimli".to_string(),
+                initiative_roll_value: 7,
+                unconscious: false,
+                dead: false,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_app_mode_initial_state() {
+        let app = AppMode::default();
+
+        let result = app.next_state().unwrap();
+
+        assert_eq!(
+            result,
+            Some(AppMode::Initializing(InitializeState {
+                splash_screen_frames_remaining: FRAMES_PER_SECOND as u32 - 1,
+                ..Default::default()
+            }))
+        );
+    }
+
+    #[test]
+    fn test_app_mode_initial_state_to_running() {
+        let mut themes = Themes::default();
+        themes.cycle();
+        let workspace = Workspace {
+            campaign: Campaign {
+                dir: None,
+                encounters: vec![SavedEncounter::default()],
+            },
+            ..Default::default()
+        };
+        let app = AppMode::Initializing(InitializeState {
+            splash_screen_frames_remaining: 0,
+            workspace: workspace.clone(),
+            message: Some(String::from("Couldn't read the library")),
+            themes: themes.clone(),
+            ..Default::default()
+        });
+
+        let result = app.next_state().unwrap();
+
+        let expected = EncounterBrowserState {
+            message: Some(String::from("Couldn't read the library")),
+            ..Default::default()
+        };
+        assert_eq!(
+            result,
+            Some(AppMode::Running(Tabs::new(
+                RunMode::BrowsingEncounters(Box::new(expected)),
+                workspace,
+                themes
+            )))
+        );
+    }
+
+    #[test]
+    fn test_app_mode_initial_state_with_imports_to_editor() {
+        let app = AppMode::Initializing(InitializeState {
+            splash_screen_frames_remaining: 0,
+            participants: vec![Participant::new("Gandalf")],
+            ..Default::default()
+        });
+
+        let Some(AppMode::Running(tabs)) = app.next_state().unwrap() else {
+            panic!("Expected the app to be running");
+        };
+
+        let RunMode::EditingEncounter(encounter) = tabs.current() else {
+            panic!("Expected the imported participants to be edited");
+        };
+        assert_eq!(encounter.participants, vec![Participant::new("Gandalf")]);
+        assert_eq!(encounter.focused_row, Some(0));
+    }
+
+    #[test]
+    fn test_browse_save_and_reopen_encounters() {
+        let dir = std::env::temp_dir().join(format!(
+            "roll-initiative-browser-test-{}",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut workspace = Workspace {
+            campaign: Campaign::open(&dir).unwrap().0,
+            ..Default::default()
+        };
+        let mut browser = EncounterBrowserState::default();
+
+        let mut encounter = browser.new_encounter();
+        encounter.participants.push(Participant::new("Owlbear"));
+        encounter.turn_time_limit = Some(Duration::from_secs(60));
+        encounter.begin_save_encounter();
+        "Owlbear den".chars().for_each(|c| encounter.input_char(c));
+        encounter.submit_prompt(&mut workspace);
+        assert_eq!(encounter.saved_as.as_deref(), Some("Owlbear den"));
+
+        browser.duplicate_focused(&mut workspace.campaign);
+        assert_eq!(browser.focused_row, 1);
+        assert_eq!(
+            browser
+                .focused_encounter(&workspace.campaign)
+                .map(|saved| saved.name.as_str()),
+            Some("Owlbear den (copy)")
+        );
+
+        let reopened = browser.open_focused(&workspace.campaign).unwrap();
+        assert_eq!(reopened.participants, encounter.participants);
+        assert_eq!(reopened.turn_time_limit, Some(Duration::from_secs(60)));
+        assert_eq!(reopened.saved_as.as_deref(), Some("Owlbear den (copy)"));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_closing_a_tab_asks_before_losing_an_unsaved_encounter() {
+        let mut tabs = Tabs::from(RunMode::EditingEncounter(Box::default()));
+        tabs.new_tab();
+        tabs.close_tab();
+        assert_eq!(tabs.tabs.len(), 1);
+
+        tabs.new_tab();
+        let RunMode::EditingEncounter(encounter) = tabs.current_mut() else {
+            panic!("Expected a new encounter to be edited");
+        };
+        encounter.participants.push(Participant::new("Owlbear"));
+        tabs.close_tab();
+        assert!(tabs.is_closing());
+        assert_eq!(
+            tabs.close_warning(),
+            Some("The encounter has changes that aren't saved.")
+        );
+
+        tabs.confirm_close();
+        assert!(!tabs.is_closing());
+        assert_eq!(tabs.tabs.len(), 1);
+    }
+
+    #[test]
+    fn test_app_mode_running_state_decrements_per_frame() {
+        let app = AppMode::Quitting(QuittingState {
+            quitting_screen_frames_remaining: 1,
+            ..Default::default()
+        });
+
+        let result = app.next_state().unwrap();
+
+        assert_eq!(
+            result,
+            Some(AppMode::Quitting(QuittingState {
+                quitting_screen_frames_remaining: 0,
+                ..Default::default()
+            }))
+        );
+    }
+
+    #[test]
+    fn test_app_mode_quitting_state_to_none() {
+        let app = AppMode::Quitting(QuittingState {
+            quitting_screen_frames_remaining: 0,
+            ..Default::default()
+        });
+
+        let result = app.next_state().unwrap();
+
+        assert_eq!(result, None);
+    }
+
+    #[test]
+    fn test_quitting_command_induce_state_change() {
+        let app = AppMode::Running(RunMode::EditingEncounter(Box::default()).into());
+
+        let func = StateInducer::from(AppCommand::Quit);
+        let result = func(&app);
+
+        assert_eq!(
             result,
             AppMode::Quitting(QuittingState {
-                quitting_screen_frames_remaining: (FRAMES_PER_SECOND * 2) as u32,
+                quitting_screen_frames_remaining: FRAMES_PER_SECOND as u32,
+                ..Default::default()
+            })
+        );
+    }
+
+    #[test]
+    fn test_add_participant_focuses_and_edits_name() {
+        let mut state = EditingEncounterState::default();
+
+        state.add_participant();
+        "Legolas".chars().for_each(|c| state.input_char(c));
+        state.toggle_editing();
+
+        assert_eq!(state.focused_row, Some(0));
+        assert_eq!(state.input, None);
+        assert_eq!(state.participants[0].name, "Legolas");
+    }
+
+    #[test]
+    fn test_add_participants_with_quick_entry() {
+        let mut state = EditingEncounterState {
+            participants: vec![Participant::new("Orc")],
+            ..Default::default()
+        };
+
+        state.add_participant();
+        "Orc x2 hp:15 init:12"
+            .chars()
+            .for_each(|c| state.input_char(c));
+        state.toggle_editing();
+
+        let names: Vec<&str> = state.participants.iter().map(|p| p.name()).collect();
+        assert_eq!(names, vec!["Orc", "Orc 2", "Orc 3"]);
+        assert_eq!(state.participants[2].initiative_rolls, vec![Some(12)]);
+        assert_eq!(state.focused_row, Some(2));
+        assert!(!state.is_editing());
+
+        // Mistakes stay in the cell to be fixed
+        state.add_participant();
+        "Troll hp:lots".chars().for_each(|c| state.input_char(c));
+        state.toggle_editing();
+
+        assert!(state.is_editing());
+        assert_eq!(
+            state.message.as_deref(),
+            Some("Couldn't add Troll hp:lots: \"lots\" isn't a valid hit points")
+        );
+    }
+
+    #[test]
+    fn test_initiative_slots_grow_and_shrink() {
+        let mut state = EditingEncounterState {
+            participants: vec![Participant::new("Gandalf"), Participant::new("Gimli")],
+            focused_row: Some(0),
+            ..Default::default()
+        };
+
+        state.add_initiative_slot();
+        state.add_initiative_slot();
+        state.add_initiative_slot();
+        state.add_initiative_slot();
+
+        assert_eq!(state.participants[0].initiative_rolls.len(), 5);
+        assert_eq!(state.participants[1].initiative_rolls.len(), 1);
+        assert_eq!(state.initiative_slot_columns(), 5);
+        assert_eq!(state.focused_column(), ParticipantColumn::InitiativeRoll(4));
+
+        state.remove_initiative_slot();
+
+        assert_eq!(state.participants[0].initiative_rolls.len(), 4);
+        assert_eq!(state.focused_column(), ParticipantColumn::InitiativeRoll(3));
+    }
+
+    #[test]
+    fn test_participant_keeps_last_initiative_slot() {
+        let mut participant = Participant::new("Gimli");
+
+        participant.remove_initiative_slot(0);
+
+        assert_eq!(participant.initiative_rolls, vec![None]);
+    }
+
+    #[test]
+    fn test_editing_initiative_roll_rejects_invalid_input() {
+        let mut state = EditingEncounterState {
+            participants: vec![Participant::new("Balrog")],
+            focused_row: Some(0),
+            ..Default::default()
+        };
+
+        state.focus_column(ParticipantColumn::InitiativeRoll(0));
+        state.toggle_editing();
+        "3x".chars().for_each(|c| state.input_char(c));
+        state.toggle_editing();
+
+        assert_eq!(state.input, Some("3x".to_string()));
+
+        state.input_backspace();
+        state.toggle_editing();
+
+        assert_eq!(state.input, None);
+        assert_eq!(state.participants[0].initiative_rolls, vec![Some(3)]);
+    }
+
+    #[test]
+    fn test_remove_last_participant_clears_focus() {
+        let mut state = EditingEncounterState {
+            participants: vec![Participant::new("Goku")],
+            focused_row: Some(0),
+            ..Default::default()
+        };
+
+        state.remove_focused_participant();
+
+        assert!(state.participants.is_empty());
+        assert_eq!(state.focused_row, None);
+    }
+
+    fn encounter(
+        rolls: &[(&str, u8)],
+        initiative_system: InitiativeSystemKind,
+    ) -> EditingEncounterState {
+        EditingEncounterState {
+            participants: rolls
+                .iter()
+                .map(|(name, roll)| Participant {
+                    name: name.to_string(),
+                    initiative_rolls: vec![Some(*roll)],
+                    ..Default::default()
+                })
+                .collect(),
+            initiative_system,
+            ..Default::default()
+        }
+    }
+
+    /// The combat log without the turn and round changes, which are logged all the time.
+    fn notable_log(combat: &RunningCombatState) -> Vec<CombatLogEntry> {
+        combat
+            .log
+            .iter()
+            .filter(|entry| {
+                !matches!(
+                    entry.event,
+                    CombatEvent::RoundStarted | CombatEvent::TurnStarted { .. }
+                )
+            })
+            .cloned()
+            .collect()
+    }
+
+    fn last_event(combat: &RunningCombatState) -> Option<CombatEvent> {
+        notable_log(combat).pop().map(|entry| entry.event)
+    }
+
+    #[test]
+    fn test_combat_needs_initiative_rolls() {
+        let state = EditingEncounterState {
+            participants: vec![Participant::new("Goku")],
+            ..Default::default()
+        };
+
+        assert_eq!(RunningCombatState::new(&state), None);
+    }
+
+    #[test]
+    fn test_next_turn_wraps_to_next_round() {
+        let state = encounter(
+            &[("Gimli", 7), ("Legolas", 15)],
+            InitiativeSystemKind::StandardD20,
+        );
+        let mut combat = RunningCombatState::new(&state).unwrap();
+
+        assert_eq!(combat.current().name, "Legolas");
+
+        combat.next_turn();
+        assert_eq!(combat.current().name, "Gimli");
+        assert_eq!(combat.round, 1);
+
+        combat.next_turn();
+        assert_eq!(combat.current().name, "Legolas");
+        assert_eq!(combat.round, 2);
+    }
+
+    #[test]
+    fn test_popcorn_active_combatant_picks_next() {
+        let state = encounter(
+            &[("Gimli", 7), ("Legolas", 15), ("Gandalf", 12)],
+            InitiativeSystemKind::Popcorn,
+        );
+        let mut combat = RunningCombatState::new(&state).unwrap();
+
+        // Legolas hands the turn to Gimli rather than Gandalf
+        combat.focused_row = 2;
+        combat.next_turn();
+        assert_eq!(combat.current().name, "Gimli");
+
+        // Legolas has already acted this round, so can't be picked
+        combat.focused_row = 0;
+        combat.next_turn();
+        assert_eq!(combat.current().name, "Gimli");
+
+        combat.focused_row = 2;
+        combat.next_turn();
+        assert_eq!(combat.current().name, "Gandalf");
+
+        // Gandalf acted last, so can pick themselves to start the next round
+        combat.focused_row = 2;
+        combat.next_turn();
+        assert_eq!(combat.current().name, "Gandalf");
+        assert_eq!(combat.round, 2);
+    }
+
+    #[test]
+    fn test_popcorn_cannot_pick_a_delayed_combatant() {
+        let state = encounter(
+            &[("Gimli", 7), ("Legolas", 15), ("Gandalf", 12)],
+            InitiativeSystemKind::Popcorn,
+        );
+        let mut combat = RunningCombatState::new(&state).unwrap();
+
+        combat.delay_current_turn();
+        combat.focus_next_row();
+        combat.focus_next_row();
+        assert!(combat.focused_delayed().is_some());
+        combat.next_turn();
+
+        assert_eq!(combat.current().name, "Gandalf");
+        assert_eq!(combat.turns.len(), 2);
+        assert_eq!(combat.delayed.len(), 1);
+    }
+
+    #[test]
+    fn test_delayed_combatant_re_enters_permanently() {
+        let state = encounter(
+            &[("Gimli", 7), ("Legolas", 15), ("Gandalf", 12)],
+            InitiativeSystemKind::StandardD20,
+        );
+        let mut combat = RunningCombatState::new(&state).unwrap();
+
+        combat.delay_current_turn();
+        assert_eq!(combat.current().name, "Gandalf");
+        assert_eq!(combat.delayed.len(), 1);
+
+        combat.next_turn();
+        assert_eq!(combat.current().name, "Gimli");
+
+        // Legolas jumps in before Gimli, and keeps that place in the next round
+        combat.focused_row = 2;
+        combat.re_enter_focused_delayed();
+        assert_eq!(combat.current().name, "Legolas");
+        assert_eq!(combat.current().initiative_roll_value, 7);
+        assert!(combat.delayed.is_empty());
+
+        combat.next_turn();
+        combat.next_turn();
+        assert_eq!(combat.current().name, "Gandalf");
+        assert_eq!(combat.round, 2);
+        combat.next_turn();
+        assert_eq!(combat.current().name, "Legolas");
+
+        assert_eq!(
+            notable_log(&combat),
+            vec![
+                CombatLogEntry {
+                    round: 1,
+                    event: CombatEvent::Delayed {
+                        name: "Legolas".to_string()
+                    }
+                },
+                CombatLogEntry {
+                    round: 1,
+                    event: CombatEvent::ReEntered {
+                        name: "Legolas".to_string(),
+                        before: "Gimli".to_string()
+                    }
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_last_combatant_in_order_cannot_delay() {
+        let state = encounter(&[("Gimli", 7)], InitiativeSystemKind::StandardD20);
+        let mut combat = RunningCombatState::new(&state).unwrap();
+
+        combat.delay_current_turn();
+
+        assert_eq!(combat.turns.len(), 1);
+        assert!(combat.delayed.is_empty());
+    }
+
+    #[test]
+    fn test_readied_action_triggers_once() {
+        let state = encounter(
+            &[("Gimli", 7), ("Legolas", 15)],
+            InitiativeSystemKind::StandardD20,
+        );
+        let mut combat = RunningCombatState::new(&state).unwrap();
+
+        combat.begin_ready();
+        "the orc opens the door"
+            .chars()
+            .for_each(|c| combat.input_char(c));
+        combat.submit_prompt();
+
+        assert_eq!(combat.prompt, None);
+        assert_eq!(combat.current().name, "Gimli");
+        assert_eq!(combat.turns[0].readied(), Some("the orc opens the door"));
+
+        combat.focused_row = 0;
+        combat.trigger_focused_readied_action();
+        combat.trigger_focused_readied_action();
+
+        assert_eq!(combat.turns[0].readied(), None);
+        assert_eq!(notable_log(&combat).len(), 2);
+    }
+
+    #[test]
+    fn test_unused_readied_action_expires() {
+        let state = encounter(
+            &[("Gimli", 7), ("Legolas", 15)],
+            InitiativeSystemKind::StandardD20,
+        );
+        let mut combat = RunningCombatState::new(&state).unwrap();
+
+        combat.begin_ready();
+        "anything".chars().for_each(|c| combat.input_char(c));
+        combat.submit_prompt();
+        combat.next_turn();
+
+        assert_eq!(combat.current().name, "Legolas");
+        assert_eq!(combat.current().readied(), None);
+        assert_eq!(
+            last_event(&combat).as_ref(),
+            Some(&CombatEvent::ReadiedActionExpired {
+                name: "Legolas".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_add_lair_action_only_once() {
+        let mut state = EditingEncounterState::default();
+
+        state.add_lair_action();
+        state.add_lair_action();
+
+        assert_eq!(state.participants, vec![Participant::lair_action()]);
+        assert_eq!(state.focused_row, Some(0));
+    }
+
+    #[test]
+    fn test_legendary_actions_prompted_after_other_turns_and_reset() {
+        let mut state = encounter(
+            &[("Gimli", 7), ("Legolas", 15), ("Balrog", 10)],
+            InitiativeSystemKind::StandardD20,
+        );
+        state.participants[2].legendary_actions = 2;
+        let mut combat = RunningCombatState::new(&state).unwrap();
+
+        // End of Legolas' turn, the Balrog spends one
+        combat.next_turn();
+        assert_eq!(
+            combat.prompt,
+            Some(CombatPrompt::SpendLegendaryAction {
+                names: vec!["Balrog".to_string()]
+            })
+        );
+        assert_eq!(combat.current().name, "Legolas");
+
+        combat.submit_prompt();
+        assert_eq!(combat.prompt, None);
+        assert_eq!(combat.current().name, "Balrog");
+        assert_eq!(combat.legendary_actions[0].remaining, 2);
+
+        // No legendary actions at the end of the Balrog's own turn
+        combat.next_turn();
+        assert_eq!(combat.prompt, None);
+        assert_eq!(combat.current().name, "Gimli");
+
+        combat.next_turn();
+        combat.submit_prompt();
+        assert_eq!(combat.current().name, "Legolas");
+        assert_eq!(combat.legendary_actions[0].remaining, 1);
+
+        // Declining moves straight on
+        combat.next_turn();
+        combat.cancel_prompt();
+        assert_eq!(combat.current().name, "Balrog");
+        assert_eq!(
+            last_event(&combat).as_ref(),
+            Some(&CombatEvent::LegendaryActionSpent {
+                name: "Balrog".to_string(),
+                remaining: 1
+            })
+        );
+    }
+
+    #[test]
+    fn test_action_economy_resets_at_start_of_own_turn() {
+        let state = encounter(
+            &[("Gimli", 7), ("Legolas", 15)],
+            InitiativeSystemKind::StandardD20,
+        );
+        let mut combat = RunningCombatState::new(&state).unwrap();
+
+        combat.focused_row = 0;
+        combat.toggle_focused_economy(EconomyResource::Action);
+        combat.toggle_focused_economy(EconomyResource::Movement);
+
+        // Legolas makes an opportunity attack on Gimli's turn
+        combat.next_turn();
+        combat.focused_row = 0;
+        combat.toggle_focused_economy(EconomyResource::Reaction);
+
+        assert_eq!(
+            combat.economy(&combat.turns[0]),
+            ActionEconomy {
+                action_used: true,
+                bonus_action_used: false,
+                reaction_used: true,
+                movement_used: true,
+            }
+        );
+
+        combat.next_turn();
+        assert_eq!(combat.current().name, "Legolas");
+        assert_eq!(combat.economy(combat.current()), ActionEconomy::default());
+    }
+
+    #[test]
+    fn test_reaction_comes_back_once_a_round() {
+        let mut state = encounter(
+            &[("Gimli", 7), ("Legolas", 15)],
+            InitiativeSystemKind::StandardD20,
+        );
+        state.participants[1].add_initiative_slot();
+        state.participants[1].set_initiative_roll(1, Some(3));
+        let mut combat = RunningCombatState::new(&state).unwrap();
+
+        combat.focused_row = 0;
+        combat.toggle_focused_economy(EconomyResource::Reaction);
+        combat.next_turn();
+        combat.next_turn();
+
+        // Legolas's second turn this round doesn't bring the reaction back
+        assert_eq!(combat.current().name, "Legolas");
+        assert!(combat.economy(combat.current()).reaction_used);
+        assert!(!combat.economy(combat.current()).action_used);
+
+        combat.next_turn();
+        assert_eq!(combat.round, 2);
+        assert!(!combat.economy(combat.current()).reaction_used);
+    }
+
+    #[test]
+    fn test_damage_to_zero_knocks_out_and_healing_wakes() {
+        let mut state = encounter(
+            &[("Gimli", 7), ("Legolas", 15)],
+            InitiativeSystemKind::StandardD20,
+        );
+        state.participants[0].hit_points = Some(20);
+        let mut combat = RunningCombatState::new(&state).unwrap();
+
+        combat.focused_row = 1;
+        combat.begin_damage();
+        for c in "25".chars() {
+            combat.input_char(c);
+        }
+        combat.submit_prompt();
+
+        assert_eq!(
+            combat.combatant("Gimli").unwrap().hit_points,
+            Some(HitPoints {
+                current: 0,
+                max: 20
+            })
+        );
+        assert!(combat.turns[1].unconscious);
+        assert_eq!(
+            last_event(&combat).as_ref(),
+            Some(&CombatEvent::KnockedOut {
+                name: "Gimli".to_string()
+            })
+        );
+
+        combat.heal("Gimli", 30);
+        assert_eq!(
+            combat.combatant("Gimli").unwrap().hit_points,
+            Some(HitPoints {
+                current: 20,
+                max: 20
+            })
+        );
+        assert!(!combat.turns[1].unconscious);
+    }
+
+    #[test]
+    fn test_renaming_to_a_taken_name_keeps_combatants_apart() {
+        let mut state = encounter(
+            &[("Orc", 12), ("Goblin", 8)],
+            InitiativeSystemKind::StandardD20,
+        );
+        for participant in state.participants.iter_mut() {
+            participant.hit_points = Some(15);
+        }
+        state.focus_column(ParticipantColumn::Name);
+        for (row, name) in [(0, "Orc"), (1, "Orc")] {
+            state.focused_row = Some(row);
+            state.toggle_editing();
+            state.input = Some(name.to_string());
+            state.toggle_editing();
+        }
+
+        let names: Vec<&str> = state.participants.iter().map(|p| p.name()).collect();
+        assert_eq!(names, vec!["Orc", "Orc 2"]);
+
+        let mut combat = RunningCombatState::new(&state).unwrap();
+        combat.damage("Orc", 5);
+        combat.damage("Orc 2", 3);
+        assert_eq!(
+            combat.combatant("Orc").unwrap().hit_points,
+            Some(HitPoints {
+                current: 10,
+                max: 15
+            })
+        );
+        assert_eq!(
+            combat.combatant("Orc 2").unwrap().hit_points,
+            Some(HitPoints {
+                current: 12,
+                max: 15
+            })
+        );
+    }
+
+    #[test]
+    fn test_damage_while_concentrating_asks_for_a_save() {
+        let state = encounter(
+            &[("Gandalf", 12), ("Balrog", 10)],
+            InitiativeSystemKind::StandardD20,
+        );
+        let mut combat = RunningCombatState::new(&state).unwrap();
+
+        combat.toggle_focused_concentration();
+        for c in "Hold Person, 10".chars() {
+            combat.input_char(c);
+        }
+        combat.submit_prompt();
+        assert_eq!(
+            combat.combatant("Gandalf").unwrap().concentration,
+            Some(Concentration {
+                spell: "Hold Person".to_string(),
+                started_round: 1,
+                duration: Some(10),
+            })
+        );
+
+        combat.damage("Gandalf", 30);
+        assert_eq!(
+            combat.prompt,
+            Some(CombatPrompt::ConcentrationSave {
+                name: "Gandalf".to_string(),
+                dc: 15,
+                input: String::new(),
+            })
+        );
+
+        // A total typed in is used as it is
+        combat.input_char('1');
+        combat.input_char('4');
+        combat.submit_prompt();
+        assert_eq!(combat.prompt, None);
+        assert_eq!(combat.combatant("Gandalf").unwrap().concentration, None);
+        assert_eq!(
+            last_event(&combat).as_ref(),
+            Some(&CombatEvent::ConcentrationBroken {
+                name: "Gandalf".to_string(),
+                spell: "Hold Person".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_knocked_out_concentrating_creature_loses_concentration() {
+        let mut state = encounter(
+            &[("Gandalf", 12), ("Balrog", 10)],
+            InitiativeSystemKind::StandardD20,
+        );
+        state.participants[0].hit_points = Some(20);
+        let mut combat = RunningCombatState::new(&state).unwrap();
+
+        combat.toggle_focused_concentration();
+        "Hold Person".chars().for_each(|c| combat.input_char(c));
+        combat.submit_prompt();
+        combat.damage("Gandalf", 25);
+
+        assert_eq!(combat.prompt, None);
+        assert_eq!(combat.combatant("Gandalf").unwrap().concentration, None);
+        assert_eq!(
+            notable_log(&combat)[2..],
+            [
+                CombatLogEntry {
+                    round: 1,
+                    event: CombatEvent::KnockedOut {
+                        name: "Gandalf".to_string()
+                    }
+                },
+                CombatLogEntry {
+                    round: 1,
+                    event: CombatEvent::ConcentrationBroken {
+                        name: "Gandalf".to_string(),
+                        spell: "Hold Person".to_string(),
+                    }
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_concentration_ends_when_duration_runs_out() {
+        let state = encounter(
+            &[("Gandalf", 12), ("Balrog", 10)],
+            InitiativeSystemKind::StandardD20,
+        );
+        let mut combat = RunningCombatState::new(&state).unwrap();
+
+        combat.toggle_focused_concentration();
+        for c in "Bless, 2".chars() {
+            combat.input_char(c);
+        }
+        combat.submit_prompt();
+
+        // Through the rest of round 1 and all of round 2
+        for _ in 0..3 {
+            combat.next_turn();
+        }
+        assert_eq!(combat.round, 2);
+        assert!(combat.combatant("Gandalf").unwrap().concentration.is_some());
+
+        combat.next_turn();
+        assert_eq!(combat.round, 3);
+        assert_eq!(combat.combatant("Gandalf").unwrap().concentration, None);
+        assert_eq!(
+            last_event(&combat).as_ref(),
+            Some(&CombatEvent::ConcentrationEnded {
+                name: "Gandalf".to_string(),
+                spell: "Bless".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_round_progress() {
+        let state = encounter(
+            &[("Gimli", 7), ("Legolas", 15), ("Aragorn", 12)],
+            InitiativeSystemKind::StandardD20,
+        );
+        let mut combat = RunningCombatState::new(&state).unwrap();
+
+        assert_eq!(combat.turns_remaining_in_round(), 2);
+        assert_eq!(combat.elapsed(), GameTime::from_rounds(0));
+
+        combat.next_turn();
+        combat.next_turn();
+        assert_eq!(combat.turns_remaining_in_round(), 0);
+
+        combat.next_turn();
+        assert_eq!(combat.round, 2);
+        assert_eq!(combat.turns_remaining_in_round(), 2);
+        assert_eq!(combat.elapsed().seconds, 6);
+    }
+
+    #[test]
+    fn test_turn_timer_counts_against_current_combatant() {
+        let mut state = encounter(
+            &[("Gimli", 7), ("Legolas", 15)],
+            InitiativeSystemKind::StandardD20,
+        );
+        state.turn_time_limit = Some(Duration::from_secs(1));
+        let mut combat = RunningCombatState::new(&state).unwrap();
+
+        for _ in 0..FRAMES_PER_SECOND {
+            combat.tick();
+        }
+        assert_eq!(combat.turn_timer_status(), Some(TimerStatus::Expired));
+
+        combat.next_turn();
+        combat.tick();
+        assert_eq!(combat.turn_timer.frames, 1);
+        assert_eq!(combat.turn_timer_status(), Some(TimerStatus::Running));
+        assert_eq!(
+            combat.combatant("Legolas").unwrap().time_spent.frames,
+            FRAMES_PER_SECOND
+        );
+        assert_eq!(combat.combatant("Gimli").unwrap().time_spent.frames, 1);
+    }
+
+    #[test]
+    fn test_turn_and_round_changes_are_logged() {
+        let state = encounter(
+            &[("Gimli", 7), ("Legolas", 15)],
+            InitiativeSystemKind::StandardD20,
+        );
+        let mut combat = RunningCombatState::new(&state).unwrap();
+
+        combat.next_turn();
+        combat.next_turn();
+
+        let entry = |round, event| CombatLogEntry { round, event };
+        assert_eq!(
+            combat.log,
+            vec![
+                entry(1, CombatEvent::RoundStarted),
+                entry(
+                    1,
+                    CombatEvent::TurnStarted {
+                        name: "Legolas".to_string()
+                    }
+                ),
+                entry(
+                    1,
+                    CombatEvent::TurnStarted {
+                        name: "Gimli".to_string()
+                    }
+                ),
+                entry(2, CombatEvent::RoundStarted),
+                entry(
+                    2,
+                    CombatEvent::TurnStarted {
+                        name: "Legolas".to_string()
+                    }
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_conditions_toggle() {
+        let state = encounter(&[("Gimli", 7)], InitiativeSystemKind::StandardD20);
+        let mut combat = RunningCombatState::new(&state).unwrap();
+
+        combat.toggle_condition("Gimli", "Prone");
+        assert_eq!(combat.combatant("Gimli").unwrap().conditions, vec!["Prone"]);
+
+        combat.toggle_condition("Gimli", "prone");
+        assert!(combat.combatant("Gimli").unwrap().conditions.is_empty());
+        assert_eq!(
+            last_event(&combat),
+            Some(CombatEvent::ConditionRemoved {
+                name: "Gimli".to_string(),
+                condition: "Prone".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_log_scroll_stays_within_log() {
+        let state = encounter(&[("Gimli", 7)], InitiativeSystemKind::StandardD20);
+        let mut combat = RunningCombatState::new(&state).unwrap();
+
+        combat.scroll_log_back(10);
+        assert_eq!(combat.log_scroll, combat.log.len() - 1);
+
+        combat.scroll_log_forward(10);
+        assert_eq!(combat.log_scroll, 0);
+    }
+
+    #[test]
+    fn test_end_combat_saves_transcript() {
+        let state = encounter(&[("Gimli", 7)], InitiativeSystemKind::StandardD20);
+        let mut combat = RunningCombatState::new(&state).unwrap();
+        let path = std::env::temp_dir().join(format!(
+            "roll-initiative-transcript-test-{}.md",
+            std::process::id()
+        ));
+
+        combat.begin_end_combat();
+        path.to_str()
+            .unwrap()
+            .chars()
+            .for_each(|c| combat.input_char(c));
+        combat.submit_prompt();
+
+        assert!(combat.finished);
+        let transcript = std::fs::read_to_string(&path).unwrap();
+        assert!(transcript.starts_with("# Combat transcript"));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_export_order_from_combat() {
+        let state = encounter(&[("Gimli", 7)], InitiativeSystemKind::StandardD20);
+        let mut combat = RunningCombatState::new(&state).unwrap();
+        let path = std::env::temp_dir().join(format!(
+            "roll-initiative-order-test-{}.html",
+            std::process::id()
+        ));
+
+        combat.begin_export();
+        let prompt = combat
+            .prompt
+            .as_mut()
+            .and_then(CombatPrompt::input_mut)
+            .unwrap();
+        *prompt = path.to_str().unwrap().to_string();
+        combat.submit_prompt();
+
+        assert_eq!(combat.prompt, None);
+        assert!(!combat.finished);
+        let order = std::fs::read_to_string(&path).unwrap();
+        assert!(order.contains("<td>Gimli</td>"));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_hidden_participants_are_left_out_until_revealed() {
+        let mut state = encounter(
+            &[("Gimli", 7), ("Assassin", 18), ("Orc", 15)],
+            InitiativeSystemKind::StandardD20,
+        );
+        state.focused_row = Some(1);
+        state.toggle_hidden();
+        let mut combat = RunningCombatState::new(&state).unwrap();
+
+        let names = |combat: &RunningCombatState| -> Vec<String> {
+            ExportedOrder::new(combat)
+                .turns
+                .into_iter()
+                .map(|turn| turn.name)
+                .collect()
+        };
+        assert!(combat.turns[0].hidden());
+        assert_eq!(names(&combat), vec!["Orc", "Gimli"]);
+
+        combat.focused_row = 0;
+        combat.reveal_focused();
+
+        assert_eq!(names(&combat), vec!["Assassin", "Orc", "Gimli"]);
+        assert!(!combat.participants[1].hidden);
+        assert_eq!(
+            combat.log.last().map(|entry| &entry.event),
+            Some(&CombatEvent::Revealed {
+                name: "Assassin".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_dead_are_removed_when_combat_ends() {
+        let state = encounter(
+            &[("Gimli", 7), ("Orc", 15)],
+            InitiativeSystemKind::StandardD20,
+        );
+        let mut combat = RunningCombatState::new(&state).unwrap();
+
+        combat.focused_row = 0;
+        combat.toggle_focused_dead();
+        let summary = CombatSummaryState::new(&combat, &mut Workspace::default());
+
+        assert_eq!(summary.encounter.participants.len(), 1);
+        assert_eq!(summary.encounter.participants[0].name, "Gimli");
+        assert_eq!(summary.summary.rounds, 1);
+    }
+
+    #[test]
+    fn test_summary_is_saved_next_to_the_encounter() {
+        let mut workspace = Workspace {
+            campaign: Campaign {
+                dir: Some(std::path::PathBuf::from("campaign")),
+                encounters: vec![],
+            },
+            ..Default::default()
+        };
+        let mut state = encounter(&[("Gimli", 7)], InitiativeSystemKind::StandardD20);
+
+        let combat = RunningCombatState::new(&state).unwrap();
+        let mut summary = CombatSummaryState::new(&combat, &mut workspace);
+        assert_eq!(
+            summary.default_path,
+            CombatSummaryState::DEFAULT_SUMMARY_PATH
+        );
+
+        state.saved_as = Some(String::from("Bridge of Khazad-dum"));
+        let combat = RunningCombatState::new(&state).unwrap();
+        summary = CombatSummaryState::new(&combat, &mut workspace);
+        summary.begin_save();
+        let expected = Path::new("campaign").join("encounters/bridge-of-khazad-dum.summary.md");
+        assert_eq!(summary.input, Some(expected.display().to_string()));
+    }
+
+    #[test]
+    fn test_add_monsters_from_compendium_with_unique_names() {
+        let mut state = EditingEncounterState::default();
+
+        state.open_picker();
+        "gobl".chars().for_each(|c| state.input_char(c));
+        state.add_picked_monster(&Library::default());
+        state.add_picked_monster(&Library::default());
+        state.cancel_editing();
+
+        assert!(!state.is_picking());
+        assert_eq!(state.participants.len(), 2);
+        assert_eq!(state.participants[0].name, "Goblin");
+        assert_eq!(state.participants[1].name, "Goblin 2");
+        assert_eq!(state.participants[1].hit_points, Some(7));
+        assert_eq!(state.participants[1].armor_class, Some(15));
+        assert!(state.participants[1].initiative_rolls[0].is_some());
+    }
+
+    #[test]
+    fn test_saved_participants_can_be_picked_from_the_library() {
+        let dir = std::env::temp_dir().join(format!(
+            "roll-initiative-editor-library-test-{}",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut workspace = Workspace {
+            library: Library::open(&dir).unwrap().0,
+            ..Default::default()
+        };
+        let mut state = EditingEncounterState::default();
+        let mut fighter = Participant::new("Goblin Slayer");
+        fighter.set_hit_points(Some(31));
+        fighter.set_level(Some(3));
+        state.participants.push(fighter);
+        state.focused_row = Some(0);
+
+        state.begin_save_template(&workspace.library);
+        "party".chars().for_each(|c| state.input_char(c));
+        state.submit_prompt(&mut workspace);
+
+        assert!(!state.is_prompting());
+        assert_eq!(workspace.library.templates[0].tags, vec!["party"]);
+        assert!(dir.join("goblin-slayer.json").exists());
+
+        // Searching by tag finds the template ahead of any monsters
+        state.open_picker();
+        "party".chars().for_each(|c| state.input_char(c));
+        state.add_picked_monster(&workspace.library);
+
+        assert_eq!(state.participants[1].name, "Goblin Slayer 2");
+        assert_eq!(state.participants[1].hit_points, Some(31));
+        assert_eq!(state.participants[1].initiative_rolls, vec![None]);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_import_from_the_editor() {
+        let path = std::env::temp_dir().join(format!(
+            "roll-initiative-import-test-{}.csv",
+            std::process::id()
+        ));
+        std::fs::write(&path, "Orc,1,15,enemy\nOrc,1,15,enemy\n").unwrap();
+        let mut state = EditingEncounterState::default();
+
+        state.begin_import();
+        path.to_str()
+            .unwrap()
+            .chars()
+            .for_each(|c| state.input_char(c));
+        state.submit_prompt(&mut Workspace::default());
+
+        assert!(!state.is_prompting());
+        assert_eq!(state.participants[1].name, "Orc 2");
+        assert_eq!(state.participants[1].side, Some(Side::Enemy));
+
+        std::fs::write(&path, "Orc,+1,lots\n").unwrap();
+        state.begin_import();
+        path.to_str()
+            .unwrap()
+            .chars()
+            .for_each(|c| state.input_char(c));
+        state.submit_prompt(&mut Workspace::default());
+
+        assert_eq!(state.participants.len(), 2);
+        assert!(state.message.as_ref().is_some_and(
+            |message| message.ends_with("line 1: \"lots\" isn't a number of hit points")
+        ));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_added_groups_are_numbered_once_after_everyone_else() {
+        let mut participants = vec![Participant::new("Orc 2")];
+
+        add_participants(
+            &mut participants,
+            import::parse("Orc x2\nOrc", import::LineFormat::PlainList).unwrap(),
+        );
+
+        let names: Vec<&str> = participants.iter().map(|p| p.name()).collect();
+        assert_eq!(names, vec!["Orc 2", "Orc", "Orc 3", "Orc 4"]);
+    }
+
+    #[test]
+    fn test_vtt_imports_report_what_was_left_out() {
+        let mut state = EditingEncounterState::default();
+
+        state.begin_import();
+        "data/samples/foundry-npc.json"
+            .chars()
+            .for_each(|c| state.input_char(c));
+        state.submit_prompt(&mut Workspace::default());
+
+        assert_eq!(state.participants[0].name, "Intellect Devourer");
+        let report = state.report.as_ref().unwrap();
+        assert_eq!(report.names, vec!["Intellect Devourer"]);
+        assert!(report
+            .body()
+            .contains("Not imported:\n- Intellect Devourer: Ignored items"));
+        assert!(state.is_reporting());
+
+        state.dismiss_report();
+        assert!(!state.is_reporting());
+    }
+
+    #[test]
+    fn test_party_keeps_hit_points_between_encounters() {
+        let mut bard = Participant::new("Astarion");
+        bard.set_hit_points(Some(20));
+        bard.set_level(Some(3));
+        let mut party = Party {
+            members: vec![PartyMember::new(&bard)],
+            ..Default::default()
+        };
+        let mut state = EditingEncounterState::default();
+
+        state.add_party(&party);
+        state.add_party(&party);
+        assert_eq!(state.participants.len(), 1);
+
+        state.participants[0].set_initiative_roll(0, Some(15));
+        let mut combat = RunningCombatState::new(&state).unwrap();
+        combat.damage("Astarion", 12);
+        let after = combat.end_combat(&mut party);
+
+        assert_eq!(after.participants[0].current_hit_points, Some(8));
+        assert_eq!(party.members[0].current_hit_points, Some(8));
+
+        // A fresh encounter later in the session picks up where the last one left off
+        let mut next = EditingEncounterState::default();
+        next.add_party(&party);
+        next.participants[0].set_initiative_roll(0, Some(3));
+        let combat = RunningCombatState::new(&next).unwrap();
+        assert_eq!(
+            combat.combatant("Astarion").unwrap().hit_points,
+            Some(HitPoints {
+                current: 8,
+                max: 20
             })
         );
     }
@@ -0,0 +1,350 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app::{Participant, TurnKind},
+    difficulty::{Difficulty, EncounterDifficulty},
+    initiative::InitiativeSystemKind,
+    library,
+};
+
+/// Set this to work on a campaign somewhere other than the usual data directory.
+pub const CAMPAIGN_DIR_VAR: &str = "ROLL_INITIATIVE_CAMPAIGN";
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// An encounter prepared ahead of time and kept with the campaign.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SavedEncounter {
+    pub name: String,
+    pub participants: Vec<Participant>,
+    pub initiative_system: InitiativeSystemKind,
+    /// In seconds.
+    pub turn_time_limit: Option<u64>,
+    /// When combat last started from this encounter, in seconds since the Unix epoch.
+    pub last_played: Option<u64>,
+}
+
+impl SavedEncounter {
+    pub fn combatant_count(&self) -> usize {
+        self.participants
+            .iter()
+            .filter(|participant| participant.kind() == TurnKind::Combatant)
+            .count()
+    }
+
+    /// How the encounter measures up against the characters saved in it, if it has any.
+    pub fn difficulty(&self) -> Option<Difficulty> {
+        EncounterDifficulty::rate(&self.participants).map(|rating| rating.difficulty)
+    }
+
+    pub fn turn_time_limit(&self) -> Option<Duration> {
+        self.turn_time_limit.map(Duration::from_secs)
+    }
+
+    /// When the encounter was last played, such as "3 days ago", as of `now`.
+    pub fn last_played_text(&self, now: u64) -> String {
+        match self.last_played {
+            None => String::from("Never"),
+            Some(played) => match now.saturating_sub(played) / SECONDS_PER_DAY {
+                0 => String::from("Today"),
+                1 => String::from("Yesterday"),
+                days => format!("{} days ago", days),
+            },
+        }
+    }
+}
+
+/// The seconds since the Unix epoch, for stamping when encounters are played.
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default()
+}
+
+/// A campaign's workspace: a directory holding its saved encounters in `encounters`, along with
+/// the creature library in `library` and the party roster in `party.json`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Campaign {
+    /// `None` when there's nowhere to keep the campaign.
+    pub dir: Option<PathBuf>,
+    /// Sorted by name.
+    pub encounters: Vec<SavedEncounter>,
+}
+
+impl Campaign {
+    /// `$ROLL_INITIATIVE_CAMPAIGN`, or the app's data directory.
+    pub fn default_dir() -> Option<PathBuf> {
+        match env::var_os(CAMPAIGN_DIR_VAR) {
+            Some(dir) => Some(PathBuf::from(dir)),
+            None => library::data_dir(),
+        }
+    }
+
+    /// Load every encounter saved in the campaign at `dir`, along with a warning for each file
+    /// that couldn't be read and was left out. A campaign that doesn't exist yet has no
+    /// encounters.
+    pub fn open(dir: &Path) -> Result<(Self, Vec<String>)> {
+        let mut campaign = Campaign {
+            dir: Some(dir.to_path_buf()),
+            encounters: vec![],
+        };
+        let mut warnings = vec![];
+        let encounters_dir = dir.join("encounters");
+        if !encounters_dir.exists() {
+            return Ok((campaign, warnings));
+        }
+
+        let entries = fs::read_dir(&encounters_dir).with_context(|| {
+            format!(
+                "Couldn't read the encounters in {}",
+                encounters_dir.display()
+            )
+        })?;
+        for entry in entries {
+            let path = entry?.path();
+            if path
+                .extension()
+                .is_some_and(|extension| extension == "json")
+            {
+                match Campaign::read(&path) {
+                    Ok(encounter) => campaign.encounters.push(encounter),
+                    Err(error) => warnings.push(format!("{:#}", error)),
+                }
+            }
+        }
+        campaign.sort();
+
+        Ok((campaign, warnings))
+    }
+
+    /// Write `encounter` to the campaign, replacing any encounter with the same name.
+    pub fn save(&mut self, encounter: SavedEncounter) -> Result<PathBuf> {
+        let path = self.path(&encounter.name)?;
+        library::check_not_taken(
+            &encounter.name,
+            self.get(&encounter.name)
+                .map(|existing| existing.name.as_str()),
+        )?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Couldn't create {}", dir.display()))?;
+        }
+
+        self.write(&path, encounter)?;
+        Ok(path)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&SavedEncounter> {
+        self.encounters
+            .iter()
+            .find(|encounter| library::same_file(&encounter.name, name))
+    }
+
+    /// Note that combat started from the encounter called `name` at `when`. The encounter is
+    /// read back from its file first, so nothing saved there since is written over.
+    pub fn mark_played(&mut self, name: &str, when: u64) -> Result<()> {
+        if self.get(name).is_none() {
+            bail!("There's no saved encounter called {}", name);
+        }
+
+        let path = self.path(name)?;
+        let mut encounter = Campaign::read(&path)?;
+        encounter.last_played = Some(when);
+
+        self.write(&path, encounter)
+    }
+
+    /// Save a copy of the encounter called `name` to build another encounter from, and return
+    /// the copy's name. The copy hasn't been played yet.
+    pub fn duplicate(&mut self, name: &str) -> Result<String> {
+        let original = self
+            .get(name)
+            .with_context(|| format!("There's no saved encounter called {}", name))?;
+
+        let copy_name = (1..)
+            .map(|number| match number {
+                1 => format!("{} (copy)", original.name),
+                _ => format!("{} (copy {})", original.name, number),
+            })
+            .find(|candidate| self.get(candidate).is_none())
+            .unwrap_or_default();
+        let copy = SavedEncounter {
+            name: copy_name.clone(),
+            last_played: None,
+            ..original.clone()
+        };
+        self.save(copy)?;
+
+        Ok(copy_name)
+    }
+
+    /// Where to save the summary of a combat started from the encounter called `name`: next to
+    /// the encounter, as Markdown.
+    pub fn summary_path(&self, name: &str) -> Result<PathBuf> {
+        Ok(self.path(name)?.with_extension("summary.md"))
+    }
+
+    /// Where the encounter called `name` is saved.
+    fn path(&self, name: &str) -> Result<PathBuf> {
+        let dir = self
+            .dir
+            .as_ref()
+            .context("There's no campaign directory to save to")?;
+        Ok(dir
+            .join("encounters")
+            .join(format!("{}.json", library::file_stem(name)?)))
+    }
+
+    fn read(path: &Path) -> Result<SavedEncounter> {
+        let json = fs::read_to_string(path)
+            .with_context(|| format!("Couldn't read {}", path.display()))?;
+        serde_json::from_str(&json)
+            .with_context(|| format!("{} isn't a valid encounter", path.display()))
+    }
+
+    /// Write `encounter` to `path`, and keep it in place of any encounter it replaces.
+    fn write(&mut self, path: &Path, encounter: SavedEncounter) -> Result<()> {
+        let json = serde_json::to_string_pretty(&encounter)?;
+        fs::write(path, json).with_context(|| format!("Couldn't write {}", path.display()))?;
+
+        self.encounters
+            .retain(|existing| !library::same_file(&existing.name, &encounter.name));
+        self.encounters.push(encounter);
+        self.sort();
+
+        Ok(())
+    }
+
+    fn sort(&mut self) {
+        self.encounters
+            .sort_by_key(|encounter| encounter.name.to_lowercase());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ambush() -> SavedEncounter {
+        let mut fighter = Participant::new("Lae'zel");
+        fighter.set_level(Some(3));
+        let mut goblin = Participant::new("Goblin");
+        goblin.set_challenge_rating(Some("1/4".parse().unwrap()));
+        goblin.set_initiative_roll(0, Some(14));
+        goblin.set_hidden(true);
+
+        SavedEncounter {
+            name: String::from("Goblin ambush"),
+            participants: vec![fighter, goblin],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_save_duplicate_and_reopen_campaign() {
+        let dir = env::temp_dir().join(format!(
+            "roll-initiative-campaign-test-{}",
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        let mut campaign = Campaign::open(&dir).unwrap().0;
+
+        let path = campaign.save(ambush()).unwrap();
+        campaign.mark_played("Goblin ambush", 1_000_000).unwrap();
+        let copy = campaign.duplicate("Goblin ambush").unwrap();
+
+        assert_eq!(path, dir.join("encounters/goblin-ambush.json"));
+        assert_eq!(
+            campaign.summary_path("Goblin ambush").unwrap(),
+            dir.join("encounters/goblin-ambush.summary.md")
+        );
+        assert_eq!(copy, "Goblin ambush (copy)");
+        assert_eq!(
+            campaign.duplicate("Goblin ambush").unwrap(),
+            "Goblin ambush (copy 2)"
+        );
+        let clash = SavedEncounter {
+            name: String::from("Goblin-ambush"),
+            ..ambush()
+        };
+        assert!(campaign.save(clash).is_err());
+        let reopened = Campaign::open(&dir).unwrap().0;
+        assert_eq!(reopened, campaign);
+        assert_eq!(reopened.encounters.len(), 3);
+        assert_eq!(reopened.encounters[0], {
+            let mut played = ambush();
+            played.last_played = Some(1_000_000);
+            played
+        });
+        assert_eq!(reopened.encounters[1].last_played, None);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_marking_played_keeps_what_was_saved_since() {
+        let dir = env::temp_dir().join(format!(
+            "roll-initiative-campaign-played-test-{}",
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        let mut campaign = Campaign::open(&dir).unwrap().0;
+        campaign.save(ambush()).unwrap();
+        let mut stale = campaign.clone();
+
+        let mut reinforced = ambush();
+        reinforced.participants.push(Participant::new("Bugbear"));
+        campaign.save(reinforced.clone()).unwrap();
+        stale.mark_played("Goblin ambush", 1_000_000).unwrap();
+
+        reinforced.last_played = Some(1_000_000);
+        assert_eq!(stale.encounters, vec![reinforced.clone()]);
+        assert_eq!(Campaign::open(&dir).unwrap().0.encounters, vec![reinforced]);
+        assert!(stale.mark_played("Dragon's lair", 1_000_000).is_err());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_unreadable_encounters_are_left_out() {
+        let dir = env::temp_dir().join(format!(
+            "roll-initiative-campaign-unreadable-test-{}",
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        Campaign::open(&dir).unwrap().0.save(ambush()).unwrap();
+        fs::write(dir.join("encounters/broken.json"), "{\"name\": ").unwrap();
+
+        let (campaign, warnings) = Campaign::open(&dir).unwrap();
+
+        assert_eq!(campaign.encounters, vec![ambush()]);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("broken.json isn't a valid encounter"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_encounter_metadata() {
+        let encounter = SavedEncounter {
+            last_played: Some(SECONDS_PER_DAY),
+            ..ambush()
+        };
+
+        assert_eq!(encounter.combatant_count(), 2);
+        assert_eq!(encounter.difficulty(), Some(Difficulty::Easy));
+        assert_eq!(encounter.last_played_text(SECONDS_PER_DAY + 60), "Today");
+        assert_eq!(encounter.last_played_text(SECONDS_PER_DAY * 2), "Yesterday");
+        assert_eq!(
+            encounter.last_played_text(SECONDS_PER_DAY * 5),
+            "4 days ago"
+        );
+        assert_eq!(ambush().last_played_text(0), "Never");
+    }
+}
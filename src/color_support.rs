@@ -0,0 +1,216 @@
+use core::fmt;
+use std::{
+    env,
+    fmt::{Display, Formatter},
+    str::FromStr,
+};
+
+use ratatui::style::Color;
+
+/// The 16 colors every color terminal has, with the values xterm gives them.
+const ANSI_COLORS: [(Color, (u8, u8, u8)); 16] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::Red, (205, 0, 0)),
+    (Color::Green, (0, 205, 0)),
+    (Color::Yellow, (205, 205, 0)),
+    (Color::Blue, (0, 0, 238)),
+    (Color::Magenta, (205, 0, 205)),
+    (Color::Cyan, (0, 205, 205)),
+    (Color::Gray, (229, 229, 229)),
+    (Color::DarkGray, (127, 127, 127)),
+    (Color::LightRed, (255, 0, 0)),
+    (Color::LightGreen, (0, 255, 0)),
+    (Color::LightYellow, (255, 255, 0)),
+    (Color::LightBlue, (92, 92, 255)),
+    (Color::LightMagenta, (255, 0, 255)),
+    (Color::LightCyan, (0, 255, 255)),
+    (Color::White, (255, 255, 255)),
+];
+
+/// The levels of each channel in the 6×6×6 color cube of 256 color terminals.
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// How many colors the terminal can show.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ColorSupport {
+    /// Any RGB color.
+    #[default]
+    TrueColor,
+    /// The 256 color palette.
+    Indexed256,
+    /// Only the 16 basic colors.
+    Ansi16,
+    /// No colors at all, so emphasis has to come from bold, reversed and underlined text.
+    Monochrome,
+}
+
+impl ColorSupport {
+    /// Work out what the terminal supports from the environment.
+    pub fn detect() -> Self {
+        ColorSupport::from_vars(|name| env::var(name).ok())
+    }
+
+    /// `NO_COLOR` turns colors off whatever the terminal supports. Otherwise `COLORTERM` says
+    /// whether the terminal takes any RGB color, and `TERM` says whether it has 256 colors.
+    pub fn from_vars(var: impl Fn(&str) -> Option<String>) -> Self {
+        if var("NO_COLOR").is_some_and(|value| !value.is_empty()) {
+            return ColorSupport::Monochrome;
+        }
+        if var("COLORTERM").is_some_and(|value| value == "truecolor" || value == "24bit") {
+            return ColorSupport::TrueColor;
+        }
+
+        match var("TERM") {
+            Some(term) if term == "dumb" => ColorSupport::Monochrome,
+            Some(term) if term.contains("256color") => ColorSupport::Indexed256,
+            _ => ColorSupport::Ansi16,
+        }
+    }
+
+    /// The closest color to `color` that the terminal can show. With no colors at all, that's
+    /// the terminal's own.
+    pub fn adapt(&self, color: Color) -> Color {
+        match (self, color) {
+            (ColorSupport::Monochrome, _) => Color::Reset,
+            (ColorSupport::TrueColor, _) => color,
+            (ColorSupport::Indexed256, Color::Rgb(r, g, b)) => Color::Indexed(nearest_256(r, g, b)),
+            (ColorSupport::Ansi16, Color::Rgb(r, g, b)) => nearest_ansi(r, g, b),
+            (ColorSupport::Ansi16, Color::Indexed(index)) => {
+                let (r, g, b) = indexed_rgb(index);
+                nearest_ansi(r, g, b)
+            }
+            _ => color,
+        }
+    }
+}
+
+impl Display for ColorSupport {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            ColorSupport::TrueColor => write!(f, "truecolor"),
+            ColorSupport::Indexed256 => write!(f, "256"),
+            ColorSupport::Ansi16 => write!(f, "16"),
+            ColorSupport::Monochrome => write!(f, "none"),
+        }
+    }
+}
+
+impl FromStr for ColorSupport {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "truecolor" | "24bit" => Ok(ColorSupport::TrueColor),
+            "256" => Ok(ColorSupport::Indexed256),
+            "16" => Ok(ColorSupport::Ansi16),
+            "none" | "monochrome" => Ok(ColorSupport::Monochrome),
+            _ => Err(format!("\"{}\" isn't truecolor, 256, 16 or none", s)),
+        }
+    }
+}
+
+fn distance((r1, g1, b1): (u8, u8, u8), (r2, g2, b2): (u8, u8, u8)) -> u32 {
+    [(r1, r2), (g1, g2), (b1, b2)]
+        .iter()
+        .map(|(a, b)| (*a as i32 - *b as i32).pow(2) as u32)
+        .sum()
+}
+
+fn nearest_ansi(r: u8, g: u8, b: u8) -> Color {
+    ANSI_COLORS
+        .iter()
+        .min_by_key(|(_, rgb)| distance(*rgb, (r, g, b)))
+        .map(|(color, _)| *color)
+        .unwrap_or(Color::Reset)
+}
+
+/// The closer of the nearest color in the color cube and the nearest shade of gray.
+fn nearest_256(r: u8, g: u8, b: u8) -> u8 {
+    let level = |channel: u8| {
+        (0..CUBE_LEVELS.len())
+            .min_by_key(|&level| (CUBE_LEVELS[level] as i32 - channel as i32).abs())
+            .unwrap_or_default() as u8
+    };
+    let cube = 16 + 36 * level(r) + 6 * level(g) + level(b);
+
+    let average = (r as u32 + g as u32 + b as u32) / 3;
+    let gray = 232 + ((average.saturating_sub(3) / 10).min(23)) as u8;
+
+    match distance(indexed_rgb(gray), (r, g, b)) < distance(indexed_rgb(cube), (r, g, b)) {
+        true => gray,
+        false => cube,
+    }
+}
+
+/// What a color in the 256 color palette looks like.
+fn indexed_rgb(index: u8) -> (u8, u8, u8) {
+    match index {
+        0..=15 => ANSI_COLORS[index as usize].1,
+        16..=231 => {
+            let index = index - 16;
+            (
+                CUBE_LEVELS[(index / 36) as usize],
+                CUBE_LEVELS[(index / 6 % 6) as usize],
+                CUBE_LEVELS[(index % 6) as usize],
+            )
+        }
+        _ => {
+            let level = 8 + (index - 232) * 10;
+            (level, level, level)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_from_environment() {
+        let detect = |vars: &[(&str, &str)]| {
+            ColorSupport::from_vars(|name| {
+                vars.iter()
+                    .find(|(var, _)| *var == name)
+                    .map(|(_, value)| value.to_string())
+            })
+        };
+
+        assert_eq!(
+            detect(&[("COLORTERM", "truecolor")]),
+            ColorSupport::TrueColor
+        );
+        assert_eq!(
+            detect(&[("TERM", "xterm-256color")]),
+            ColorSupport::Indexed256
+        );
+        assert_eq!(detect(&[("TERM", "xterm")]), ColorSupport::Ansi16);
+        assert_eq!(detect(&[("TERM", "dumb")]), ColorSupport::Monochrome);
+        assert_eq!(
+            detect(&[("NO_COLOR", "1"), ("COLORTERM", "truecolor")]),
+            ColorSupport::Monochrome
+        );
+        // An empty NO_COLOR doesn't count
+        assert_eq!(
+            detect(&[("NO_COLOR", ""), ("COLORTERM", "24bit")]),
+            ColorSupport::TrueColor
+        );
+    }
+
+    #[test]
+    fn test_colors_map_to_the_nearest_in_the_palette() {
+        let amber = Color::Rgb(255, 191, 0);
+        let slate = Color::Rgb(68, 68, 68);
+
+        assert_eq!(ColorSupport::TrueColor.adapt(amber), amber);
+        assert_eq!(ColorSupport::Indexed256.adapt(amber), Color::Indexed(214));
+        assert_eq!(ColorSupport::Indexed256.adapt(slate), Color::Indexed(238));
+        assert_eq!(ColorSupport::Ansi16.adapt(amber), Color::Yellow);
+        assert_eq!(ColorSupport::Ansi16.adapt(slate), Color::DarkGray);
+        assert_eq!(
+            ColorSupport::Ansi16.adapt(Color::Indexed(196)),
+            Color::LightRed
+        );
+        assert_eq!(ColorSupport::Ansi16.adapt(Color::Cyan), Color::Cyan);
+        assert_eq!(ColorSupport::Monochrome.adapt(amber), Color::Reset);
+    }
+}
@@ -0,0 +1,672 @@
+use core::fmt;
+use std::{
+    fmt::{Display, Formatter},
+    path::Path,
+    str::FromStr,
+    time::Duration,
+};
+
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use crate::FRAMES_PER_SECOND;
+
+/// Something that happened during combat, worth keeping a record of.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CombatEvent {
+    RoundStarted,
+    TurnStarted { name: String },
+    Delayed { name: String },
+    ReEntered { name: String, before: String },
+    Readied { name: String, trigger: String },
+    ReadiedActionTriggered { name: String, trigger: String },
+    ReadiedActionExpired { name: String },
+    LegendaryActionSpent { name: String, remaining: u8 },
+    Damaged { name: String, amount: u16 },
+    Healed { name: String, amount: u16 },
+    KnockedOut { name: String },
+    Died { name: String },
+    Revived { name: String },
+    Revealed { name: String },
+    ConditionApplied { name: String, condition: String },
+    ConditionRemoved { name: String, condition: String },
+    ConcentrationStarted { name: String, spell: String },
+    ConcentrationSave { name: String, dc: u16, total: i32 },
+    ConcentrationBroken { name: String, spell: String },
+    ConcentrationEnded { name: String, spell: String },
+}
+
+impl Display for CombatEvent {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            CombatEvent::RoundStarted => write!(f, "A new round begins"),
+            CombatEvent::TurnStarted { name } => write!(f, "{}'s turn", name),
+            CombatEvent::Delayed { name } => write!(f, "{} delays their turn", name),
+            CombatEvent::ReEntered { name, before } => {
+                write!(f, "{} stops delaying and acts before {}", name, before)
+            }
+            CombatEvent::Readied { name, trigger } => {
+                write!(f, "{} readies an action: {}", name, trigger)
+            }
+            CombatEvent::ReadiedActionTriggered { name, trigger } => {
+                write!(f, "{} takes their readied action: {}", name, trigger)
+            }
+            CombatEvent::ReadiedActionExpired { name } => {
+                write!(f, "{}'s readied action expires", name)
+            }
+            CombatEvent::Damaged { name, amount } => write!(f, "{} takes {} damage", name, amount),
+            CombatEvent::Healed { name, amount } => write!(f, "{} heals {}", name, amount),
+            CombatEvent::KnockedOut { name } => write!(f, "{} drops to 0 HP", name),
+            CombatEvent::Died { name } => write!(f, "{} dies", name),
+            CombatEvent::Revived { name } => write!(f, "{} is no longer dead", name),
+            CombatEvent::Revealed { name } => write!(f, "{} is revealed", name),
+            CombatEvent::ConditionApplied { name, condition } => {
+                write!(f, "{} is {}", name, condition)
+            }
+            CombatEvent::ConditionRemoved { name, condition } => {
+                write!(f, "{} is no longer {}", name, condition)
+            }
+            CombatEvent::ConcentrationStarted { name, spell } => {
+                write!(f, "{} concentrates on {}", name, spell)
+            }
+            CombatEvent::ConcentrationSave { name, dc, total } => {
+                let outcome = if *total >= *dc as i32 {
+                    "succeeds"
+                } else {
+                    "fails"
+                };
+                write!(
+                    f,
+                    "{} {} a DC {} concentration save with {}",
+                    name, outcome, dc, total
+                )
+            }
+            CombatEvent::ConcentrationBroken { name, spell } => {
+                write!(f, "{} loses concentration on {}", name, spell)
+            }
+            CombatEvent::ConcentrationEnded { name, spell } => {
+                write!(f, "{} stops concentrating on {}", name, spell)
+            }
+            CombatEvent::LegendaryActionSpent { name, remaining } => {
+                write!(f, "{} takes a legendary action ({} left)", name, remaining)
+            }
+        }
+    }
+}
+
+impl CombatEvent {
+    /// Whether `name` is mentioned in the event.
+    pub fn involves(&self, name: &str) -> bool {
+        match self {
+            CombatEvent::RoundStarted => false,
+            CombatEvent::ReEntered {
+                name: subject,
+                before,
+            } => subject == name || before == name,
+            CombatEvent::TurnStarted { name: subject }
+            | CombatEvent::Delayed { name: subject }
+            | CombatEvent::Readied { name: subject, .. }
+            | CombatEvent::ReadiedActionTriggered { name: subject, .. }
+            | CombatEvent::ReadiedActionExpired { name: subject }
+            | CombatEvent::LegendaryActionSpent { name: subject, .. }
+            | CombatEvent::Damaged { name: subject, .. }
+            | CombatEvent::Healed { name: subject, .. }
+            | CombatEvent::KnockedOut { name: subject }
+            | CombatEvent::Died { name: subject }
+            | CombatEvent::Revived { name: subject }
+            | CombatEvent::Revealed { name: subject }
+            | CombatEvent::ConditionApplied { name: subject, .. }
+            | CombatEvent::ConditionRemoved { name: subject, .. }
+            | CombatEvent::ConcentrationStarted { name: subject, .. }
+            | CombatEvent::ConcentrationSave { name: subject, .. }
+            | CombatEvent::ConcentrationBroken { name: subject, .. }
+            | CombatEvent::ConcentrationEnded { name: subject, .. } => subject == name,
+        }
+    }
+}
+
+/// A `CombatEvent`, along with the round it happened in.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CombatLogEntry {
+    pub round: u32,
+    pub event: CombatEvent,
+}
+
+impl Display for CombatLogEntry {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "R{} {}", self.round, self.event)
+    }
+}
+
+/// The formats a combat log can be exported in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TranscriptFormat {
+    Markdown,
+    PlainText,
+}
+
+impl TranscriptFormat {
+    /// Markdown for `.md` files, plain text for anything else.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("md") | Some("markdown") => TranscriptFormat::Markdown,
+            _ => TranscriptFormat::PlainText,
+        }
+    }
+}
+
+/// Write out the whole combat log, grouped by round. Anything involving the `hidden`
+/// participants, who were never revealed, is left out.
+pub fn transcript(log: &[CombatLogEntry], format: TranscriptFormat, hidden: &[String]) -> String {
+    let mut transcript = match format {
+        TranscriptFormat::Markdown => String::from("# Combat transcript\n"),
+        TranscriptFormat::PlainText => String::from("Combat transcript\n"),
+    };
+
+    for (round, entries) in &log.iter().group_by(|entry| entry.round) {
+        match format {
+            TranscriptFormat::Markdown => transcript.push_str(&format!("\n## Round {}\n\n", round)),
+            TranscriptFormat::PlainText => transcript.push_str(&format!("\nRound {}\n", round)),
+        }
+
+        for entry in entries {
+            // The round heading already says a new round began
+            if entry.event == CombatEvent::RoundStarted {
+                continue;
+            }
+            if hidden.iter().any(|name| entry.event.involves(name)) {
+                continue;
+            }
+
+            match format {
+                TranscriptFormat::Markdown => transcript.push_str(&format!("- {}\n", entry.event)),
+                TranscriptFormat::PlainText => transcript.push_str(&format!("  {}\n", entry.event)),
+            }
+        }
+    }
+
+    transcript
+}
+
+/// The parts of a combatant's turn which can each be used once.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EconomyResource {
+    Action,
+    BonusAction,
+    Reaction,
+    Movement,
+}
+
+impl EconomyResource {
+    pub const ALL: [EconomyResource; 4] = [
+        EconomyResource::Action,
+        EconomyResource::BonusAction,
+        EconomyResource::Reaction,
+        EconomyResource::Movement,
+    ];
+
+    /// One letter abbreviation, for showing the resources side by side.
+    pub fn abbreviation(&self) -> &'static str {
+        match self {
+            EconomyResource::Action => "A",
+            EconomyResource::BonusAction => "B",
+            EconomyResource::Reaction => "R",
+            EconomyResource::Movement => "M",
+        }
+    }
+}
+
+impl Display for EconomyResource {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            EconomyResource::Action => write!(f, "Action"),
+            EconomyResource::BonusAction => write!(f, "Bonus action"),
+            EconomyResource::Reaction => write!(f, "Reaction"),
+            EconomyResource::Movement => write!(f, "Movement"),
+        }
+    }
+}
+
+/// Which parts of their turn a combatant has used. The action, bonus action and movement are
+/// available again at the start of each of their turns. The reaction is the creature's, once a
+/// round however many turns it has, so it's kept on the `Combatant` and filled in from there.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ActionEconomy {
+    pub action_used: bool,
+    pub bonus_action_used: bool,
+    pub reaction_used: bool,
+    pub movement_used: bool,
+}
+
+impl ActionEconomy {
+    pub fn is_used(&self, resource: EconomyResource) -> bool {
+        match resource {
+            EconomyResource::Action => self.action_used,
+            EconomyResource::BonusAction => self.bonus_action_used,
+            EconomyResource::Reaction => self.reaction_used,
+            EconomyResource::Movement => self.movement_used,
+        }
+    }
+
+    pub fn toggle(&mut self, resource: EconomyResource) {
+        let used = match resource {
+            EconomyResource::Action => &mut self.action_used,
+            EconomyResource::BonusAction => &mut self.bonus_action_used,
+            EconomyResource::Reaction => &mut self.reaction_used,
+            EconomyResource::Movement => &mut self.movement_used,
+        };
+        *used = !*used;
+    }
+
+    pub fn reset(&mut self) {
+        *self = ActionEconomy::default();
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HitPoints {
+    pub current: u16,
+    pub max: u16,
+}
+
+/// An amount of in-game time, counted in seconds.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct GameTime {
+    pub seconds: u32,
+}
+
+impl GameTime {
+    /// Each round of combat is about six seconds of in-game time.
+    pub const SECONDS_PER_ROUND: u32 = 6;
+
+    pub fn from_rounds(rounds: u32) -> Self {
+        GameTime {
+            seconds: rounds * Self::SECONDS_PER_ROUND,
+        }
+    }
+}
+
+impl Display for GameTime {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let minutes = self.seconds / 60;
+        let seconds = self.seconds % 60;
+        match minutes / 60 {
+            0 => write!(f, "{}:{:02}", minutes, seconds),
+            hours => write!(f, "{}:{:02}:{:02}", hours, minutes % 60, seconds),
+        }
+    }
+}
+
+/// Real time, counted in frames of the main loop.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Stopwatch {
+    pub frames: u64,
+}
+
+impl Stopwatch {
+    pub fn tick(&mut self) {
+        self.frames += 1;
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        Duration::from_millis(self.frames * 1000 / FRAMES_PER_SECOND)
+    }
+
+    /// How far through `limit` the stopwatch is.
+    pub fn status(&self, limit: Duration) -> TimerStatus {
+        let elapsed = self.elapsed();
+        if elapsed >= limit {
+            TimerStatus::Expired
+        } else if elapsed * 4 >= limit * 3 {
+            TimerStatus::Warning
+        } else {
+            TimerStatus::Running
+        }
+    }
+}
+
+impl Display for Stopwatch {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let seconds = self.elapsed().as_secs();
+        write!(f, "{}:{:02}", seconds / 60, seconds % 60)
+    }
+}
+
+/// How close a turn is to running out of time. A warning is given for the last quarter.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimerStatus {
+    Running,
+    Warning,
+    Expired,
+}
+
+/// A spell being concentrated on, and how long it lasts.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Concentration {
+    pub spell: String,
+    pub started_round: u32,
+    /// The spell's duration in rounds, if it has one shorter than the rest of the fight.
+    pub duration: Option<u32>,
+}
+
+impl Concentration {
+    /// The minimum DC for a concentration saving throw.
+    pub const MIN_SAVE_DC: u16 = 10;
+
+    /// The DC of the Constitution save made to keep concentrating after taking `damage`.
+    pub fn save_dc(damage: u16) -> u16 {
+        (damage / 2).max(Self::MIN_SAVE_DC)
+    }
+
+    /// The rounds left as of `round`, counting `round` itself.
+    pub fn rounds_remaining(&self, round: u32) -> Option<u32> {
+        self.duration
+            .map(|duration| (self.started_round + duration).saturating_sub(round))
+    }
+}
+
+impl FromStr for Concentration {
+    type Err = ();
+
+    /// Parse a spell and an optional duration in rounds, such as `Hold Person, 10`.
+    /// The round it started in is filled in by the caller.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (spell, duration) = match s.rsplit_once(',') {
+            Some((spell, duration)) => (spell, Some(duration.trim().parse().map_err(|_| ())?)),
+            None => (s, None),
+        };
+
+        match spell.trim() {
+            "" => Err(()),
+            spell => Ok(Concentration {
+                spell: spell.to_string(),
+                started_round: 0,
+                duration,
+            }),
+        }
+    }
+}
+
+/// What combat keeps track of for each creature, however many turns it has.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Combatant {
+    pub name: String,
+    pub hit_points: Option<HitPoints>,
+    pub concentration: Option<Concentration>,
+    /// Real time spent on this creature's turns.
+    pub time_spent: Stopwatch,
+    /// Conditions such as Prone or Frightened, as they were typed in.
+    pub conditions: Vec<String>,
+    /// Whether the creature has used its reaction since its reaction last came back.
+    pub reaction_used: bool,
+    /// The round the reaction last came back in, at the start of the creature's first turn.
+    pub reaction_round: Option<u32>,
+}
+
+/// A legendary creature's legendary actions, which refill at the start of its turn.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LegendaryActions {
+    pub name: String,
+    pub max: u8,
+    pub remaining: u8,
+}
+
+/// A question asked during combat, which needs answering before combat carries on.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CombatPrompt {
+    /// The trigger the current combatant is readying their action for.
+    ReadyTrigger {
+        input: String,
+    },
+    /// Whether the first of `names` spends a legendary action at the end of the current turn.
+    /// The rest are asked in turn afterwards.
+    SpendLegendaryAction {
+        names: Vec<String>,
+    },
+    Damage {
+        name: String,
+        input: String,
+    },
+    Heal {
+        name: String,
+        input: String,
+    },
+    /// The spell being concentrated on, and optionally its duration in rounds.
+    Concentration {
+        name: String,
+        input: String,
+    },
+    /// The result of a Constitution save to keep concentrating. Either the total, or nothing
+    /// or just a modifier to roll a d20.
+    ConcentrationSave {
+        name: String,
+        dc: u16,
+        input: String,
+    },
+    /// A condition to apply, or to remove if the combatant already has it.
+    Condition {
+        name: String,
+        input: String,
+    },
+    /// Where to save the combat transcript before combat ends. Left empty, nothing is saved.
+    /// Holds the reason saving failed, if it did.
+    EndCombat {
+        input: String,
+        error: Option<String>,
+    },
+    /// Where to export the initiative order. Holds the reason exporting failed, if it did.
+    ExportOrder {
+        input: String,
+        error: Option<String>,
+    },
+}
+
+impl CombatPrompt {
+    /// The text typed so far, for prompts which are answered by typing.
+    pub fn input(&self) -> Option<&str> {
+        match self {
+            CombatPrompt::SpendLegendaryAction { .. } => None,
+            CombatPrompt::ReadyTrigger { input }
+            | CombatPrompt::Damage { input, .. }
+            | CombatPrompt::Heal { input, .. }
+            | CombatPrompt::Concentration { input, .. }
+            | CombatPrompt::ConcentrationSave { input, .. }
+            | CombatPrompt::Condition { input, .. }
+            | CombatPrompt::EndCombat { input, .. }
+            | CombatPrompt::ExportOrder { input, .. } => Some(input),
+        }
+    }
+
+    pub fn input_mut(&mut self) -> Option<&mut String> {
+        match self {
+            CombatPrompt::SpendLegendaryAction { .. } => None,
+            CombatPrompt::ReadyTrigger { input }
+            | CombatPrompt::Damage { input, .. }
+            | CombatPrompt::Heal { input, .. }
+            | CombatPrompt::Concentration { input, .. }
+            | CombatPrompt::ConcentrationSave { input, .. }
+            | CombatPrompt::Condition { input, .. }
+            | CombatPrompt::EndCombat { input, .. }
+            | CombatPrompt::ExportOrder { input, .. } => Some(input),
+        }
+    }
+
+    /// Whether the prompt is answered with yes or no, rather than by typing.
+    pub fn is_confirmation(&self) -> bool {
+        self.input().is_none()
+    }
+
+    /// What to show in the body of the prompt.
+    pub fn body(&self) -> String {
+        match self {
+            CombatPrompt::ReadyTrigger { input } => format!("{}_", input),
+            CombatPrompt::SpendLegendaryAction { names } => format!(
+                "Does {} take a legendary action?",
+                names.first().map(String::as_str).unwrap_or_default()
+            ),
+            CombatPrompt::Damage { input, .. } | CombatPrompt::Heal { input, .. } => {
+                format!("Amount: {}_", input)
+            }
+            CombatPrompt::Concentration { input, .. } => {
+                format!("Spell, rounds (optional): {}_", input)
+            }
+            CombatPrompt::ConcentrationSave { input, .. } => format!(
+                "Total, or a modifier like +3 to roll a d20, or nothing to roll a plain d20: {}_",
+                input
+            ),
+            CombatPrompt::Condition { input, .. } => format!("Condition: {}_", input),
+            CombatPrompt::EndCombat { input, error } => {
+                let error = error
+                    .as_ref()
+                    .map(|error| format!("Couldn't save: {}. ", error))
+                    .unwrap_or_default();
+                format!(
+                    "{}Save the transcript to (.md for Markdown, empty to skip): {}_",
+                    error, input
+                )
+            }
+            CombatPrompt::ExportOrder { input, error } => {
+                let error = error
+                    .as_ref()
+                    .map(|error| format!("Couldn't export: {}. ", error))
+                    .unwrap_or_default();
+                format!(
+                    "{}Export to (.md for Markdown, .html or .json): {}_",
+                    error, input
+                )
+            }
+        }
+    }
+}
+
+impl Display for CombatPrompt {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            CombatPrompt::ReadyTrigger { .. } => write!(f, "Ready an action for when"),
+            CombatPrompt::SpendLegendaryAction { .. } => write!(f, "Legendary action"),
+            CombatPrompt::Damage { name, .. } => write!(f, "Damage {}", name),
+            CombatPrompt::Heal { name, .. } => write!(f, "Heal {}", name),
+            CombatPrompt::Concentration { name, .. } => {
+                write!(f, "{} concentrates on", name)
+            }
+            CombatPrompt::ConcentrationSave { name, dc, .. } => {
+                write!(f, "{} makes a DC {} Constitution save", name, dc)
+            }
+            CombatPrompt::Condition { name, .. } => write!(f, "Condition on {}", name),
+            CombatPrompt::EndCombat { .. } => write!(f, "End combat"),
+            CombatPrompt::ExportOrder { .. } => write!(f, "Export initiative order"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transcript_groups_events_by_round() {
+        let log = vec![
+            CombatLogEntry {
+                round: 1,
+                event: CombatEvent::TurnStarted {
+                    name: "Gimli".to_string(),
+                },
+            },
+            CombatLogEntry {
+                round: 1,
+                event: CombatEvent::Damaged {
+                    name: "Orc".to_string(),
+                    amount: 9,
+                },
+            },
+            CombatLogEntry {
+                round: 2,
+                event: CombatEvent::RoundStarted,
+            },
+            CombatLogEntry {
+                round: 2,
+                event: CombatEvent::Died {
+                    name: "Orc".to_string(),
+                },
+            },
+        ];
+
+        assert_eq!(
+            transcript(&log, TranscriptFormat::Markdown, &[]),
+            "# Combat transcript\n\n## Round 1\n\n- Gimli's turn\n- Orc takes 9 damage\n\n\
+            ## Round 2\n\n- Orc dies\n"
+        );
+        assert_eq!(
+            transcript(&log, TranscriptFormat::PlainText, &[]),
+            "Combat transcript\n\nRound 1\n  Gimli's turn\n  Orc takes 9 damage\n\n\
+            Round 2\n  Orc dies\n"
+        );
+        assert_eq!(
+            transcript(&log, TranscriptFormat::PlainText, &["Orc".to_string()]),
+            "Combat transcript\n\nRound 1\n  Gimli's turn\n\nRound 2\n"
+        );
+    }
+
+    #[test]
+    fn test_transcript_format_from_extension() {
+        assert_eq!(
+            TranscriptFormat::from_path(Path::new("session-12.md")),
+            TranscriptFormat::Markdown
+        );
+        assert_eq!(
+            TranscriptFormat::from_path(Path::new("session-12.txt")),
+            TranscriptFormat::PlainText
+        );
+    }
+
+    #[test]
+    fn test_game_time_display() {
+        assert_eq!(GameTime::from_rounds(0).to_string(), "0:00");
+        assert_eq!(GameTime::from_rounds(10).to_string(), "1:00");
+        assert_eq!(GameTime::from_rounds(12).to_string(), "1:12");
+        assert_eq!(GameTime::from_rounds(600).to_string(), "1:00:00");
+    }
+
+    #[test]
+    fn test_stopwatch_status() {
+        let limit = Duration::from_secs(60);
+        let after = |seconds: u64| Stopwatch {
+            frames: seconds * FRAMES_PER_SECOND,
+        };
+
+        assert_eq!(after(30).status(limit), TimerStatus::Running);
+        assert_eq!(after(45).status(limit), TimerStatus::Warning);
+        assert_eq!(after(60).status(limit), TimerStatus::Expired);
+        assert_eq!(after(75).to_string(), "1:15");
+    }
+
+    #[test]
+    fn test_concentration_save_dc_is_at_least_ten() {
+        assert_eq!(Concentration::save_dc(7), 10);
+        assert_eq!(Concentration::save_dc(21), 10);
+        assert_eq!(Concentration::save_dc(23), 11);
+        assert_eq!(Concentration::save_dc(44), 22);
+    }
+
+    #[test]
+    fn test_parse_concentration() {
+        assert_eq!(
+            "Hold Person, 10".parse(),
+            Ok(Concentration {
+                spell: "Hold Person".to_string(),
+                started_round: 0,
+                duration: Some(10),
+            })
+        );
+        assert_eq!(
+            "Bless".parse(),
+            Ok(Concentration {
+                spell: "Bless".to_string(),
+                started_round: 0,
+                duration: None,
+            })
+        );
+        assert_eq!(" , 10".parse::<Concentration>(), Err(()));
+        assert_eq!("Bless, soon".parse::<Concentration>(), Err(()));
+    }
+}
@@ -3,10 +3,72 @@ use std::fmt::{Display, Formatter};
 
 use crossterm::event::KeyCode;
 
-use crate::app::{AppMode, QuittingState, RunMode};
+use crate::{
+    app::{
+        AppMode, CombatSummaryState, EditingEncounterState, EncounterBrowserState, QuittingState,
+        RunMode, RunningCombatState, Tabs, Workspace,
+    },
+    combat::{CombatPrompt, EconomyResource},
+};
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum AppCommand {
+    FocusNextRow,
+    FocusPrevRow,
+    FocusNextColumn,
+    FocusPrevColumn,
+    ToggleEditing,
+    CancelEditing,
+    InputChar(char),
+    InputBackspace,
+    AddParticipant,
+    RemoveParticipant,
+    AddInitiativeSlot,
+    RemoveInitiativeSlot,
+    AddLairAction,
+    OpenEncounter,
+    NewEncounter,
+    DuplicateEncounter,
+    SaveEncounter,
+    BrowseEncounters,
+    ToggleHidden,
+    OpenCompendium,
+    AddFromCompendium,
+    ToggleHitPointsMode,
+    SaveToLibrary,
+    AddParty,
+    SaveParty,
+    Import,
+    DismissReport,
+    CycleInitiativeSystem,
+    CycleTurnTimeLimit,
+    StartCombat,
+    NextTurn,
+    DelayTurn,
+    ReEnterFromDelay,
+    ReadyAction,
+    TriggerReadiedAction,
+    ToggleEconomy(EconomyResource),
+    DamageFocused,
+    HealFocused,
+    ToggleConcentration,
+    ToggleCondition,
+    ToggleDead,
+    RevealFocused,
+    ScrollLogBack,
+    ScrollLogForward,
+    SubmitPrompt,
+    CancelPrompt,
+    ExportOrder,
+    EndCombat,
+    SaveSummary,
+    ReturnToEncounter,
+    NextTab,
+    PrevTab,
+    NewTab,
+    RenameTab,
+    CloseTab,
+    CycleTheme,
     Quit,
     NoOp,
 }
@@ -16,15 +78,72 @@ pub enum AppCommand {
 impl Display for AppCommand {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         match self {
+            AppCommand::FocusNextRow => write!(f, "Next row"),
+            AppCommand::FocusPrevRow => write!(f, "Prev. row"),
+            AppCommand::FocusNextColumn => write!(f, "Next column"),
+            AppCommand::FocusPrevColumn => write!(f, "Prev. column"),
+            AppCommand::ToggleEditing => write!(f, "Toggle editing"),
+            AppCommand::CancelEditing => write!(f, "Cancel editing"),
+            AppCommand::InputChar(_) | AppCommand::InputBackspace => Ok(()),
+            AppCommand::AddParticipant => write!(f, "Add"),
+            AppCommand::RemoveParticipant => write!(f, "Delete"),
+            AppCommand::AddInitiativeSlot => write!(f, "Add roll slot"),
+            AppCommand::RemoveInitiativeSlot => write!(f, "Remove roll slot"),
+            AppCommand::AddLairAction => write!(f, "Add lair action"),
+            AppCommand::OpenEncounter => write!(f, "Open encounter"),
+            AppCommand::NewEncounter => write!(f, "New encounter"),
+            AppCommand::DuplicateEncounter => write!(f, "Duplicate as template"),
+            AppCommand::SaveEncounter => write!(f, "Save encounter"),
+            AppCommand::BrowseEncounters => write!(f, "Browse encounters"),
+            AppCommand::OpenCompendium => write!(f, "Add from compendium"),
+            AppCommand::AddFromCompendium => write!(f, "Add selected"),
+            AppCommand::ToggleHitPointsMode => write!(f, "Average/rolled HP"),
+            AppCommand::SaveToLibrary => write!(f, "Save to library"),
+            AppCommand::AddParty => write!(f, "Add party"),
+            AppCommand::SaveParty => write!(f, "Save party"),
+            AppCommand::ToggleHidden => write!(f, "Hide/unhide"),
+            AppCommand::Import => write!(f, "Import from file"),
+            AppCommand::DismissReport => write!(f, "Close"),
+            AppCommand::CycleInitiativeSystem => write!(f, "Change initiative system"),
+            AppCommand::CycleTurnTimeLimit => write!(f, "Turn time limit"),
+            AppCommand::StartCombat => write!(f, "Start combat"),
+            AppCommand::NextTurn => write!(f, "Next turn"),
+            AppCommand::DelayTurn => write!(f, "Delay"),
+            AppCommand::ReEnterFromDelay => write!(f, "Act now"),
+            AppCommand::ReadyAction => write!(f, "Ready"),
+            AppCommand::TriggerReadiedAction => write!(f, "Trigger readied action"),
+            AppCommand::ToggleEconomy(resource) => write!(f, "Toggle {}", resource),
+            AppCommand::DamageFocused => write!(f, "Damage"),
+            AppCommand::HealFocused => write!(f, "Heal"),
+            AppCommand::ToggleConcentration => write!(f, "Concentration"),
+            AppCommand::ToggleCondition => write!(f, "Condition"),
+            AppCommand::ToggleDead => write!(f, "Mark dead"),
+            AppCommand::RevealFocused => write!(f, "Reveal"),
+            AppCommand::ScrollLogBack => write!(f, "Scroll history back"),
+            AppCommand::ScrollLogForward => write!(f, "Scroll history forward"),
+            AppCommand::SubmitPrompt => write!(f, "Submit"),
+            AppCommand::CancelPrompt => write!(f, "Cancel"),
+            AppCommand::ExportOrder => write!(f, "Export order"),
+            AppCommand::EndCombat => write!(f, "End combat"),
+            AppCommand::SaveSummary => write!(f, "Save summary"),
+            AppCommand::ReturnToEncounter => write!(f, "Back to encounter"),
+            AppCommand::NextTab => write!(f, "Next tab"),
+            AppCommand::PrevTab => write!(f, "Prev. tab"),
+            AppCommand::NewTab => write!(f, "New tab"),
+            AppCommand::RenameTab => write!(f, "Rename tab"),
+            AppCommand::CloseTab => write!(f, "Close tab"),
+            AppCommand::CycleTheme => write!(f, "Change theme"),
             AppCommand::Quit => write!(f, "Quit"),
             AppCommand::NoOp => Ok(()),
         }
     }
 }
 
+/// How many log entries the history panel scrolls by at a time.
+const LOG_SCROLL_STEP: usize = 5;
 
 /// Given the current AppState, induce action and return the resulting AppState.
-pub type StateInducer = fn(&AppMode) -> AppMode;
+pub type StateInducer = Box<dyn Fn(&AppMode) -> AppMode>;
 
 /// This block is the key mappings for the various 'modes' of the app.
 #[rustfmt::skip]
@@ -34,66 +153,625 @@ impl From<(&AppMode, Option<KeyCode>)> for AppCommand {
                 // There are no commands in the initializing or quitting states
                 AppMode::Initializing(_) | AppMode::Quitting(_) => AppCommand::NoOp,
 
-                AppMode::Running(run_mode) => match run_mode {
+                // While a tab's new name is being typed in
+                AppMode::Running(tabs) if tabs.is_renaming() => match key {
+                    KeyCode::Enter => AppCommand::SubmitPrompt,
+                    KeyCode::Esc => AppCommand::CancelPrompt,
+                    KeyCode::Backspace => AppCommand::InputBackspace,
+                    KeyCode::Char(c) => AppCommand::InputChar(c),
+                    _ => AppCommand::NoOp,
+                },
+
+                // While asking whether to close a tab with something in it
+                AppMode::Running(tabs) if tabs.is_closing() => match key {
+                    | KeyCode::Char('y')
+                    | KeyCode::Enter => AppCommand::SubmitPrompt,
+
+                    | KeyCode::Char('n')
+                    | KeyCode::Esc => AppCommand::CancelPrompt,
+
+                    _ => AppCommand::NoOp,
+                },
+
+                AppMode::Running(tabs) => match tabs.current() {
+                    RunMode::BrowsingEncounters(_state) => match key {
+                        | KeyCode::Char('j')
+                        | KeyCode::Down => AppCommand::FocusNextRow,
+
+                        | KeyCode::Char('k')
+                        | KeyCode::Up => AppCommand::FocusPrevRow,
+
+                        | KeyCode::Enter
+                        | KeyCode::Char('o') => AppCommand::OpenEncounter,
+
+                        // Start from scratch, or from a copy of the focused encounter
+                        KeyCode::Char('n') => AppCommand::NewEncounter,
+                        KeyCode::Char('c') => AppCommand::DuplicateEncounter,
+
+                        // Switch between the open encounters
+                        KeyCode::Tab => AppCommand::NextTab,
+                        KeyCode::BackTab => AppCommand::PrevTab,
+                        KeyCode::Char('N') => AppCommand::NewTab,
+                        KeyCode::Char('R') => AppCommand::RenameTab,
+                        KeyCode::Char('X') => AppCommand::CloseTab,
+                        KeyCode::Char('T') => AppCommand::CycleTheme,
+
+                        KeyCode::Char('q') => AppCommand::Quit,
+
+                        _ => AppCommand::NoOp,
+                    },
+
+                    // While the compendium is open, keys are typed into its search
+                    RunMode::EditingEncounter(state) if state.is_picking() => match key {
+                        KeyCode::Enter => AppCommand::AddFromCompendium,
+                        KeyCode::Tab => AppCommand::ToggleHitPointsMode,
+                        KeyCode::Down => AppCommand::FocusNextRow,
+                        KeyCode::Up => AppCommand::FocusPrevRow,
+                        KeyCode::Esc => AppCommand::CancelEditing,
+                        KeyCode::Backspace => AppCommand::InputBackspace,
+                        KeyCode::Char(c) => AppCommand::InputChar(c),
+                        _ => AppCommand::NoOp,
+                    },
+
+                    // The import report stays up until it's been read
+                    RunMode::EditingEncounter(state) if state.is_reporting() => match key {
+                        KeyCode::Enter | KeyCode::Esc => AppCommand::DismissReport,
+                        _ => AppCommand::NoOp,
+                    },
+
+                    // While the status line asks for tags or a file to import
+                    RunMode::EditingEncounter(state) if state.is_prompting() => match key {
+                        KeyCode::Enter => AppCommand::SubmitPrompt,
+                        KeyCode::Esc => AppCommand::CancelPrompt,
+                        KeyCode::Backspace => AppCommand::InputBackspace,
+                        KeyCode::Char(c) => AppCommand::InputChar(c),
+                        _ => AppCommand::NoOp,
+                    },
+
+                    // While a cell is being edited, keys are typed into it
+                    RunMode::EditingEncounter(state) if state.is_editing() => match key {
+                        KeyCode::Enter => AppCommand::ToggleEditing,
+                        KeyCode::Esc => AppCommand::CancelEditing,
+                        KeyCode::Backspace => AppCommand::InputBackspace,
+                        KeyCode::Char(c) => AppCommand::InputChar(c),
+                        _ => AppCommand::NoOp,
+                    },
+
                     RunMode::EditingEncounter(_state) => match key {
                         // Vim & arrow key movement
                         | KeyCode::Char('j') 
-                        | KeyCode::Down => AppCommand::NoOp,
+                        | KeyCode::Down => AppCommand::FocusNextRow,
 
                         | KeyCode::Char('k')
-                        | KeyCode::Up => AppCommand::NoOp,
+                        | KeyCode::Up => AppCommand::FocusPrevRow,
 
                         | KeyCode::Char('h')
-                        | KeyCode::Left => AppCommand::NoOp,
+                        | KeyCode::Left => AppCommand::FocusPrevColumn,
 
                         | KeyCode::Char('l')
-                        | KeyCode::Right => AppCommand::NoOp,
+                        | KeyCode::Right => AppCommand::FocusNextColumn,
 
                         // Edit the current participant row
                         | KeyCode::Enter
-                        | KeyCode::Char('e') => AppCommand::NoOp,
+                        | KeyCode::Char('e') => AppCommand::ToggleEditing,
 
                         // Add a new participant row
-                        KeyCode::Char('a') => AppCommand::NoOp,
+                        KeyCode::Char('a') => AppCommand::AddParticipant,
 
                         // Remove the current participant row
                         | KeyCode::Char('d')
                         | KeyCode::Backspace
-                        | KeyCode::Delete => AppCommand::NoOp,
+                        | KeyCode::Delete => AppCommand::RemoveParticipant,
+
+                        // Add or remove an initiative roll slot on the current participant
+                        | KeyCode::Char('+')
+                        | KeyCode::Char(']') => AppCommand::AddInitiativeSlot,
+
+                        | KeyCode::Char('-')
+                        | KeyCode::Char('[') => AppCommand::RemoveInitiativeSlot,
+
+                        // Pick monsters from the compendium
+                        KeyCode::Char('m') => AppCommand::OpenCompendium,
+
+                        // Give the encounter a lair action
+                        KeyCode::Char('L') => AppCommand::AddLairAction,
+
+                        // Keep an ambusher out of the players' sight until they're revealed
+                        KeyCode::Char('H') => AppCommand::ToggleHidden,
+
+                        // Keep the current participant for later encounters
+                        KeyCode::Char('S') => AppCommand::SaveToLibrary,
+
+                        // Bring in the regular party, or save who's here as the party
+                        KeyCode::Char('p') => AppCommand::AddParty,
+                        KeyCode::Char('P') => AppCommand::SaveParty,
+                        KeyCode::Char('I') => AppCommand::Import,
+
+                        // Choose how the turn order is worked out
+                        KeyCode::Char('i') => AppCommand::CycleInitiativeSystem,
+                        KeyCode::Char('t') => AppCommand::CycleTurnTimeLimit,
+
+                        // Keep the encounter with the campaign, or look through the others
+                        KeyCode::Char('W') => AppCommand::SaveEncounter,
+                        KeyCode::Char('B') => AppCommand::BrowseEncounters,
+
+                        // Roll for initiative!
+                        KeyCode::Char('s') => AppCommand::StartCombat,
+
+                        // Switch between the open encounters
+                        KeyCode::Tab => AppCommand::NextTab,
+                        KeyCode::BackTab => AppCommand::PrevTab,
+                        KeyCode::Char('N') => AppCommand::NewTab,
+                        KeyCode::Char('R') => AppCommand::RenameTab,
+                        KeyCode::Char('X') => AppCommand::CloseTab,
+                        KeyCode::Char('T') => AppCommand::CycleTheme,
 
                         // Quit the app
                         KeyCode::Char('q') => AppCommand::Quit,
 
                         _ => AppCommand::NoOp,
-                    }
+                    },
+
+                    // Yes or no questions are answered before anything else happens
+                    RunMode::RunningCombat(state)
+                        if state.prompt.as_ref().is_some_and(CombatPrompt::is_confirmation) =>
+                    match key {
+                        | KeyCode::Char('y')
+                        | KeyCode::Enter => AppCommand::SubmitPrompt,
+
+                        | KeyCode::Char('n')
+                        | KeyCode::Esc => AppCommand::CancelPrompt,
+
+                        _ => AppCommand::NoOp,
+                    },
+
+                    // While a prompt is open, keys are typed into it
+                    RunMode::RunningCombat(state) if state.prompt.is_some() => match key {
+                        KeyCode::Enter => AppCommand::SubmitPrompt,
+                        KeyCode::Esc => AppCommand::CancelPrompt,
+                        KeyCode::Backspace => AppCommand::InputBackspace,
+                        KeyCode::Char(c) => AppCommand::InputChar(c),
+                        _ => AppCommand::NoOp,
+                    },
+
+                    RunMode::RunningCombat(_state) => match key {
+                        | KeyCode::Char('j')
+                        | KeyCode::Down => AppCommand::FocusNextRow,
+
+                        | KeyCode::Char('k')
+                        | KeyCode::Up => AppCommand::FocusPrevRow,
+
+                        | KeyCode::Char('n')
+                        | KeyCode::Char(' ') => AppCommand::NextTurn,
+
+                        // Delay the current turn, or bring the focused delayed combatant back in
+                        KeyCode::Char('d') => AppCommand::DelayTurn,
+                        | KeyCode::Enter
+                        | KeyCode::Char('e') => AppCommand::ReEnterFromDelay,
+
+                        // Ready an action, or take the focused combatant's readied action
+                        KeyCode::Char('r') => AppCommand::ReadyAction,
+                        KeyCode::Char('t') => AppCommand::TriggerReadiedAction,
+
+                        // Mark what the focused combatant has used this turn
+                        KeyCode::Char('1') => AppCommand::ToggleEconomy(EconomyResource::Action),
+                        KeyCode::Char('2') => AppCommand::ToggleEconomy(EconomyResource::BonusAction),
+                        KeyCode::Char('3') => AppCommand::ToggleEconomy(EconomyResource::Reaction),
+                        KeyCode::Char('4') => AppCommand::ToggleEconomy(EconomyResource::Movement),
+
+                        // Hit points and concentration of the focused combatant
+                        KeyCode::Char('-') => AppCommand::DamageFocused,
+                        KeyCode::Char('+') => AppCommand::HealFocused,
+                        KeyCode::Char('c') => AppCommand::ToggleConcentration,
+                        KeyCode::Char('o') => AppCommand::ToggleCondition,
+                        KeyCode::Char('K') => AppCommand::ToggleDead,
+                        KeyCode::Char('v') => AppCommand::RevealFocused,
+
+                        KeyCode::PageUp => AppCommand::ScrollLogBack,
+                        KeyCode::PageDown => AppCommand::ScrollLogForward,
+
+                        KeyCode::Char('w') => AppCommand::ExportOrder,
+                        KeyCode::Char('x') => AppCommand::EndCombat,
+
+                        // Switch between the open encounters
+                        KeyCode::Tab => AppCommand::NextTab,
+                        KeyCode::BackTab => AppCommand::PrevTab,
+                        KeyCode::Char('N') => AppCommand::NewTab,
+                        KeyCode::Char('R') => AppCommand::RenameTab,
+                        KeyCode::Char('X') => AppCommand::CloseTab,
+                        KeyCode::Char('T') => AppCommand::CycleTheme,
+
+                        KeyCode::Char('q') => AppCommand::Quit,
+
+                        _ => AppCommand::NoOp,
+                    },
+
+                    // While the summary's file name is being typed in
+                    RunMode::CombatSummary(state) if state.input.is_some() => match key {
+                        KeyCode::Enter => AppCommand::SubmitPrompt,
+                        KeyCode::Esc => AppCommand::CancelPrompt,
+                        KeyCode::Backspace => AppCommand::InputBackspace,
+                        KeyCode::Char(c) => AppCommand::InputChar(c),
+                        _ => AppCommand::NoOp,
+                    },
+
+                    RunMode::CombatSummary(_state) => match key {
+                        KeyCode::Char('s') => AppCommand::SaveSummary,
+                        | KeyCode::Enter
+                        | KeyCode::Esc => AppCommand::ReturnToEncounter,
+
+                        // Switch between the open encounters
+                        KeyCode::Tab => AppCommand::NextTab,
+                        KeyCode::BackTab => AppCommand::PrevTab,
+                        KeyCode::Char('N') => AppCommand::NewTab,
+                        KeyCode::Char('R') => AppCommand::RenameTab,
+                        KeyCode::Char('X') => AppCommand::CloseTab,
+                        KeyCode::Char('T') => AppCommand::CycleTheme,
+
+                        KeyCode::Char('q') => AppCommand::Quit,
+                        _ => AppCommand::NoOp,
+                    },
                 },
             }).unwrap_or(AppCommand::NoOp)
     }
 }
 
+/// Apply `change` to the open tabs, if the app is running.
+fn change_tabs(state: &AppMode, change: impl Fn(&mut Tabs)) -> AppMode {
+    match state {
+        AppMode::Running(tabs) => {
+            let mut tabs = tabs.clone();
+            change(&mut tabs);
+            AppMode::Running(tabs)
+        }
+        _ => state.clone(),
+    }
+}
+
+fn is_renaming_tab(state: &AppMode) -> bool {
+    matches!(state, AppMode::Running(tabs) if tabs.is_renaming())
+}
+
+fn is_closing_tab(state: &AppMode) -> bool {
+    matches!(state, AppMode::Running(tabs) if tabs.is_closing())
+}
+
+/// Apply `action` to the encounter browser in the current tab, if it's showing.
+fn browse(state: &AppMode, action: impl Fn(&mut EncounterBrowserState, &mut Workspace)) -> AppMode {
+    change_tabs(state, |tabs| {
+        if let (RunMode::BrowsingEncounters(browser_state), workspace) =
+            tabs.current_mut_with_workspace()
+        {
+            action(browser_state, workspace);
+        }
+    })
+}
+
+/// Apply `edit` to the encounter being edited in the current tab, if there is one.
+fn edit_encounter(state: &AppMode, edit: impl Fn(&mut EditingEncounterState)) -> AppMode {
+    change_tabs(state, |tabs| {
+        if let RunMode::EditingEncounter(editing_state) = tabs.current_mut() {
+            edit(editing_state);
+        }
+    })
+}
+
+/// Like `edit_encounter`, for edits that use the campaign, library or party.
+fn edit_with_workspace(
+    state: &AppMode,
+    edit: impl Fn(&mut EditingEncounterState, &mut Workspace),
+) -> AppMode {
+    change_tabs(state, |tabs| {
+        if let (RunMode::EditingEncounter(editing_state), workspace) =
+            tabs.current_mut_with_workspace()
+        {
+            edit(editing_state, workspace);
+        }
+    })
+}
+
+/// Apply `action` to the combat being run in the current tab, if there is one.
+fn run_combat(state: &AppMode, action: impl Fn(&mut RunningCombatState)) -> AppMode {
+    change_tabs(state, |tabs| {
+        if let RunMode::RunningCombat(combat_state) = tabs.current_mut() {
+            action(combat_state);
+        }
+    })
+}
+
+/// Apply `action` to the summary of the combat that just ended, if the current tab shows it.
+fn summarize(state: &AppMode, action: impl Fn(&mut CombatSummaryState)) -> AppMode {
+    change_tabs(state, |tabs| {
+        if let RunMode::CombatSummary(summary_state) = tabs.current_mut() {
+            action(summary_state);
+        }
+    })
+}
+
 impl From<AppCommand> for StateInducer {
     fn from(value: AppCommand) -> Self {
         match value {
-            AppCommand::Quit => |state: &AppMode| match state {
+            AppCommand::FocusNextRow => Box::new(|state: &AppMode| {
+                let state = browse(state, |browser, workspace| {
+                    browser.focus_next_row(&workspace.campaign)
+                });
+                let state = edit_with_workspace(&state, |editing, workspace| {
+                    editing.focus_next_row(&workspace.library)
+                });
+                run_combat(&state, RunningCombatState::focus_next_row)
+            }),
+            AppCommand::FocusPrevRow => Box::new(|state: &AppMode| {
+                let state = browse(state, |browser, _| browser.focus_prev_row());
+                let state = edit_encounter(&state, EditingEncounterState::focus_prev_row);
+                run_combat(&state, RunningCombatState::focus_prev_row)
+            }),
+            AppCommand::FocusNextColumn => Box::new(|state: &AppMode| {
+                edit_encounter(state, EditingEncounterState::focus_next_column)
+            }),
+            AppCommand::FocusPrevColumn => Box::new(|state: &AppMode| {
+                edit_encounter(state, EditingEncounterState::focus_prev_column)
+            }),
+            AppCommand::ToggleEditing => Box::new(|state: &AppMode| {
+                edit_encounter(state, EditingEncounterState::toggle_editing)
+            }),
+            AppCommand::CancelEditing => Box::new(|state: &AppMode| {
+                edit_encounter(state, EditingEncounterState::cancel_editing)
+            }),
+            AppCommand::InputChar(c) => Box::new(move |state: &AppMode| {
+                if is_renaming_tab(state) {
+                    return change_tabs(state, |tabs| tabs.rename_input_char(c));
+                }
+                let state = edit_encounter(state, |editing| editing.input_char(c));
+                let state = run_combat(&state, |combat| combat.input_char(c));
+                summarize(&state, |summary| summary.input_char(c))
+            }),
+            AppCommand::InputBackspace => Box::new(|state: &AppMode| {
+                if is_renaming_tab(state) {
+                    return change_tabs(state, Tabs::rename_input_backspace);
+                }
+                let state = edit_encounter(state, EditingEncounterState::input_backspace);
+                let state = run_combat(&state, RunningCombatState::input_backspace);
+                summarize(&state, CombatSummaryState::input_backspace)
+            }),
+            AppCommand::AddParticipant => Box::new(|state: &AppMode| {
+                edit_encounter(state, EditingEncounterState::add_participant)
+            }),
+            AppCommand::RemoveParticipant => Box::new(|state: &AppMode| {
+                edit_encounter(state, EditingEncounterState::remove_focused_participant)
+            }),
+            AppCommand::AddInitiativeSlot => Box::new(|state: &AppMode| {
+                edit_encounter(state, EditingEncounterState::add_initiative_slot)
+            }),
+            AppCommand::RemoveInitiativeSlot => Box::new(|state: &AppMode| {
+                edit_encounter(state, EditingEncounterState::remove_initiative_slot)
+            }),
+            AppCommand::AddLairAction => Box::new(|state: &AppMode| {
+                edit_encounter(state, EditingEncounterState::add_lair_action)
+            }),
+            AppCommand::OpenEncounter => Box::new(|state: &AppMode| {
+                change_tabs(state, |tabs| {
+                    if let RunMode::BrowsingEncounters(browser_state) = tabs.current() {
+                        if let Some(encounter) =
+                            browser_state.open_focused(&tabs.workspace.campaign)
+                        {
+                            tabs.open_encounter(encounter);
+                        }
+                    }
+                })
+            }),
+            AppCommand::NewEncounter => Box::new(|state: &AppMode| {
+                change_tabs(state, |tabs| {
+                    if let RunMode::BrowsingEncounters(browser_state) = tabs.current() {
+                        let encounter = browser_state.new_encounter();
+                        tabs.open_encounter(encounter);
+                    }
+                })
+            }),
+            AppCommand::DuplicateEncounter => Box::new(|state: &AppMode| {
+                browse(state, |browser, workspace| {
+                    browser.duplicate_focused(&mut workspace.campaign)
+                })
+            }),
+            AppCommand::SaveEncounter => Box::new(|state: &AppMode| {
+                edit_encounter(state, EditingEncounterState::begin_save_encounter)
+            }),
+            AppCommand::BrowseEncounters => {
+                Box::new(|state: &AppMode| change_tabs(state, Tabs::open_browser))
+            }
+            AppCommand::OpenCompendium => Box::new(|state: &AppMode| {
+                edit_encounter(state, EditingEncounterState::open_picker)
+            }),
+            AppCommand::AddFromCompendium => Box::new(|state: &AppMode| {
+                edit_with_workspace(state, |editing, workspace| {
+                    editing.add_picked_monster(&workspace.library)
+                })
+            }),
+            AppCommand::ToggleHitPointsMode => Box::new(|state: &AppMode| {
+                edit_encounter(state, EditingEncounterState::toggle_hit_points_mode)
+            }),
+            AppCommand::SaveToLibrary => Box::new(|state: &AppMode| {
+                edit_with_workspace(state, |editing, workspace| {
+                    editing.begin_save_template(&workspace.library)
+                })
+            }),
+            AppCommand::AddParty => Box::new(|state: &AppMode| {
+                edit_with_workspace(state, |editing, workspace| {
+                    editing.add_party(&workspace.party)
+                })
+            }),
+            AppCommand::SaveParty => Box::new(|state: &AppMode| {
+                edit_with_workspace(state, |editing, workspace| {
+                    editing.save_party(&mut workspace.party)
+                })
+            }),
+            AppCommand::ToggleHidden => Box::new(|state: &AppMode| {
+                edit_encounter(state, EditingEncounterState::toggle_hidden)
+            }),
+            AppCommand::Import => Box::new(|state: &AppMode| {
+                edit_encounter(state, EditingEncounterState::begin_import)
+            }),
+            AppCommand::DismissReport => Box::new(|state: &AppMode| {
+                edit_encounter(state, EditingEncounterState::dismiss_report)
+            }),
+            AppCommand::CycleInitiativeSystem => Box::new(|state: &AppMode| {
+                edit_encounter(state, EditingEncounterState::cycle_initiative_system)
+            }),
+            AppCommand::CycleTurnTimeLimit => Box::new(|state: &AppMode| {
+                edit_encounter(state, EditingEncounterState::cycle_turn_time_limit)
+            }),
+            AppCommand::StartCombat => Box::new(|state: &AppMode| {
+                change_tabs(state, |tabs| {
+                    let (mode, workspace) = tabs.current_mut_with_workspace();
+                    if let RunMode::EditingEncounter(editing_state) = mode {
+                        if let Some(combat_state) = RunningCombatState::new(editing_state) {
+                            // Not being able to note when it was played is no reason to hold
+                            // up combat, so any error is left unsaid
+                            editing_state.mark_played(&mut workspace.campaign);
+                            *mode = RunMode::RunningCombat(Box::new(combat_state));
+                        }
+                    }
+                })
+            }),
+            AppCommand::NextTurn => {
+                Box::new(|state: &AppMode| run_combat(state, RunningCombatState::next_turn))
+            }
+            AppCommand::DelayTurn => Box::new(|state: &AppMode| {
+                run_combat(state, RunningCombatState::delay_current_turn)
+            }),
+            AppCommand::ReEnterFromDelay => Box::new(|state: &AppMode| {
+                run_combat(state, RunningCombatState::re_enter_focused_delayed)
+            }),
+            AppCommand::ReadyAction => {
+                Box::new(|state: &AppMode| run_combat(state, RunningCombatState::begin_ready))
+            }
+            AppCommand::TriggerReadiedAction => Box::new(|state: &AppMode| {
+                run_combat(state, RunningCombatState::trigger_focused_readied_action)
+            }),
+            AppCommand::ToggleEconomy(resource) => Box::new(move |state: &AppMode| {
+                run_combat(state, |combat| combat.toggle_focused_economy(resource))
+            }),
+            AppCommand::DamageFocused => {
+                Box::new(|state: &AppMode| run_combat(state, RunningCombatState::begin_damage))
+            }
+            AppCommand::HealFocused => {
+                Box::new(|state: &AppMode| run_combat(state, RunningCombatState::begin_heal))
+            }
+            AppCommand::ToggleConcentration => Box::new(|state: &AppMode| {
+                run_combat(state, RunningCombatState::toggle_focused_concentration)
+            }),
+            AppCommand::ToggleCondition => {
+                Box::new(|state: &AppMode| run_combat(state, RunningCombatState::begin_condition))
+            }
+            AppCommand::ToggleDead => Box::new(|state: &AppMode| {
+                run_combat(state, RunningCombatState::toggle_focused_dead)
+            }),
+            AppCommand::RevealFocused => {
+                Box::new(|state: &AppMode| run_combat(state, RunningCombatState::reveal_focused))
+            }
+            AppCommand::ScrollLogBack => Box::new(|state: &AppMode| {
+                run_combat(state, |combat| combat.scroll_log_back(LOG_SCROLL_STEP))
+            }),
+            AppCommand::ScrollLogForward => Box::new(|state: &AppMode| {
+                run_combat(state, |combat| combat.scroll_log_forward(LOG_SCROLL_STEP))
+            }),
+            AppCommand::SubmitPrompt => Box::new(|state: &AppMode| {
+                if is_renaming_tab(state) {
+                    return change_tabs(state, Tabs::submit_rename);
+                }
+                if is_closing_tab(state) {
+                    return change_tabs(state, Tabs::confirm_close);
+                }
+
+                // Answering the end of combat prompt is what finally ends it
+                let next = run_combat(state, RunningCombatState::submit_prompt);
+                let next = change_tabs(&next, |tabs| {
+                    let (mode, workspace) = tabs.current_mut_with_workspace();
+                    if let RunMode::RunningCombat(combat_state) = mode {
+                        if combat_state.finished {
+                            let summary = CombatSummaryState::new(combat_state, workspace);
+                            *mode = RunMode::CombatSummary(Box::new(summary));
+                        }
+                    }
+                });
+                let next = summarize(&next, CombatSummaryState::submit_save);
+                edit_with_workspace(&next, EditingEncounterState::submit_prompt)
+            }),
+            AppCommand::CancelPrompt => Box::new(|state: &AppMode| {
+                if is_renaming_tab(state) {
+                    return change_tabs(state, Tabs::cancel_rename);
+                }
+                if is_closing_tab(state) {
+                    return change_tabs(state, Tabs::cancel_close);
+                }
+                let state = run_combat(state, RunningCombatState::cancel_prompt);
+                let state = summarize(&state, CombatSummaryState::cancel_save);
+                edit_encounter(&state, EditingEncounterState::cancel_editing)
+            }),
+            AppCommand::ExportOrder => {
+                Box::new(|state: &AppMode| run_combat(state, RunningCombatState::begin_export))
+            }
+            AppCommand::EndCombat => {
+                Box::new(|state: &AppMode| run_combat(state, RunningCombatState::begin_end_combat))
+            }
+            AppCommand::SaveSummary => {
+                Box::new(|state: &AppMode| summarize(state, CombatSummaryState::begin_save))
+            }
+            AppCommand::ReturnToEncounter => Box::new(|state: &AppMode| {
+                change_tabs(state, |tabs| {
+                    if let RunMode::CombatSummary(summary_state) = tabs.current() {
+                        let encounter = summary_state.encounter.clone();
+                        *tabs.current_mut() = RunMode::EditingEncounter(Box::new(encounter));
+                    }
+                })
+            }),
+            AppCommand::NextTab => Box::new(|state: &AppMode| change_tabs(state, Tabs::next_tab)),
+            AppCommand::PrevTab => Box::new(|state: &AppMode| change_tabs(state, Tabs::prev_tab)),
+            AppCommand::NewTab => Box::new(|state: &AppMode| change_tabs(state, Tabs::new_tab)),
+            AppCommand::RenameTab => {
+                Box::new(|state: &AppMode| change_tabs(state, Tabs::begin_rename))
+            }
+            AppCommand::CloseTab => Box::new(|state: &AppMode| change_tabs(state, Tabs::close_tab)),
+            AppCommand::CycleTheme => Box::new(|state: &AppMode| {
+                // The theme is the whole app's, not any one tab's
+                change_tabs(state, |tabs| tabs.themes.cycle())
+            }),
+            AppCommand::Quit => Box::new(|state: &AppMode| match state {
                 AppMode::Quitting(quitting_state) => AppMode::Quitting(quitting_state.clone()),
-                _ => AppMode::Quitting(QuittingState::default()),
-            },
+                AppMode::Running(tabs) => AppMode::Quitting(QuittingState {
+                    themes: tabs.themes.clone(),
+                    ..Default::default()
+                }),
+                AppMode::Initializing(state) => AppMode::Quitting(QuittingState {
+                    themes: state.themes.clone(),
+                    ..Default::default()
+                }),
+            }),
             AppCommand::NoOp => {
                 // Do nothing
-                |state: &AppMode| state.clone()
+                Box::new(|state: &AppMode| state.clone())
             }
         }
     }
 }
 
+#[cfg(test)]
 mod test {
     use super::*;
-    use crate::app::*;
+    use crate::{
+        app::*,
+        party::{Party, PartyMember},
+    };
+
+    fn current_tab(app_mode: &AppMode) -> &RunMode {
+        let AppMode::Running(tabs) = app_mode else {
+            panic!("Expected the app to be running");
+        };
+        tabs.current()
+    }
 
     #[test]
     fn test_app_command_from() {
-        let app_mode =
-            AppMode::Running(RunMode::EditingEncounter(EditingEncounterState::default()));
+        let app_mode = AppMode::Running(RunMode::EditingEncounter(Box::default()).into());
         let key = Some(KeyCode::Char('q'));
         let app_command = AppCommand::from((&app_mode, key));
         assert_eq!(app_command, AppCommand::Quit);
@@ -101,8 +779,7 @@ mod test {
 
     #[test]
     fn test_app_command_from_none() {
-        let app_mode =
-            AppMode::Running(RunMode::EditingEncounter(EditingEncounterState::default()));
+        let app_mode = AppMode::Running(RunMode::EditingEncounter(Box::default()).into());
         let key = None;
         let app_command = AppCommand::from((&app_mode, key));
         assert_eq!(app_command, AppCommand::NoOp);
@@ -112,8 +789,7 @@ mod test {
     fn test_state_inducer_from() {
         let app_command = AppCommand::Quit;
         let state_inducer = StateInducer::from(app_command);
-        let app_mode =
-            AppMode::Running(RunMode::EditingEncounter(EditingEncounterState::default()));
+        let app_mode = AppMode::Running(RunMode::EditingEncounter(Box::default()).into());
         let new_app_mode = state_inducer(&app_mode);
         assert_eq!(new_app_mode, AppMode::Quitting(QuittingState::default()));
     }
@@ -122,8 +798,7 @@ mod test {
     fn test_noop_induces_no_change() {
         let app_command = AppCommand::NoOp;
         let state_inducer = StateInducer::from(app_command);
-        let app_mode =
-            AppMode::Running(RunMode::EditingEncounter(EditingEncounterState::default()));
+        let app_mode = AppMode::Running(RunMode::EditingEncounter(Box::default()).into());
         let new_app_mode = state_inducer(&app_mode);
         assert_eq!(new_app_mode, app_mode);
     }
@@ -132,9 +807,166 @@ mod test {
     fn test_quit_induces_quitting() {
         let app_command = AppCommand::Quit;
         let state_inducer = StateInducer::from(app_command);
-        let app_mode =
-            AppMode::Running(RunMode::EditingEncounter(EditingEncounterState::default()));
+        let app_mode = AppMode::Running(RunMode::EditingEncounter(Box::default()).into());
         let new_app_mode = state_inducer(&app_mode);
         assert_eq!(new_app_mode, AppMode::Quitting(QuittingState::default()));
     }
+
+    #[test]
+    fn test_keys_are_typed_while_editing() {
+        let app_mode = AppMode::Running(
+            RunMode::EditingEncounter(Box::new(EditingEncounterState {
+                input: Some(String::new()),
+                ..Default::default()
+            }))
+            .into(),
+        );
+        let key = Some(KeyCode::Char('q'));
+        let app_command = AppCommand::from((&app_mode, key));
+        assert_eq!(app_command, AppCommand::InputChar('q'));
+    }
+
+    #[test]
+    fn test_add_initiative_slot_induces_new_slot() {
+        let app_mode = AppMode::Running(
+            RunMode::EditingEncounter(Box::new(EditingEncounterState {
+                participants: vec![Participant::new("Gandalf")],
+                focused_row: Some(0),
+                ..Default::default()
+            }))
+            .into(),
+        );
+        let state_inducer = StateInducer::from(AppCommand::AddInitiativeSlot);
+        let new_app_mode = state_inducer(&app_mode);
+
+        let RunMode::EditingEncounter(state) = current_tab(&new_app_mode) else {
+            panic!("Expected to still be editing the encounter");
+        };
+        assert_eq!(state.participants[0].initiative_rolls(), &[None, None]);
+    }
+
+    #[test]
+    fn test_end_combat_shows_summary_then_returns_to_editor() {
+        let mut encounter = EditingEncounterState::default();
+        encounter.participants.push("Gandalf".parse().unwrap());
+        encounter.participants[0].set_initiative_roll(0, Some(12));
+        let combat = RunningCombatState::new(&encounter).unwrap();
+        let app_mode = AppMode::Running(RunMode::RunningCombat(Box::new(combat)).into());
+
+        let asking = StateInducer::from(AppCommand::EndCombat)(&app_mode);
+        let RunMode::RunningCombat(combat) = current_tab(&asking) else {
+            panic!("Expected combat to wait for an answer");
+        };
+        assert!(combat.prompt.is_some());
+
+        let ended = StateInducer::from(AppCommand::SubmitPrompt)(&asking);
+        assert!(matches!(current_tab(&ended), RunMode::CombatSummary(_)));
+
+        let back = StateInducer::from(AppCommand::ReturnToEncounter)(&ended);
+        let RunMode::EditingEncounter(encounter) = current_tab(&back) else {
+            panic!("Expected to be back to editing the encounter");
+        };
+        assert_eq!(encounter.participants.len(), 1);
+    }
+
+    #[test]
+    fn test_tabs_keep_their_own_encounters() {
+        let mut encounter = EditingEncounterState::default();
+        encounter.participants.push("Gandalf".parse().unwrap());
+        encounter.participants[0].set_initiative_roll(0, Some(12));
+        let app_mode = AppMode::Running(RunMode::EditingEncounter(Box::new(encounter)).into());
+
+        let fighting = StateInducer::from(AppCommand::StartCombat)(&app_mode);
+        let opened = StateInducer::from(AppCommand::NewTab)(&fighting);
+        let RunMode::EditingEncounter(encounter) = current_tab(&opened) else {
+            panic!("Expected a new encounter to be edited");
+        };
+        assert!(encounter.participants.is_empty());
+
+        // Tab goes back to the first tab, where combat is still going
+        let key = AppCommand::from((&opened, Some(KeyCode::Tab)));
+        let switched = StateInducer::from(key)(&opened);
+        assert!(matches!(current_tab(&switched), RunMode::RunningCombat(_)));
+
+        let mut renamed = StateInducer::from(AppCommand::RenameTab)(&switched);
+        assert_eq!(
+            AppCommand::from((&renamed, Some(KeyCode::Char('q')))),
+            AppCommand::InputChar('q')
+        );
+        for _ in 0.."Encounter 1".len() {
+            renamed = StateInducer::from(AppCommand::InputBackspace)(&renamed);
+        }
+        for c in "Bridge".chars() {
+            renamed = StateInducer::from(AppCommand::InputChar(c))(&renamed);
+        }
+        let renamed = StateInducer::from(AppCommand::SubmitPrompt)(&renamed);
+        let AppMode::Running(tabs) = &renamed else {
+            panic!("Expected the app to be running");
+        };
+        assert_eq!(tabs.tabs[0].name, "Bridge");
+        assert_eq!(tabs.tabs[1].name, "Encounter 2");
+
+        // Closing the tab would end its combat, so that's asked about first
+        let asking = StateInducer::from(AppCommand::CloseTab)(&renamed);
+        assert_eq!(
+            AppCommand::from((&asking, Some(KeyCode::Char('n')))),
+            AppCommand::CancelPrompt
+        );
+        let kept = StateInducer::from(AppCommand::CancelPrompt)(&asking);
+        assert_eq!(kept, renamed);
+
+        let asking = StateInducer::from(AppCommand::CloseTab)(&kept);
+        let closed = StateInducer::from(AppCommand::SubmitPrompt)(&asking);
+        let closed = StateInducer::from(AppCommand::CloseTab)(&closed);
+        let AppMode::Running(tabs) = &closed else {
+            panic!("Expected the app to be running");
+        };
+        assert_eq!(tabs.tabs.len(), 1);
+        assert_eq!(tabs.tabs[0].name, "Encounter 2");
+    }
+
+    #[test]
+    fn test_tabs_share_the_party() {
+        let mut bard = Participant::new("Astarion");
+        bard.set_hit_points(Some(20));
+        bard.set_level(Some(3));
+        let mut tabs = Tabs::from(RunMode::EditingEncounter(Box::default()));
+        tabs.workspace.party = Party {
+            members: vec![PartyMember::new(&bard)],
+            ..Default::default()
+        };
+        let app_mode = StateInducer::from(AppCommand::AddParty)(&AppMode::Running(tabs));
+        let app_mode = edit_encounter(&app_mode, |editing| {
+            editing.participants[0].set_initiative_roll(0, Some(15))
+        });
+
+        let fighting = StateInducer::from(AppCommand::StartCombat)(&app_mode);
+        let hurt = run_combat(&fighting, |combat| combat.damage("Astarion", 12));
+        let asking = StateInducer::from(AppCommand::EndCombat)(&hurt);
+        let ended = StateInducer::from(AppCommand::SubmitPrompt)(&asking);
+
+        // A new tab's encounter gets the party as the first one left it
+        let opened = StateInducer::from(AppCommand::NewTab)(&ended);
+        let joined = StateInducer::from(AppCommand::AddParty)(&opened);
+        let RunMode::EditingEncounter(encounter) = current_tab(&joined) else {
+            panic!("Expected a new encounter to be edited");
+        };
+        assert_eq!(encounter.participants[0].current_hit_points(), Some(8));
+    }
+
+    #[test]
+    fn test_cycling_the_theme_carries_on_to_the_quit_screen() {
+        let app_mode = AppMode::Running(RunMode::EditingEncounter(Box::default()).into());
+
+        let cycled = StateInducer::from(AppCommand::CycleTheme)(&app_mode);
+        let AppMode::Running(tabs) = &cycled else {
+            panic!("Expected the app to be running");
+        };
+        assert_eq!(tabs.themes.current_name(), "light");
+
+        let AppMode::Quitting(quitting) = StateInducer::from(AppCommand::Quit)(&cycled) else {
+            panic!("Expected the app to be quitting");
+        };
+        assert_eq!(quitting.themes, tabs.themes);
+    }
 }
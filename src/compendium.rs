@@ -0,0 +1,164 @@
+use core::fmt;
+use std::fmt::{Display, Formatter};
+
+use lazy_static::lazy_static;
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::{app::Participant, dice::DiceExpression, difficulty::ChallengeRating};
+
+/// A monster's stat block, as far as running combat needs it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Monster {
+    pub name: String,
+    pub challenge_rating: ChallengeRating,
+    pub armor_class: u8,
+    pub hit_points: DiceExpression,
+    pub initiative_modifier: i8,
+}
+
+/// A monster as it's written in the bundled data.
+#[derive(Deserialize)]
+struct MonsterRecord {
+    name: String,
+    cr: String,
+    ac: u8,
+    hp: String,
+    init: i8,
+}
+
+lazy_static! {
+    /// The monsters from the SRD, sorted by name.
+    pub static ref SRD_MONSTERS: Vec<Monster> = {
+        let records: Vec<MonsterRecord> =
+            serde_json::from_str(include_str!("../data/srd-monsters.json"))
+                .expect("The bundled monster data should be valid JSON");
+
+        records
+            .into_iter()
+            .map(|record| Monster {
+                challenge_rating: record.cr.parse().unwrap_or_else(|_| {
+                    panic!("{} should have a valid challenge rating", record.name)
+                }),
+                hit_points: record.hp.parse().unwrap_or_else(|_| {
+                    panic!("{} should have valid hit dice", record.name)
+                }),
+                armor_class: record.ac,
+                initiative_modifier: record.init,
+                name: record.name,
+            })
+            .collect()
+    };
+}
+
+/// Whether monsters added to an encounter get their hit points rolled, or the average.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HitPointsMode {
+    #[default]
+    Average,
+    Rolled,
+}
+
+impl HitPointsMode {
+    pub fn toggle(&self) -> Self {
+        match self {
+            HitPointsMode::Average => HitPointsMode::Rolled,
+            HitPointsMode::Rolled => HitPointsMode::Average,
+        }
+    }
+}
+
+impl Display for HitPointsMode {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            HitPointsMode::Average => write!(f, "Average HP"),
+            HitPointsMode::Rolled => write!(f, "Rolled HP"),
+        }
+    }
+}
+
+impl Monster {
+    /// A participant with this monster's stats, who has already rolled initiative.
+    pub fn to_participant(
+        &self,
+        name: &str,
+        hit_points_mode: HitPointsMode,
+        rng: &mut impl Rng,
+    ) -> Participant {
+        let hit_points = match hit_points_mode {
+            HitPointsMode::Average => self.hit_points.average(),
+            HitPointsMode::Rolled => self.hit_points.roll(rng),
+        };
+        let initiative = DiceExpression::D20
+            .with_modifier(self.initiative_modifier as i32)
+            .roll(rng);
+
+        let mut participant = Participant::new(name);
+        participant.set_hit_points(Some(hit_points.clamp(1, u16::MAX as i32) as u16));
+        participant.set_armor_class(Some(self.armor_class));
+        participant.set_initiative_modifier(self.initiative_modifier);
+        participant.set_challenge_rating(Some(self.challenge_rating));
+        participant.set_initiative_roll(0, Some(initiative.clamp(1, u8::MAX as i32) as u8));
+        participant
+    }
+}
+
+/// The monsters whose names contain `query`, ignoring case. Names starting with the query
+/// come first.
+pub fn search<'a>(monsters: &'a [Monster], query: &str) -> Vec<&'a Monster> {
+    let query = query.trim().to_lowercase();
+
+    let (mut starts_with, contains): (Vec<&Monster>, Vec<&Monster>) = monsters
+        .iter()
+        .filter(|monster| monster.name.to_lowercase().contains(&query))
+        .partition(|monster| monster.name.to_lowercase().starts_with(&query));
+
+    starts_with.extend(contains);
+    starts_with
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    #[test]
+    fn test_bundled_monsters_load() {
+        let goblin = SRD_MONSTERS
+            .iter()
+            .find(|monster| monster.name == "Goblin")
+            .unwrap();
+
+        assert_eq!(goblin.challenge_rating.to_string(), "1/4");
+        assert_eq!(goblin.armor_class, 15);
+        assert_eq!(goblin.hit_points.to_string(), "2d6");
+        assert_eq!(goblin.initiative_modifier, 2);
+    }
+
+    #[test]
+    fn test_search_puts_prefix_matches_first() {
+        let names: Vec<&str> = search(&SRD_MONSTERS, "giant")
+            .iter()
+            .map(|monster| monster.name.as_str())
+            .collect();
+
+        assert_eq!(names.first(), Some(&"Giant Eagle"));
+        assert!(names.contains(&"Hill Giant"));
+        assert!(search(&SRD_MONSTERS, "zzz").is_empty());
+    }
+
+    #[test]
+    fn test_to_participant_with_average_hit_points() {
+        let ogre = search(&SRD_MONSTERS, "Ogre")[0];
+        let mut rng = StdRng::seed_from_u64(3);
+
+        let participant = ogre.to_participant("Ogre", HitPointsMode::Average, &mut rng);
+
+        assert_eq!(participant.hit_points(), Some(59));
+        assert_eq!(participant.armor_class(), Some(11));
+        assert_eq!(participant.challenge_rating(), Some(ogre.challenge_rating));
+        let roll = participant.initiative_rolls()[0].unwrap();
+        assert!((1..=19).contains(&roll));
+    }
+}
@@ -0,0 +1,223 @@
+use core::fmt;
+use std::{
+    fmt::{Display, Formatter},
+    str::FromStr,
+};
+
+use rand::Rng;
+
+/// A dice expression such as `2d6+3`, `d20` or just `7`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DiceExpression {
+    pub count: u16,
+    pub sides: u16,
+    pub modifier: i32,
+}
+
+impl DiceExpression {
+    pub const D20: DiceExpression = DiceExpression {
+        count: 1,
+        sides: 20,
+        modifier: 0,
+    };
+
+    pub fn with_modifier(self, modifier: i32) -> Self {
+        DiceExpression { modifier, ..self }
+    }
+
+    /// Totals too big or small for an `i32`, which can only come from absurd expressions, stop
+    /// at its limits.
+    pub fn roll(&self, rng: &mut impl Rng) -> i32 {
+        let dice = (0..self.count)
+            .map(|_| rng.gen_range(1..=self.sides as i32))
+            .fold(0, i32::saturating_add);
+
+        dice.saturating_add(self.modifier)
+    }
+
+    /// The average result, rounded down, as used for monsters' fixed hit points.
+    pub fn average(&self) -> i32 {
+        ((self.count as i32).saturating_mul(self.sides as i32 + 1) / 2)
+            .saturating_add(self.modifier)
+    }
+}
+
+impl Display for DiceExpression {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        if self.count > 0 {
+            write!(f, "{}d{}", self.count, self.sides)?;
+            match self.modifier {
+                0 => Ok(()),
+                m if m > 0 => write!(f, "+{}", m),
+                m => write!(f, "{}", m),
+            }
+        } else {
+            write!(f, "{}", self.modifier)
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseDiceError {
+    Empty,
+    InvalidNumber(String),
+    NoSides,
+}
+
+impl Display for ParseDiceError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            ParseDiceError::Empty => write!(f, "no dice given"),
+            ParseDiceError::InvalidNumber(number) => write!(f, "'{}' is not a number", number),
+            ParseDiceError::NoSides => write!(f, "dice need at least one side"),
+        }
+    }
+}
+
+impl std::error::Error for ParseDiceError {}
+
+impl FromStr for DiceExpression {
+    type Err = ParseDiceError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s: String = s.chars().filter(|c| !c.is_whitespace()).collect();
+        if s.is_empty() {
+            return Err(ParseDiceError::Empty);
+        }
+
+        // Split off the modifier, keeping its sign.
+        let (dice, modifier) = match s.rfind(['+', '-']) {
+            Some(index) if index > 0 => s.split_at(index),
+            _ => (s.as_str(), ""),
+        };
+
+        let parse = |number: &str| {
+            number
+                .parse::<i32>()
+                .map_err(|_| ParseDiceError::InvalidNumber(number.to_string()))
+        };
+
+        let modifier = match modifier {
+            "" => 0,
+            m => parse(m.trim_start_matches('+'))?,
+        };
+
+        let Some((count, sides)) = dice.split_once(['d', 'D']) else {
+            // A flat number, with no dice to roll
+            return Ok(DiceExpression {
+                count: 0,
+                sides: 0,
+                modifier: parse(dice)?.saturating_add(modifier),
+            });
+        };
+
+        let count = match count {
+            "" => 1,
+            count => count
+                .parse::<u16>()
+                .map_err(|_| ParseDiceError::InvalidNumber(count.to_string()))?,
+        };
+        let sides = sides
+            .parse::<u16>()
+            .map_err(|_| ParseDiceError::InvalidNumber(sides.to_string()))?;
+        if sides == 0 {
+            return Err(ParseDiceError::NoSides);
+        }
+
+        Ok(DiceExpression {
+            count,
+            sides,
+            modifier,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    #[test]
+    fn test_parse_dice_expressions() {
+        assert_eq!(
+            "2d6+3".parse(),
+            Ok(DiceExpression {
+                count: 2,
+                sides: 6,
+                modifier: 3
+            })
+        );
+        assert_eq!("d20".parse(), Ok(DiceExpression::D20));
+        assert_eq!(
+            "18d10 - 36".parse(),
+            Ok(DiceExpression {
+                count: 18,
+                sides: 10,
+                modifier: -36
+            })
+        );
+        assert_eq!(
+            "7".parse(),
+            Ok(DiceExpression {
+                count: 0,
+                sides: 0,
+                modifier: 7
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid_dice_expressions() {
+        assert_eq!("".parse::<DiceExpression>(), Err(ParseDiceError::Empty));
+        assert_eq!(
+            "2d0".parse::<DiceExpression>(),
+            Err(ParseDiceError::NoSides)
+        );
+        assert_eq!(
+            "2dx".parse::<DiceExpression>(),
+            Err(ParseDiceError::InvalidNumber("x".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_average_rounds_down() {
+        let goblin: DiceExpression = "2d6".parse().unwrap();
+        let ogre: DiceExpression = "7d10+21".parse().unwrap();
+
+        assert_eq!(goblin.average(), 7);
+        assert_eq!(ogre.average(), 59);
+    }
+
+    #[test]
+    fn test_roll_stays_in_range() {
+        let mut rng = StdRng::seed_from_u64(20);
+        let expression: DiceExpression = "3d4+1".parse().unwrap();
+
+        for _ in 0..100 {
+            let result = expression.roll(&mut rng);
+            assert!((4..=13).contains(&result));
+        }
+    }
+
+    #[test]
+    fn test_huge_modifiers_stop_at_the_limits() {
+        let mut rng = StdRng::seed_from_u64(20);
+        let blessed: DiceExpression = "d20+2147483647".parse().unwrap();
+        let cursed: DiceExpression = "d20-2147483648".parse().unwrap();
+        let flat: DiceExpression = "2147483647+1".parse().unwrap();
+
+        assert_eq!(blessed.roll(&mut rng), i32::MAX);
+        assert_eq!(blessed.average(), i32::MAX);
+        assert!(cursed.roll(&mut rng) < 0);
+        assert_eq!(flat.modifier, i32::MAX);
+    }
+
+    #[test]
+    fn test_display_round_trips() {
+        for expression in ["2d6+3", "1d20", "4d8-2", "7"] {
+            let parsed: DiceExpression = expression.parse().unwrap();
+            assert_eq!(parsed.to_string(), expression);
+        }
+    }
+}
@@ -0,0 +1,295 @@
+use core::fmt;
+use std::{
+    fmt::{Display, Formatter},
+    str::FromStr,
+};
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::app::{Participant, TurnKind};
+
+/// Every challenge rating, lowest first, along with the XP a monster of that rating is worth.
+const CHALLENGE_RATINGS: [(&str, u32); 34] = [
+    ("0", 10),
+    ("1/8", 25),
+    ("1/4", 50),
+    ("1/2", 100),
+    ("1", 200),
+    ("2", 450),
+    ("3", 700),
+    ("4", 1_100),
+    ("5", 1_800),
+    ("6", 2_300),
+    ("7", 2_900),
+    ("8", 3_900),
+    ("9", 5_000),
+    ("10", 5_900),
+    ("11", 7_200),
+    ("12", 8_400),
+    ("13", 10_000),
+    ("14", 11_500),
+    ("15", 13_000),
+    ("16", 15_000),
+    ("17", 18_000),
+    ("18", 20_000),
+    ("19", 22_000),
+    ("20", 25_000),
+    ("21", 33_000),
+    ("22", 41_000),
+    ("23", 50_000),
+    ("24", 62_000),
+    ("25", 75_000),
+    ("26", 90_000),
+    ("27", 105_000),
+    ("28", 120_000),
+    ("29", 135_000),
+    ("30", 155_000),
+];
+
+/// The easy, medium, hard and deadly XP thresholds for one character of each level.
+const XP_THRESHOLDS: [[u32; 4]; 20] = [
+    [25, 50, 75, 100],
+    [50, 100, 150, 200],
+    [75, 150, 225, 400],
+    [125, 250, 375, 500],
+    [250, 500, 750, 1_100],
+    [300, 600, 900, 1_400],
+    [350, 750, 1_100, 1_700],
+    [450, 900, 1_400, 2_100],
+    [550, 1_100, 1_600, 2_400],
+    [600, 1_200, 1_900, 2_800],
+    [800, 1_600, 2_400, 3_600],
+    [1_000, 2_000, 3_000, 4_500],
+    [1_100, 2_200, 3_400, 5_100],
+    [1_250, 2_500, 3_800, 5_700],
+    [1_400, 2_800, 4_300, 6_400],
+    [1_600, 3_200, 4_800, 7_200],
+    [2_000, 3_900, 5_900, 8_800],
+    [2_100, 4_200, 6_300, 9_500],
+    [2_400, 4_900, 7_300, 10_900],
+    [2_800, 5_700, 8_500, 12_700],
+];
+
+/// The encounter multipliers, in tenths. A single monster against a party of three to five
+/// uses the second one, and the group size moves along from there.
+const MULTIPLIERS: [u32; 8] = [5, 10, 15, 20, 25, 30, 40, 50];
+
+pub const MAX_LEVEL: u8 = 20;
+
+/// A monster's challenge rating, from 0 up to 30.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ChallengeRating(usize);
+
+impl ChallengeRating {
+    pub fn xp(&self) -> u32 {
+        CHALLENGE_RATINGS[self.0].1
+    }
+}
+
+impl Display for ChallengeRating {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}", CHALLENGE_RATINGS[self.0].0)
+    }
+}
+
+impl FromStr for ChallengeRating {
+    type Err = ();
+
+    /// Parse a rating such as `5`, `1/4` or `0.5`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = match s.trim() {
+            "0.125" | ".125" => "1/8",
+            "0.25" | ".25" => "1/4",
+            "0.5" | ".5" => "1/2",
+            s => s,
+        };
+
+        CHALLENGE_RATINGS
+            .iter()
+            .position(|(rating, _)| *rating == s)
+            .map(ChallengeRating)
+            .ok_or(())
+    }
+}
+
+/// Ratings are saved the way they're written, such as `"1/4"`.
+impl Serialize for ChallengeRating {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for ChallengeRating {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse()
+            .map_err(|_| de::Error::custom(format!("invalid challenge rating: {}", s)))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Difficulty {
+    Trivial,
+    Easy,
+    Medium,
+    Hard,
+    Deadly,
+}
+
+impl Display for Difficulty {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Difficulty::Trivial => write!(f, "Trivial"),
+            Difficulty::Easy => write!(f, "Easy"),
+            Difficulty::Medium => write!(f, "Medium"),
+            Difficulty::Hard => write!(f, "Hard"),
+            Difficulty::Deadly => write!(f, "Deadly"),
+        }
+    }
+}
+
+/// How an encounter measures up against the party facing it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EncounterDifficulty {
+    pub difficulty: Difficulty,
+    /// The monsters' XP, adjusted for how many of them there are.
+    pub adjusted_xp: u32,
+    /// The party's easy, medium, hard and deadly thresholds.
+    pub thresholds: [u32; 4],
+    pub characters: usize,
+    pub monsters: usize,
+}
+
+impl EncounterDifficulty {
+    /// Rate the encounter, treating participants with a level as player characters and those
+    /// with only a challenge rating as monsters. `None` unless there is at least one of each.
+    pub fn rate(participants: &[Participant]) -> Option<Self> {
+        let combatants = participants
+            .iter()
+            .filter(|participant| participant.kind() == TurnKind::Combatant);
+
+        let levels: Vec<u8> = combatants.clone().filter_map(Participant::level).collect();
+        let monster_xp: Vec<u32> = combatants
+            .filter(|participant| participant.level().is_none())
+            .filter_map(|participant| participant.challenge_rating())
+            .map(|rating| rating.xp())
+            .collect();
+
+        if levels.is_empty() || monster_xp.is_empty() {
+            return None;
+        }
+
+        let mut thresholds = [0; 4];
+        for level in &levels {
+            let level_thresholds = XP_THRESHOLDS[(level.clamp(&1, &MAX_LEVEL) - 1) as usize];
+            for (threshold, level_threshold) in thresholds.iter_mut().zip(level_thresholds) {
+                *threshold += level_threshold;
+            }
+        }
+
+        let adjusted_xp = monster_xp.iter().sum::<u32>()
+            * MULTIPLIERS[multiplier_index(monster_xp.len(), levels.len())]
+            / 10;
+
+        let difficulty = match thresholds.iter().rposition(|t| adjusted_xp >= *t) {
+            Some(3) => Difficulty::Deadly,
+            Some(2) => Difficulty::Hard,
+            Some(1) => Difficulty::Medium,
+            Some(_) => Difficulty::Easy,
+            None => Difficulty::Trivial,
+        };
+
+        Some(EncounterDifficulty {
+            difficulty,
+            adjusted_xp,
+            thresholds,
+            characters: levels.len(),
+            monsters: monster_xp.len(),
+        })
+    }
+}
+
+/// Which of `MULTIPLIERS` applies. Small parties use the next multiplier up, and large parties
+/// the next one down.
+fn multiplier_index(monsters: usize, characters: usize) -> usize {
+    let index = match monsters {
+        1 => 1,
+        2 => 2,
+        3..=6 => 3,
+        7..=10 => 4,
+        11..=14 => 5,
+        _ => 6,
+    };
+
+    match characters {
+        0..=2 => index + 1,
+        3..=5 => index,
+        _ => index - 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn character(level: u8) -> Participant {
+        let mut participant = Participant::new("Adventurer");
+        participant.set_level(Some(level));
+        participant
+    }
+
+    fn monster(rating: &str) -> Participant {
+        let mut participant = Participant::new("Monster");
+        participant.set_challenge_rating(Some(rating.parse().unwrap()));
+        participant
+    }
+
+    #[test]
+    fn test_parse_challenge_ratings() {
+        assert_eq!("1/4".parse::<ChallengeRating>().map(|cr| cr.xp()), Ok(50));
+        assert_eq!("0.5".parse::<ChallengeRating>().map(|cr| cr.xp()), Ok(100));
+        assert_eq!(
+            "30".parse::<ChallengeRating>().map(|cr| cr.xp()),
+            Ok(155_000)
+        );
+        assert_eq!("31".parse::<ChallengeRating>(), Err(()));
+        assert!("1/8".parse::<ChallengeRating>() < "1".parse::<ChallengeRating>());
+    }
+
+    #[test]
+    fn test_rate_encounter() {
+        // Four 3rd level characters against an ogre and two goblins:
+        // (450 + 50 + 50) x 2 = 1100, between the party's hard and deadly thresholds
+        let participants = vec![
+            character(3),
+            character(3),
+            character(3),
+            character(3),
+            monster("2"),
+            monster("1/4"),
+            monster("1/4"),
+        ];
+
+        let rating = EncounterDifficulty::rate(&participants).unwrap();
+
+        assert_eq!(rating.adjusted_xp, 1_100);
+        assert_eq!(rating.thresholds, [300, 600, 900, 1_600]);
+        assert_eq!(rating.difficulty, Difficulty::Hard);
+    }
+
+    #[test]
+    fn test_small_party_uses_higher_multiplier() {
+        let participants = vec![character(1), character(1), monster("1/2")];
+
+        let rating = EncounterDifficulty::rate(&participants).unwrap();
+
+        assert_eq!(rating.adjusted_xp, 150);
+        assert_eq!(rating.difficulty, Difficulty::Hard);
+    }
+
+    #[test]
+    fn test_no_rating_without_both_sides() {
+        assert_eq!(EncounterDifficulty::rate(&[character(5)]), None);
+        assert_eq!(EncounterDifficulty::rate(&[monster("5")]), None);
+    }
+}
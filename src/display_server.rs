@@ -0,0 +1,227 @@
+use std::{
+    io::{ErrorKind, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, Context, Result};
+use tungstenite::Message;
+
+use crate::player_view::ViewBroadcast;
+
+/// Where the display is served when no address is given. Only this machine can see it.
+pub const DEFAULT_ADDRESS: &str = "127.0.0.1:7621";
+
+/// How often each WebSocket checks for a new view, and for the browser going away.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How long to wait between looks at a request that's still arriving, and how long to wait for
+/// all of it.
+const HEADER_WAIT: Duration = Duration::from_millis(5);
+const HEADER_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The page the players' screen shows. It keeps a WebSocket open for updates, and reconnects
+/// if the app restarts.
+const PAGE: &str = r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+  <meta charset="utf-8">
+  <title>Initiative</title>
+  <style>
+    body { background: #111; color: #eee; font-family: sans-serif; font-size: 2.5vw; margin: 2vw; }
+    h1 { font-size: 1.2em; color: #aaa; }
+    table { border-collapse: collapse; width: 100%; }
+    td { padding: 0.3em 0.6em; border-bottom: 1px solid #333; }
+    tr.current { background: #3a3000; font-weight: bold; }
+    tr.current td:first-child::before { content: "▶ "; }
+    tr.delayed { font-style: italic; color: #999; }
+    .unhurt, .hurt { color: #8c8; }
+    .bloodied { color: #ec6; }
+    .down, .dead { color: #e66; }
+  </style>
+</head>
+<body>
+  <h1 id="round">Waiting for combat</h1>
+  <table><tbody id="turns"></tbody></table>
+  <script>
+    function cell(row, text, className) {
+      const td = row.insertCell();
+      td.textContent = text;
+      if (className) td.className = className;
+    }
+
+    function show(view) {
+      document.getElementById("round").textContent =
+        view.round === null ? "Waiting for combat" : "Round " + view.round;
+      const turns = document.getElementById("turns");
+      turns.replaceChildren();
+      for (const turn of view.turns) {
+        const row = turns.insertRow();
+        row.className = (turn.current ? "current " : "") + (turn.delayed ? "delayed" : "");
+        cell(row, turn.name);
+        cell(row, turn.hit_points
+          ? turn.hit_points.current + "/" + turn.hit_points.max
+          : (turn.health || ""), turn.health);
+        cell(row, turn.conditions.join(", "));
+      }
+    }
+
+    function connect() {
+      const socket = new WebSocket("ws://" + location.host + "/updates");
+      socket.onmessage = (event) => show(JSON.parse(event.data));
+      socket.onclose = () => setTimeout(connect, 1000);
+    }
+
+    connect();
+  </script>
+</body>
+</html>
+"#;
+
+/// Serves a page showing the initiative order over HTTP, and pushes every change to it over
+/// a WebSocket. Only what `PlayerView` lets through is ever sent.
+pub struct DisplayServer {
+    address: SocketAddr,
+}
+
+impl DisplayServer {
+    /// Start serving on `address` in the background, showing whatever is sent to `views`.
+    pub fn start(address: SocketAddr, views: Arc<ViewBroadcast>) -> Result<Self> {
+        let listener = TcpListener::bind(address)
+            .with_context(|| format!("Couldn't serve the player display on {}", address))?;
+        let address = listener.local_addr()?;
+
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let views = Arc::clone(&views);
+                // A browser that goes away only ends its own connection
+                thread::spawn(move || handle_connection(stream, &views));
+            }
+        });
+
+        Ok(DisplayServer { address })
+    }
+
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+}
+
+fn handle_connection(mut stream: TcpStream, views: &ViewBroadcast) -> Result<()> {
+    // Look at the request without taking it, so a WebSocket handshake can still read it all
+    let mut buffer = [0; 4096];
+    let length = peek_headers(&stream, &mut buffer)?;
+    let request = String::from_utf8_lossy(&buffer[..length]).to_lowercase();
+    if request.contains("upgrade: websocket") {
+        return send_updates(stream, views);
+    }
+
+    stream.read_exact(&mut buffer[..length])?;
+    let (status, content_type, body) = match request.split_whitespace().nth(1) {
+        Some("/") => ("200 OK", "text/html", PAGE.to_string()),
+        Some("/view.json") => ("200 OK", "application/json", views.latest().1),
+        _ => ("404 Not Found", "text/plain", String::from("Not found")),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}; charset=utf-8\r\nContent-Length: {}\r\n\
+        Connection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    )?;
+
+    Ok(())
+}
+
+/// Wait for the request headers to arrive, which may take more than one packet, and peek at
+/// as much of them as fits in `buffer`. A request that never finishes is given up on after
+/// `HEADER_TIMEOUT`. Returns how much was peeked.
+fn peek_headers(stream: &TcpStream, buffer: &mut [u8]) -> Result<usize> {
+    let started = Instant::now();
+    loop {
+        let peeked = stream.peek(buffer)?;
+        let complete = buffer[..peeked].windows(4).any(|end| end == b"\r\n\r\n");
+        if complete || peeked == 0 || peeked == buffer.len() || started.elapsed() > HEADER_TIMEOUT {
+            return Ok(peeked);
+        }
+        thread::sleep(HEADER_WAIT);
+    }
+}
+
+/// Push the view to a WebSocket whenever it changes, until the browser goes away.
+fn send_updates(stream: TcpStream, views: &ViewBroadcast) -> Result<()> {
+    let mut socket = tungstenite::accept(stream).map_err(|error| anyhow!("{}", error))?;
+    // Reading is what answers pings and notices the browser closing, so it can't block for long
+    socket.get_mut().set_read_timeout(Some(POLL_INTERVAL))?;
+    let mut sent = None;
+
+    loop {
+        let (version, json) = views.latest();
+        if sent != Some(version) {
+            socket.send(Message::Text(json))?;
+            sent = Some(version);
+        }
+
+        match socket.read() {
+            Ok(_) => {}
+            Err(tungstenite::Error::Io(error))
+                if matches!(error.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+            Err(tungstenite::Error::ConnectionClosed) => return Ok(()),
+            Err(error) => return Err(error.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::player_view::PlayerView;
+
+    use super::*;
+
+    fn get(address: SocketAddr, path: &str) -> String {
+        let mut stream = TcpStream::connect(address).unwrap();
+        write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn test_serve_page_and_live_updates() {
+        let views = Arc::new(ViewBroadcast::new());
+        let server =
+            DisplayServer::start("127.0.0.1:0".parse().unwrap(), Arc::clone(&views)).unwrap();
+
+        assert!(get(server.address(), "/").contains("<title>Initiative</title>"));
+        assert!(get(server.address(), "/view.json").ends_with("{\"round\":null,\"turns\":[]}"));
+
+        let (mut socket, _) = tungstenite::client(format!("ws://{}/updates", server.address()), {
+            TcpStream::connect(server.address()).unwrap()
+        })
+        .unwrap();
+        let first = socket.read().unwrap();
+        assert_eq!(first.to_text().unwrap(), "{\"round\":null,\"turns\":[]}");
+
+        views.publish(&PlayerView {
+            round: Some(2),
+            turns: vec![],
+        });
+        let update = socket.read().unwrap();
+        assert_eq!(update.to_text().unwrap(), "{\"round\":2,\"turns\":[]}");
+
+        // The server keeps listening, so pings are answered and closing is agreed to
+        socket
+            .send(Message::Ping(b"still there?".to_vec()))
+            .unwrap();
+        assert_eq!(
+            socket.read().unwrap(),
+            Message::Pong(b"still there?".to_vec())
+        );
+        socket.close(None).unwrap();
+        assert!(matches!(socket.read().unwrap(), Message::Close(_)));
+    }
+}
@@ -0,0 +1,339 @@
+use core::fmt;
+use std::{
+    fmt::{Display, Formatter},
+    path::Path,
+    str::FromStr,
+};
+
+use serde::Serialize;
+
+use crate::app::{CombatTurn, RunningCombatState, TurnKind};
+
+/// Bumped whenever the JSON export changes in a way tools would notice.
+pub const JSON_FORMAT_VERSION: u32 = 1;
+
+/// The formats the initiative order can be exported in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// A table, for pasting into Discord or notes.
+    #[default]
+    Markdown,
+    /// A page that can be opened on its own.
+    Html,
+    /// For other tools to read.
+    Json,
+}
+
+impl ExportFormat {
+    /// HTML for `.html` files, JSON for `.json` files, and Markdown for anything else.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("html") | Some("htm") => ExportFormat::Html,
+            Some("json") => ExportFormat::Json,
+            _ => ExportFormat::Markdown,
+        }
+    }
+}
+
+impl Display for ExportFormat {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            ExportFormat::Markdown => write!(f, "markdown"),
+            ExportFormat::Html => write!(f, "html"),
+            ExportFormat::Json => write!(f, "json"),
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "markdown" | "md" => Ok(ExportFormat::Markdown),
+            "html" => Ok(ExportFormat::Html),
+            "json" => Ok(ExportFormat::Json),
+            _ => Err(format!("\"{}\" isn't markdown, html or json", s)),
+        }
+    }
+}
+
+/// The initiative order at one moment of combat.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct ExportedOrder {
+    pub version: u32,
+    pub round: u32,
+    /// Every turn in order, followed by anyone who has delayed theirs. Hidden participants
+    /// are left out.
+    pub turns: Vec<ExportedTurn>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct ExportedTurn {
+    pub name: String,
+    pub initiative: u8,
+    pub lair_action: bool,
+    /// Whether it's this turn now.
+    pub current: bool,
+    pub delayed: bool,
+    pub unconscious: bool,
+    pub dead: bool,
+    /// The trigger of a readied action.
+    pub readied: Option<String>,
+    pub hit_points: Option<ExportedHitPoints>,
+    /// The spell being concentrated on.
+    pub concentration: Option<String>,
+    pub conditions: Vec<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct ExportedHitPoints {
+    pub current: u16,
+    pub max: u16,
+}
+
+impl ExportedOrder {
+    pub fn new(combat: &RunningCombatState) -> Self {
+        let turn = |turn: &CombatTurn, current: bool, delayed: bool| {
+            let combatant = combat.combatant(turn.name());
+            ExportedTurn {
+                name: turn.name().to_string(),
+                initiative: turn.initiative_roll_value(),
+                lair_action: turn.kind() == TurnKind::LairAction,
+                current,
+                delayed,
+                unconscious: turn.unconscious(),
+                dead: turn.dead(),
+                readied: turn.readied().map(String::from),
+                hit_points: combatant.and_then(|combatant| combatant.hit_points).map(
+                    |hit_points| ExportedHitPoints {
+                        current: hit_points.current,
+                        max: hit_points.max,
+                    },
+                ),
+                concentration: combatant
+                    .and_then(|combatant| combatant.concentration.as_ref())
+                    .map(|concentration| concentration.spell.clone()),
+                conditions: combatant
+                    .map(|combatant| combatant.conditions.clone())
+                    .unwrap_or_default(),
+            }
+        };
+
+        // Hidden participants aren't for the players to know about
+        let turns = combat
+            .turns
+            .iter()
+            .enumerate()
+            .map(|(index, t)| (t, index == combat.current_turn, false))
+            .chain(combat.delayed.iter().map(|t| (t, false, true)))
+            .filter(|(t, _, _)| !t.hidden())
+            .map(|(t, current, delayed)| turn(t, current, delayed))
+            .collect();
+
+        ExportedOrder {
+            version: JSON_FORMAT_VERSION,
+            round: combat.round,
+            turns,
+        }
+    }
+
+    pub fn render(&self, format: ExportFormat) -> String {
+        match format {
+            ExportFormat::Markdown => self.to_markdown(),
+            ExportFormat::Html => self.to_html(),
+            ExportFormat::Json => {
+                // Nothing in the order can fail to serialize
+                let mut json = serde_json::to_string_pretty(self).unwrap_or_default();
+                json.push('\n');
+                json
+            }
+        }
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut markdown = format!("# Initiative order\n\nRound {}\n\n", self.round);
+        markdown.push_str("| | Init | Name | HP | Status |\n");
+        markdown.push_str("| --- | --- | --- | --- | --- |\n");
+
+        for turn in &self.turns {
+            let cells = [
+                String::from(if turn.current { "▶" } else { "" }),
+                turn.initiative.to_string(),
+                turn.name.clone(),
+                turn.hit_points_text(),
+                turn.status().join(", "),
+            ];
+            let cells: Vec<String> = cells.iter().map(|cell| cell.replace('|', "\\|")).collect();
+            markdown.push_str(&format!("| {} |\n", cells.join(" | ")));
+        }
+
+        markdown
+    }
+
+    pub fn to_html(&self) -> String {
+        let mut rows = String::new();
+        for turn in &self.turns {
+            let mut classes = vec![];
+            if turn.current {
+                classes.push("current");
+            }
+            if turn.dead || turn.unconscious {
+                classes.push("down");
+            }
+            rows.push_str(&format!(
+                "      <tr class=\"{}\"><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                classes.join(" "),
+                turn.initiative,
+                escape_html(&turn.name),
+                escape_html(&turn.hit_points_text()),
+                escape_html(&turn.status().join(", ")),
+            ));
+        }
+
+        format!(
+            "<!DOCTYPE html>
+<html lang=\"en\">
+<head>
+  <meta charset=\"utf-8\">
+  <title>Initiative order</title>
+  <style>
+    body {{ font-family: sans-serif; margin: 2em; }}
+    table {{ border-collapse: collapse; }}
+    th, td {{ border: 1px solid #999; padding: 0.3em 0.8em; text-align: left; }}
+    tr.current {{ font-weight: bold; background: #ffe9a8; }}
+    tr.down {{ color: #888; }}
+  </style>
+</head>
+<body>
+  <h1>Initiative order</h1>
+  <p>Round {}</p>
+  <table>
+    <thead>
+      <tr><th>Init</th><th>Name</th><th>HP</th><th>Status</th></tr>
+    </thead>
+    <tbody>
+{}    </tbody>
+  </table>
+</body>
+</html>
+",
+            self.round, rows
+        )
+    }
+}
+
+impl ExportedTurn {
+    fn hit_points_text(&self) -> String {
+        match self.hit_points {
+            Some(hit_points) => format!("{}/{}", hit_points.current, hit_points.max),
+            None => String::from("-"),
+        }
+    }
+
+    /// Everything worth knowing about the turn besides its place in the order.
+    fn status(&self) -> Vec<String> {
+        let mut status = vec![];
+        if self.lair_action {
+            status.push(String::from("Lair action"));
+        }
+        if self.dead {
+            status.push(String::from("Dead"));
+        } else if self.unconscious {
+            status.push(String::from("Unconscious"));
+        }
+        if self.delayed {
+            status.push(String::from("Delayed"));
+        }
+        if let Some(trigger) = &self.readied {
+            status.push(format!("Readied: {}", trigger));
+        }
+        if let Some(spell) = &self.concentration {
+            status.push(format!("Concentrating on {}", spell));
+        }
+        status.extend(self.conditions.iter().cloned());
+        status
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::app::{EditingEncounterState, Participant};
+
+    use super::*;
+
+    fn combat() -> RunningCombatState {
+        let mut legolas = Participant::new("Legolas");
+        legolas.set_initiative_roll(0, Some(21));
+        legolas.set_hit_points(Some(20));
+        let mut orc = Participant::new("Orc <Chief>");
+        orc.set_initiative_roll(0, Some(8));
+        orc.set_hit_points(Some(15));
+        let encounter = EditingEncounterState {
+            participants: vec![orc, legolas],
+            ..Default::default()
+        };
+
+        let mut combat = RunningCombatState::new(&encounter).unwrap();
+        combat.toggle_condition("Orc <Chief>", "Prone");
+        combat
+    }
+
+    #[test]
+    fn test_format_from_path() {
+        assert_eq!(
+            ExportFormat::from_path(Path::new("order.html")),
+            ExportFormat::Html
+        );
+        assert_eq!(
+            ExportFormat::from_path(Path::new("order.json")),
+            ExportFormat::Json
+        );
+        assert_eq!(
+            ExportFormat::from_path(Path::new("order.txt")),
+            ExportFormat::Markdown
+        );
+        assert_eq!("JSON".parse(), Ok(ExportFormat::Json));
+    }
+
+    #[test]
+    fn test_export_markdown_and_html() {
+        let order = ExportedOrder::new(&combat());
+
+        assert_eq!(
+            order.to_markdown(),
+            "# Initiative order\n\nRound 1\n\n\
+            | | Init | Name | HP | Status |\n\
+            | --- | --- | --- | --- | --- |\n\
+            | ▶ | 21 | Legolas | 20/20 |  |\n\
+            |  | 8 | Orc <Chief> | 15/15 | Prone |\n"
+        );
+
+        let html = order.to_html();
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<tr class=\"current\"><td>21</td><td>Legolas</td>"));
+        assert!(html.contains("<td>Orc &lt;Chief&gt;</td><td>15/15</td><td>Prone</td>"));
+    }
+
+    #[test]
+    fn test_export_json_is_stable() {
+        let json = ExportedOrder::new(&combat()).render(ExportFormat::Json);
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(value["version"], 1);
+        assert_eq!(value["round"], 1);
+        assert_eq!(value["turns"][0]["name"], "Legolas");
+        assert_eq!(value["turns"][0]["current"], true);
+        assert_eq!(value["turns"][1]["hit_points"]["current"], 15);
+        assert_eq!(value["turns"][1]["conditions"][0], "Prone");
+        // Every field is always there, even when it's empty
+        assert_eq!(value["turns"][1]["readied"], serde_json::Value::Null);
+    }
+}
@@ -0,0 +1,366 @@
+use core::fmt;
+use std::{
+    fmt::{Display, Formatter},
+    fs,
+    path::Path,
+};
+
+use anyhow::{Context, Result};
+
+use crate::{
+    app::{self, ParseParticipantError, Participant, Side},
+    quick_entry::QuickEntry,
+    vtt,
+};
+
+/// The columns of an imported CSV file, in order. Only the name is required.
+pub const CSV_COLUMNS: [&str; 5] = ["name", "modifier", "hp", "side", "rolls"];
+
+/// How a file of participants is laid out.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImportFormat {
+    /// A participant or group on each line.
+    Lines(LineFormat),
+    /// Characters exported from Foundry VTT or Roll20.
+    VttJson,
+}
+
+/// How each line of a file describes a participant.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LineFormat {
+    /// Name, modifier, HP, side and initiative rolls. Rolls are separated by spaces or
+    /// semicolons, and the header line is optional.
+    Csv,
+    /// Written the way they'd be typed into the editor.
+    PlainList,
+}
+
+impl ImportFormat {
+    /// CSV for `.csv` files, VTT exports for `.json` files, and a plain list for anything else.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension() {
+            Some(extension) if extension.eq_ignore_ascii_case("csv") => {
+                ImportFormat::Lines(LineFormat::Csv)
+            }
+            Some(extension) if extension.eq_ignore_ascii_case("json") => ImportFormat::VttJson,
+            _ => ImportFormat::Lines(LineFormat::PlainList),
+        }
+    }
+}
+
+/// The participants read from a file, and anything in it that was left out.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Imported {
+    pub participants: Vec<Participant>,
+    pub warnings: Vec<String>,
+}
+
+/// What an import added, for showing once it's done.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ImportReport {
+    /// Where the participants came from.
+    pub source: String,
+    pub names: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+impl ImportReport {
+    pub fn new(source: &str, imported: &Imported) -> Self {
+        ImportReport {
+            source: source.to_string(),
+            names: imported
+                .participants
+                .iter()
+                .map(|participant| participant.name().to_string())
+                .collect(),
+            warnings: imported.warnings.clone(),
+        }
+    }
+
+    /// Add what another file imported.
+    pub fn extend(&mut self, report: ImportReport) {
+        self.source = match self.source.is_empty() {
+            true => report.source,
+            false => format!("{}, {}", self.source, report.source),
+        };
+        self.names.extend(report.names);
+        self.warnings.extend(report.warnings);
+    }
+
+    /// The report as lines of text: who was imported, then each warning.
+    pub fn body(&self) -> String {
+        let mut body = format!("Imported {}.", self.names.join(", "));
+        if !self.warnings.is_empty() {
+            body.push_str("\n\nNot imported:");
+            for warning in &self.warnings {
+                body.push_str(&format!("\n- {}", warning));
+            }
+        }
+        body
+    }
+}
+
+/// What was wrong with one line of an imported file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LineErrorKind {
+    Participant(ParseParticipantError),
+    InvalidModifier(String),
+    InvalidHitPoints(String),
+    InvalidRoll(String),
+    TooManyFields(usize),
+    UnclosedQuote,
+}
+
+impl Display for LineErrorKind {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            LineErrorKind::Participant(error) => write!(f, "{}", error),
+            LineErrorKind::InvalidModifier(modifier) => {
+                write!(f, "\"{}\" isn't an initiative modifier", modifier)
+            }
+            LineErrorKind::InvalidHitPoints(hit_points) => {
+                write!(f, "\"{}\" isn't a number of hit points", hit_points)
+            }
+            LineErrorKind::InvalidRoll(roll) => write!(f, "\"{}\" isn't an initiative roll", roll),
+            LineErrorKind::TooManyFields(count) => write!(
+                f,
+                "{} fields, but only {} are expected ({})",
+                count,
+                CSV_COLUMNS.len(),
+                CSV_COLUMNS.join(", ")
+            ),
+            LineErrorKind::UnclosedQuote => write!(f, "a quote is never closed"),
+        }
+    }
+}
+
+impl From<ParseParticipantError> for LineErrorKind {
+    fn from(error: ParseParticipantError) -> Self {
+        LineErrorKind::Participant(error)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LineError {
+    /// Counting from 1, the way editors show them.
+    pub line: usize,
+    pub kind: LineErrorKind,
+}
+
+impl Display for LineError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.kind)
+    }
+}
+
+/// Every line of a file that couldn't be imported.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ImportError {
+    pub errors: Vec<LineError>,
+}
+
+impl Display for ImportError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        for (index, error) in self.errors.iter().enumerate() {
+            if index > 0 {
+                write!(f, "; ")?;
+            }
+            write!(f, "{}", error)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ImportError {}
+
+/// Read the participants from the file at `path`, in the format its extension suggests.
+pub fn import(path: &Path) -> Result<Imported> {
+    let text =
+        fs::read_to_string(path).with_context(|| format!("Couldn't read {}", path.display()))?;
+
+    match ImportFormat::from_path(path) {
+        ImportFormat::VttJson => vtt::parse(&text),
+        ImportFormat::Lines(format) => parse(&text, format)
+            .map(|participants| Imported {
+                participants,
+                warnings: vec![],
+            })
+            .map_err(anyhow::Error::from),
+    }
+    .with_context(|| format!("Couldn't import {}", path.display()))
+}
+
+/// Read participants from `text`, skipping blank lines and `#` comments. Every line is checked,
+/// so that all the mistakes can be fixed at once.
+pub fn parse(text: &str, format: LineFormat) -> Result<Vec<Participant>, ImportError> {
+    let mut participants = vec![];
+    let mut errors = vec![];
+
+    let lines = text
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'));
+
+    for (number, line) in lines {
+        let parsed = match format {
+            LineFormat::Csv if is_csv_header(line) => continue,
+            LineFormat::Csv => parse_csv_line(line).map(|participant| vec![participant]),
+            LineFormat::PlainList => line
+                .parse::<QuickEntry>()
+                .map(|entry| entry.participants())
+                .map_err(LineErrorKind::from),
+        };
+
+        match parsed {
+            Ok(parsed) => app::add_participants(&mut participants, parsed),
+            Err(kind) => errors.push(LineError { line: number, kind }),
+        }
+    }
+
+    match errors.is_empty() {
+        true => Ok(participants),
+        false => Err(ImportError { errors }),
+    }
+}
+
+fn is_csv_header(line: &str) -> bool {
+    split_csv_line(line)
+        .ok()
+        .and_then(|fields| fields.first().cloned())
+        .is_some_and(|field| field.eq_ignore_ascii_case(CSV_COLUMNS[0]))
+}
+
+fn parse_csv_line(line: &str) -> Result<Participant, LineErrorKind> {
+    let fields = split_csv_line(line)?;
+    if fields.len() > CSV_COLUMNS.len() {
+        return Err(LineErrorKind::TooManyFields(fields.len()));
+    }
+
+    // Missing trailing fields are the same as empty ones
+    let field = |index: usize| fields.get(index).map(|field| field.trim()).unwrap_or("");
+
+    // Names are taken as they are, rather than in the quick entry grammar
+    let mut participant = match field(0) {
+        "" => return Err(ParseParticipantError::EmptyName.into()),
+        name => Participant::new(name),
+    };
+
+    if !field(1).is_empty() {
+        let modifier = field(1)
+            .parse()
+            .map_err(|_| LineErrorKind::InvalidModifier(field(1).to_string()))?;
+        participant.set_initiative_modifier(modifier);
+    }
+
+    if !field(2).is_empty() {
+        let hit_points = field(2)
+            .parse()
+            .map_err(|_| LineErrorKind::InvalidHitPoints(field(2).to_string()))?;
+        participant.set_hit_points(Some(hit_points));
+    }
+
+    if !field(3).is_empty() {
+        participant.set_side(Some(field(3).parse::<Side>()?));
+    }
+
+    let rolls = field(4)
+        .split(|c: char| c == ';' || c.is_whitespace())
+        .filter(|roll| !roll.is_empty());
+    for (slot, roll) in rolls.enumerate() {
+        let roll = roll
+            .parse()
+            .map_err(|_| LineErrorKind::InvalidRoll(roll.to_string()))?;
+        if slot > 0 {
+            participant.add_initiative_slot();
+        }
+        participant.set_initiative_roll(slot, Some(roll));
+    }
+
+    Ok(participant)
+}
+
+/// The fields of a CSV line. Fields can be wrapped in double quotes to hold commas, and a
+/// quote inside quotes is written twice.
+fn split_csv_line(line: &str) -> Result<Vec<String>, LineErrorKind> {
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (c, quoted) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            ('"', _) => quoted = !quoted,
+            (',', false) => fields.push(std::mem::take(&mut field)),
+            (c, _) => field.push(c),
+        }
+    }
+
+    if quoted {
+        return Err(LineErrorKind::UnclosedQuote);
+    }
+    fields.push(field);
+
+    Ok(fields)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_plain_list() {
+        let text = "# The usual suspects\nAragorn\n\n  Legolas  +3 \nUruk-hai x2 hp:22\n";
+
+        let participants = parse(text, LineFormat::PlainList).unwrap();
+
+        let names: Vec<&str> = participants.iter().map(Participant::name).collect();
+        assert_eq!(names, vec!["Aragorn", "Legolas", "Uruk-hai", "Uruk-hai 2"]);
+        assert_eq!(participants[1].initiative_modifier(), 3);
+        assert_eq!(participants[3].hit_points(), Some(22));
+    }
+
+    #[test]
+    fn test_parse_csv() {
+        let text = "name,modifier,hp,side,rolls\n\
+            \"Smeagol, the Tame\",+3,18,neutral,\n\
+            Orc,1,15,enemy,12 7\n\
+            Frodo\n";
+
+        let participants = parse(text, LineFormat::Csv).unwrap();
+
+        assert_eq!(participants.len(), 3);
+        assert_eq!(participants[0].name(), "Smeagol, the Tame");
+        assert_eq!(participants[0].initiative_modifier(), 3);
+        assert_eq!(participants[0].side(), Some(Side::Neutral));
+        assert_eq!(participants[1].hit_points(), Some(15));
+        assert_eq!(participants[1].initiative_rolls(), &[Some(12), Some(7)]);
+        assert_eq!(participants[2].initiative_rolls(), &[None]);
+    }
+
+    #[test]
+    fn test_errors_are_reported_for_every_bad_line() {
+        let text = "Orc,1,fifteen\n,2\nGoblin,2,7,enemy,12\nTroll,0,84,monstrous\n\"Ent,0";
+
+        let error = parse(text, LineFormat::Csv).unwrap_err();
+
+        let lines: Vec<usize> = error.errors.iter().map(|error| error.line).collect();
+        assert_eq!(lines, vec![1, 2, 4, 5]);
+        assert_eq!(
+            error.errors[0].kind,
+            LineErrorKind::InvalidHitPoints("fifteen".to_string())
+        );
+        assert_eq!(
+            error.errors[1].kind,
+            LineErrorKind::Participant(ParseParticipantError::EmptyName)
+        );
+        assert_eq!(
+            error.to_string().split("; ").nth(2),
+            Some("line 4: \"monstrous\" isn't a side (ally, enemy or neutral)")
+        );
+    }
+}
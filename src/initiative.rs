@@ -0,0 +1,278 @@
+use core::fmt;
+use std::fmt::{Display, Formatter};
+
+use serde::{Deserialize, Serialize};
+
+use crate::app::{participants_to_ordered_combat_turns, CombatTurn, Participant, TurnKind};
+
+/// A set of rules for turning the participants' initiative rolls into a turn order.
+pub trait InitiativeSystem {
+    /// The order in which turns are taken in a round.
+    fn order_turns(&self, participants: &[Participant]) -> Vec<CombatTurn>;
+
+    /// Whether the combatant whose turn it is chooses who goes next, rather than the order
+    /// being fixed at the start of the round.
+    fn active_combatant_picks_next(&self) -> bool {
+        false
+    }
+}
+
+/// Highest roll goes first, as in most d20 games.
+pub struct StandardD20;
+
+impl InitiativeSystem for StandardD20 {
+    fn order_turns(&self, participants: &[Participant]) -> Vec<CombatTurn> {
+        participants_to_ordered_combat_turns(participants.to_vec())
+    }
+}
+
+/// Lowest roll goes first, as with AD&D segments & weapon speed factors.
+pub struct LowestFirst;
+
+impl InitiativeSystem for LowestFirst {
+    fn order_turns(&self, participants: &[Participant]) -> Vec<CombatTurn> {
+        let mut result: Vec<CombatTurn> = participants
+            .iter()
+            .cloned()
+            .flat_map(Vec::<CombatTurn>::from)
+            .collect();
+
+        result.sort_by_key(|turn| (turn.initiative_roll_value(), turn.kind()));
+
+        result
+    }
+}
+
+/// Shadowrun-style initiative passes. Everyone acts in the first pass in order of their score,
+/// then 10 is subtracted from each score and everyone still above zero acts again, and so on.
+/// The lair only acts in the first pass.
+pub struct ShadowrunPasses;
+
+impl ShadowrunPasses {
+    const PASS_COST: u8 = 10;
+}
+
+impl InitiativeSystem for ShadowrunPasses {
+    fn order_turns(&self, participants: &[Participant]) -> Vec<CombatTurn> {
+        let mut pass = participants_to_ordered_combat_turns(participants.to_vec());
+        let mut result = Vec::new();
+
+        while !pass.is_empty() {
+            let next_pass = pass
+                .iter()
+                .filter(|turn| turn.kind() == TurnKind::Combatant)
+                .filter(|turn| turn.initiative_roll_value() > Self::PASS_COST)
+                .map(|turn| {
+                    let mut next = CombatTurn::new(
+                        turn.name(),
+                        turn.initiative_roll_value() - Self::PASS_COST,
+                    );
+                    next.set_hidden(turn.hidden());
+                    next
+                })
+                .collect();
+
+            result.extend(pass);
+            pass = next_pass;
+        }
+
+        result
+    }
+}
+
+/// Popcorn initiative. The highest roll starts the round, and from then on whoever is acting
+/// hands the turn to someone who hasn't acted yet this round.
+pub struct Popcorn;
+
+impl InitiativeSystem for Popcorn {
+    fn order_turns(&self, participants: &[Participant]) -> Vec<CombatTurn> {
+        participants_to_ordered_combat_turns(participants.to_vec())
+    }
+
+    fn active_combatant_picks_next(&self) -> bool {
+        true
+    }
+}
+
+/// The initiative system chosen for an encounter.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub enum InitiativeSystemKind {
+    #[default]
+    StandardD20,
+    LowestFirst,
+    ShadowrunPasses,
+    Popcorn,
+}
+
+impl InitiativeSystemKind {
+    pub fn system(&self) -> &'static dyn InitiativeSystem {
+        match self {
+            InitiativeSystemKind::StandardD20 => &StandardD20,
+            InitiativeSystemKind::LowestFirst => &LowestFirst,
+            InitiativeSystemKind::ShadowrunPasses => &ShadowrunPasses,
+            InitiativeSystemKind::Popcorn => &Popcorn,
+        }
+    }
+
+    /// The next system in the list, wrapping around to the first.
+    pub fn cycle(&self) -> Self {
+        match self {
+            InitiativeSystemKind::StandardD20 => InitiativeSystemKind::LowestFirst,
+            InitiativeSystemKind::LowestFirst => InitiativeSystemKind::ShadowrunPasses,
+            InitiativeSystemKind::ShadowrunPasses => InitiativeSystemKind::Popcorn,
+            InitiativeSystemKind::Popcorn => InitiativeSystemKind::StandardD20,
+        }
+    }
+}
+
+impl Display for InitiativeSystemKind {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            InitiativeSystemKind::StandardD20 => write!(f, "Standard d20"),
+            InitiativeSystemKind::LowestFirst => write!(f, "Lowest first"),
+            InitiativeSystemKind::ShadowrunPasses => write!(f, "Shadowrun passes"),
+            InitiativeSystemKind::Popcorn => write!(f, "Popcorn"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn participant(name: &str, rolls: &[u8]) -> Participant {
+        let mut participant = Participant::new(name);
+        for (slot, roll) in rolls.iter().enumerate() {
+            if slot > 0 {
+                participant.add_initiative_slot();
+            }
+            participant.set_initiative_roll(slot, Some(*roll));
+        }
+        participant
+    }
+
+    fn names_and_rolls(turns: &[CombatTurn]) -> Vec<(&str, u8)> {
+        turns
+            .iter()
+            .map(|turn| (turn.name(), turn.initiative_roll_value()))
+            .collect()
+    }
+
+    #[test]
+    fn test_standard_d20_highest_first() {
+        let participants = vec![participant("Fighter", &[12]), participant("Rogue", &[19])];
+
+        let result = StandardD20.order_turns(&participants);
+
+        assert_eq!(
+            names_and_rolls(&result),
+            vec![("Rogue", 19), ("Fighter", 12)]
+        );
+    }
+
+    #[test]
+    fn test_lowest_first() {
+        let participants = vec![
+            participant("Halberdier", &[9]),
+            participant("Dagger thief", &[2]),
+            participant("Wizard", &[5]),
+        ];
+
+        let result = LowestFirst.order_turns(&participants);
+
+        assert_eq!(
+            names_and_rolls(&result),
+            vec![("Dagger thief", 2), ("Wizard", 5), ("Halberdier", 9)]
+        );
+    }
+
+    #[test]
+    fn test_shadowrun_passes_finish_each_pass_before_the_next() {
+        let participants = vec![
+            participant("Street Samurai", &[25]),
+            participant("Decker", &[12]),
+        ];
+
+        let result = ShadowrunPasses.order_turns(&participants);
+
+        assert_eq!(
+            names_and_rolls(&result),
+            vec![
+                ("Street Samurai", 25),
+                ("Decker", 12),
+                ("Street Samurai", 15),
+                ("Decker", 2),
+                ("Street Samurai", 5),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_shadowrun_passes_stop_at_zero() {
+        let participants = vec![participant("Troll", &[10])];
+
+        let result = ShadowrunPasses.order_turns(&participants);
+
+        assert_eq!(names_and_rolls(&result), vec![("Troll", 10)]);
+    }
+
+    #[test]
+    fn test_only_popcorn_lets_active_combatant_pick() {
+        assert!(InitiativeSystemKind::Popcorn
+            .system()
+            .active_combatant_picks_next());
+        assert!(!InitiativeSystemKind::StandardD20
+            .system()
+            .active_combatant_picks_next());
+    }
+
+    #[test]
+    fn test_cycle_visits_every_system() {
+        let mut kind = InitiativeSystemKind::default();
+        for _ in 0..4 {
+            kind = kind.cycle();
+        }
+
+        assert_eq!(kind, InitiativeSystemKind::default());
+    }
+
+    #[test]
+    fn test_lair_action_loses_ties() {
+        let participants = vec![
+            participant("Dragon", &[20]),
+            Participant::lair_action(),
+            participant("Paladin", &[21]),
+            participant("Bard", &[20]),
+        ];
+
+        let result = StandardD20.order_turns(&participants);
+
+        assert_eq!(
+            names_and_rolls(&result),
+            vec![
+                ("Paladin", 21),
+                ("Dragon", 20),
+                ("Bard", 20),
+                ("Lair Action", 20)
+            ]
+        );
+        assert_eq!(result[3].kind(), TurnKind::LairAction);
+    }
+
+    #[test]
+    fn test_lair_action_only_acts_in_first_shadowrun_pass() {
+        let participants = vec![Participant::lair_action(), participant("Decker", &[24])];
+
+        let result = ShadowrunPasses.order_turns(&participants);
+
+        assert_eq!(
+            names_and_rolls(&result),
+            vec![
+                ("Decker", 24),
+                ("Lair Action", 20),
+                ("Decker", 14),
+                ("Decker", 4)
+            ]
+        );
+    }
+}
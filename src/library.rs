@@ -0,0 +1,349 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context, Result};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app::Participant, campaign::Campaign, dice::DiceExpression, difficulty::ChallengeRating,
+};
+
+/// Set this to keep the library somewhere other than the usual data directory.
+pub const LIBRARY_DIR_VAR: &str = "ROLL_INITIATIVE_LIBRARY";
+
+/// A creature saved from the editor so it can be added to later encounters.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CreatureTemplate {
+    pub name: String,
+    pub tags: Vec<String>,
+    pub hit_points: Option<u16>,
+    pub armor_class: Option<u8>,
+    pub initiative_modifier: i8,
+    pub level: Option<u8>,
+    pub challenge_rating: Option<ChallengeRating>,
+    pub legendary_actions: u8,
+}
+
+impl CreatureTemplate {
+    pub fn new(participant: &Participant, tags: Vec<String>) -> Self {
+        CreatureTemplate {
+            name: participant.name().to_string(),
+            tags,
+            hit_points: participant.hit_points(),
+            armor_class: participant.armor_class(),
+            initiative_modifier: participant.initiative_modifier(),
+            level: participant.level(),
+            challenge_rating: participant.challenge_rating(),
+            legendary_actions: participant.legendary_actions(),
+        }
+    }
+
+    /// Player characters have a level. Their players roll their own initiative.
+    pub fn is_player_character(&self) -> bool {
+        self.level.is_some()
+    }
+
+    /// A participant with this template's stats. Everyone but player characters has already
+    /// rolled initiative.
+    pub fn to_participant(&self, name: &str, rng: &mut impl Rng) -> Participant {
+        let mut participant = Participant::new(name);
+        participant.set_hit_points(self.hit_points);
+        participant.set_armor_class(self.armor_class);
+        participant.set_initiative_modifier(self.initiative_modifier);
+        participant.set_level(self.level);
+        participant.set_challenge_rating(self.challenge_rating);
+        participant.set_legendary_actions(self.legendary_actions);
+
+        if !self.is_player_character() {
+            let initiative = DiceExpression::D20
+                .with_modifier(self.initiative_modifier as i32)
+                .roll(rng);
+            participant.set_initiative_roll(0, Some(initiative.clamp(1, u8::MAX as i32) as u8));
+        }
+        participant
+    }
+
+    /// Whether the name or any of the tags contain `query`, which should already be lowercase.
+    fn matches(&self, query: &str) -> bool {
+        self.name.to_lowercase().contains(query)
+            || self
+                .tags
+                .iter()
+                .any(|tag| tag.to_lowercase().contains(query))
+    }
+}
+
+/// Tags as they're typed in: separated by commas or spaces.
+pub fn parse_tags(input: &str) -> Vec<String> {
+    input
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|tag| !tag.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// The creature templates saved on disk, one JSON file each.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Library {
+    /// Where templates are saved. `None` when there's nowhere to keep them.
+    pub dir: Option<PathBuf>,
+    /// Sorted by name.
+    pub templates: Vec<CreatureTemplate>,
+}
+
+impl Library {
+    /// `$ROLL_INITIATIVE_LIBRARY`, or a `library` directory in the campaign's workspace.
+    pub fn default_dir() -> Option<PathBuf> {
+        match env::var_os(LIBRARY_DIR_VAR) {
+            Some(dir) => Some(PathBuf::from(dir)),
+            None => Campaign::default_dir().map(|dir| dir.join("library")),
+        }
+    }
+
+    /// Load every template in `dir`, along with a warning for each file that couldn't be read
+    /// and was left out. A directory that doesn't exist yet is an empty library.
+    pub fn open(dir: &Path) -> Result<(Self, Vec<String>)> {
+        let mut library = Library {
+            dir: Some(dir.to_path_buf()),
+            templates: vec![],
+        };
+        let mut warnings = vec![];
+        if !dir.exists() {
+            return Ok((library, warnings));
+        }
+
+        let entries = fs::read_dir(dir)
+            .with_context(|| format!("Couldn't read the library at {}", dir.display()))?;
+        for entry in entries {
+            let path = entry?.path();
+            if path
+                .extension()
+                .is_some_and(|extension| extension == "json")
+            {
+                let template = fs::read_to_string(&path)
+                    .with_context(|| format!("Couldn't read {}", path.display()))
+                    .and_then(|json| {
+                        serde_json::from_str(&json)
+                            .with_context(|| format!("{} isn't a valid template", path.display()))
+                    });
+                match template {
+                    Ok(template) => library.templates.push(template),
+                    Err(error) => warnings.push(format!("{:#}", error)),
+                }
+            }
+        }
+        library.sort();
+
+        Ok((library, warnings))
+    }
+
+    /// Write `template` to the library directory, replacing any template with the same name.
+    pub fn save(&mut self, template: CreatureTemplate) -> Result<PathBuf> {
+        let dir = self
+            .dir
+            .as_ref()
+            .context("There's no library directory to save to")?;
+        fs::create_dir_all(dir)
+            .with_context(|| format!("Couldn't create the library at {}", dir.display()))?;
+
+        let stem = file_stem(&template.name)?;
+        check_not_taken(
+            &template.name,
+            self.get(&template.name)
+                .map(|existing| existing.name.as_str()),
+        )?;
+
+        let path = dir.join(format!("{}.json", stem));
+        let json = serde_json::to_string_pretty(&template)?;
+        fs::write(&path, json).with_context(|| format!("Couldn't write {}", path.display()))?;
+
+        self.templates
+            .retain(|existing| !same_file(&existing.name, &template.name));
+        self.templates.push(template);
+        self.sort();
+
+        Ok(path)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&CreatureTemplate> {
+        self.templates
+            .iter()
+            .find(|template| same_file(&template.name, name))
+    }
+
+    /// The templates whose name or tags contain `query`, ignoring case. Names starting with the
+    /// query come first.
+    pub fn search(&self, query: &str) -> Vec<&CreatureTemplate> {
+        let query = query.trim().to_lowercase();
+
+        let (mut starts_with, others): (Vec<&CreatureTemplate>, Vec<&CreatureTemplate>) = self
+            .templates
+            .iter()
+            .filter(|template| template.matches(&query))
+            .partition(|template| template.name.to_lowercase().starts_with(&query));
+
+        starts_with.extend(others);
+        starts_with
+    }
+
+    fn sort(&mut self) {
+        self.templates
+            .sort_by_key(|template| template.name.to_lowercase());
+    }
+}
+
+/// Where the app keeps what it saves between sessions: `$XDG_DATA_HOME/roll-initiative`, or
+/// `~/.local/share/roll-initiative`.
+pub fn data_dir() -> Option<PathBuf> {
+    let data_home = env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".local/share")))?;
+    Some(data_home.join("roll-initiative"))
+}
+
+/// A file name for a template or encounter, so "Sir Reginald!" is saved as `sir-reginald.json`.
+/// A name without any letters or numbers in it has no file name.
+pub fn file_stem(name: &str) -> Result<String> {
+    let stem = name
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+    if stem.is_empty() {
+        bail!("\"{}\" needs a letter or number in it to be saved", name);
+    }
+
+    Ok(stem)
+}
+
+/// Whether `a` and `b` would be saved to the same file.
+pub fn same_file(a: &str, b: &str) -> bool {
+    matches!((file_stem(a), file_stem(b)), (Ok(a), Ok(b)) if a == b)
+}
+
+/// Make sure saving `name` won't write over something else that's saved as `existing`, like
+/// "Goblin-2" would over "Goblin 2".
+pub fn check_not_taken(name: &str, existing: Option<&str>) -> Result<()> {
+    match existing {
+        Some(existing) if existing != name => bail!(
+            "{} would be saved over {}, so it needs a different name",
+            name,
+            existing
+        ),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    fn wizard() -> Participant {
+        let mut participant = Participant::new("Sir Reginald!");
+        participant.set_hit_points(Some(22));
+        participant.set_armor_class(Some(12));
+        participant.set_initiative_modifier(3);
+        participant.set_level(Some(4));
+        participant
+    }
+
+    #[test]
+    fn test_save_and_reopen_library() {
+        let dir = env::temp_dir().join(format!(
+            "roll-initiative-library-test-{}",
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        let mut library = Library::open(&dir).unwrap().0;
+
+        let path = library
+            .save(CreatureTemplate::new(
+                &wizard(),
+                parse_tags("party, wizard"),
+            ))
+            .unwrap();
+        library
+            .save(CreatureTemplate::new(&wizard(), parse_tags("party")))
+            .unwrap();
+
+        assert_eq!(path, dir.join("sir-reginald.json"));
+        fs::write(dir.join("broken.json"), "{\"name\": ").unwrap();
+        let (reopened, warnings) = Library::open(&dir).unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("broken.json isn't a valid template"));
+        let mut imposter = wizard();
+        imposter.set_name("Sir-Reginald");
+        assert_eq!(
+            library
+                .save(CreatureTemplate::new(&imposter, vec![]))
+                .unwrap_err()
+                .to_string(),
+            "Sir-Reginald would be saved over Sir Reginald!, so it needs a different name"
+        );
+        imposter.set_name("?!");
+        assert_eq!(
+            library
+                .save(CreatureTemplate::new(&imposter, vec![]))
+                .unwrap_err()
+                .to_string(),
+            "\"?!\" needs a letter or number in it to be saved"
+        );
+        assert_eq!(reopened, library);
+        assert_eq!(reopened.templates.len(), 1);
+        assert_eq!(reopened.templates[0].tags, vec!["party"]);
+        assert_eq!(reopened.templates[0].hit_points, Some(22));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_search_by_name_or_tag() {
+        let mut ogre = Participant::new("Ogre Chieftain");
+        ogre.set_challenge_rating(Some("4".parse().unwrap()));
+        let library = Library {
+            dir: None,
+            templates: vec![
+                CreatureTemplate::new(&wizard(), vec!["party".to_string()]),
+                CreatureTemplate::new(&ogre, vec!["boss".to_string(), "giant".to_string()]),
+            ],
+        };
+
+        let names = |query| -> Vec<&str> {
+            library
+                .search(query)
+                .iter()
+                .map(|template| template.name.as_str())
+                .collect()
+        };
+
+        assert_eq!(names("PARTY"), vec!["Sir Reginald!"]);
+        assert_eq!(names("ogre"), vec!["Ogre Chieftain"]);
+        assert_eq!(names("boss"), vec!["Ogre Chieftain"]);
+        assert_eq!(names("").len(), 2);
+    }
+
+    #[test]
+    fn test_player_characters_roll_their_own_initiative() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let template = CreatureTemplate::new(&wizard(), vec![]);
+
+        let participant = template.to_participant("Reggie", &mut rng);
+
+        assert_eq!(participant.name(), "Reggie");
+        assert_eq!(participant.level(), Some(4));
+        assert_eq!(participant.hit_points(), Some(22));
+        assert_eq!(participant.initiative_rolls(), &[None]);
+
+        let monster = CreatureTemplate {
+            level: None,
+            ..template
+        };
+        assert!(monster.to_participant("Imp", &mut rng).initiative_rolls()[0].is_some());
+    }
+}
@@ -1,19 +1,48 @@
-use std::time::{Duration, Instant};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use anyhow::{Context, Result};
-use app::AppMode;
-use lazy_static::lazy_static;
-use ratatui::{
-    prelude::CrosstermBackend,
-    text::{Span, Text},
-    widgets::Block,
-    Frame, Terminal,
+use app::{
+    AppMode, EditingEncounterState, InitializeState, Participant, RunningCombatState, Workspace,
 };
+use clap::Parser;
+use color_support::ColorSupport;
+use export::{ExportFormat, ExportedOrder};
+use import::ImportReport;
+use lazy_static::lazy_static;
+#[cfg(any(unix, feature = "player-display"))]
+use player_view::{PlayerView, ViewBroadcast};
+use ratatui::{prelude::CrosstermBackend, Terminal};
+use theme::Themes;
 
 mod app;
+mod campaign;
+mod color_support;
+mod combat;
 mod commands;
+mod compendium;
+mod dice;
+mod difficulty;
+#[cfg(feature = "player-display")]
+mod display_server;
+mod export;
+mod import;
+mod initiative;
+mod library;
+mod party;
+#[cfg(unix)]
+mod player_socket;
+#[cfg(any(unix, feature = "player-display"))]
+mod player_view;
+mod quick_entry;
+mod summary;
 mod theme;
 mod ui;
+mod vtt;
 
 pub const FRAMES_PER_SECOND: u64 = 24;
 
@@ -24,9 +53,124 @@ lazy_static! {
     };
 }
 
+/// Roll for initiative and run combat encounters from the terminal.
+#[derive(Parser)]
+#[command(version, about)]
+struct Args {
+    /// Start with the participants in a CSV file (name, modifier, HP, side, rolls) or a plain
+    /// list with one per line, like `Orc x3 +1 hp:15`. `.json` files are read as characters
+    /// exported from Foundry VTT or Roll20. Can be given more than once.
+    #[arg(long, value_name = "FILE")]
+    import: Vec<PathBuf>,
+
+    /// Write the initiative order of the imported participants to a file and quit, instead of
+    /// starting the app. The format follows the extension (.md, .html or .json), and `-`
+    /// writes to standard output.
+    #[arg(long, value_name = "FILE")]
+    export: Option<PathBuf>,
+
+    /// The format to export in (markdown, html or json), whatever the file is called.
+    #[arg(long, value_name = "FORMAT", requires = "export")]
+    format: Option<ExportFormat>,
+
+    /// Serve the initiative order to a browser, for the players' screen. Enemies' hit points
+    /// are only shown as how hurt they look. Listens on 127.0.0.1:7621 unless given an address.
+    #[cfg(feature = "player-display")]
+    #[arg(
+        long,
+        value_name = "ADDRESS",
+        num_args = 0..=1,
+        default_missing_value = display_server::DEFAULT_ADDRESS
+    )]
+    serve: Option<std::net::SocketAddr>,
+
+    /// Share combat with player views in other terminals through a Unix socket at this path.
+    #[cfg(unix)]
+    #[arg(long, value_name = "SOCKET", conflicts_with = "player_view")]
+    share: Option<PathBuf>,
+
+    /// Show the combat shared by the GM at this socket, read-only, instead of running the app.
+    /// Reconnects whenever the GM restarts.
+    #[cfg(unix)]
+    #[arg(long, value_name = "SOCKET")]
+    player_view: Option<PathBuf>,
+
+    /// Start with this theme: dark, light, high-contrast, or the name of a theme file in the
+    /// themes directory. [T] switches between them while the app is running.
+    #[arg(long, value_name = "NAME")]
+    theme: Option<String>,
+
+    /// The colors the terminal can show: truecolor, 256, 16 or none. Worked out from NO_COLOR,
+    /// COLORTERM and TERM unless given.
+    #[arg(long, value_name = "COLORS")]
+    colors: Option<ColorSupport>,
+}
+
 fn main() -> Result<()> {
+    let args = Args::parse();
+
+    // Theme files are read before taking over the terminal too, so mistakes in them are seen
+    let mut themes = match Themes::default_dir() {
+        Some(dir) => Themes::open(&dir)?,
+        None => Themes::default(),
+    };
+    if let Some(name) = &args.theme {
+        themes.select(name)?;
+    }
+    themes.adapt(args.colors.unwrap_or_else(ColorSupport::detect));
+
+    #[cfg(unix)]
+    if let Some(path) = &args.player_view {
+        return player_socket::watch(path, themes.current());
+    }
+
+    // Read everything in before taking over the terminal, so mistakes are easy to see
+    let mut participants = vec![];
+    let mut report: Option<ImportReport> = None;
+    for path in &args.import {
+        let imported = import::import(path)?;
+        if !imported.warnings.is_empty() {
+            let source = ImportReport::new(&path.display().to_string(), &imported);
+            report
+                .get_or_insert_with(ImportReport::default)
+                .extend(source);
+        }
+        app::add_participants(&mut participants, imported.participants);
+    }
+
+    if let Some(path) = &args.export {
+        if let Some(report) = &report {
+            report
+                .warnings
+                .iter()
+                .for_each(|warning| eprintln!("{}", warning));
+        }
+        return export(participants, path, args.format);
+    }
+
     // Define application state
-    let mut mode = AppMode::default();
+    let (workspace, message) = Workspace::open();
+    let mut mode = AppMode::Initializing(InitializeState {
+        participants,
+        report,
+        workspace,
+        message,
+        themes,
+        ..Default::default()
+    });
+
+    // Only what the players are allowed to see is ever sent to their screens
+    #[cfg(any(unix, feature = "player-display"))]
+    let views = Arc::new(ViewBroadcast::new());
+    #[cfg(feature = "player-display")]
+    if let Some(address) = args.serve {
+        let display = display_server::DisplayServer::start(address, Arc::clone(&views))?;
+        eprintln!("Player display at http://{}", display.address());
+    }
+    #[cfg(unix)]
+    if let Some(path) = &args.share {
+        player_socket::share(path, Arc::clone(&views))?;
+    }
 
     // Enable raw mode
     crossterm::terminal::enable_raw_mode().context("Failed to enable raw mode.")?;
@@ -43,6 +187,8 @@ fn main() -> Result<()> {
         // Update the state, or quit if necessary.
         if let Ok(Some(next_state)) = mode.next_state() {
             mode = next_state;
+            #[cfg(any(unix, feature = "player-display"))]
+            views.publish(&PlayerView::new(&mode));
         } else {
             // Break out of the loop if there was an error (since we don't expect to recover from
             // errors reading user input & such), or if the next state is `None` (which means the
@@ -71,5 +217,30 @@ fn main() -> Result<()> {
     crossterm::execute!(std::io::stderr(), crossterm::terminal::LeaveAlternateScreen)?;
     crossterm::terminal::disable_raw_mode()?;
 
+    #[cfg(unix)]
+    if let Some(path) = &args.share {
+        let _ = fs::remove_file(path);
+    }
+
     Ok(())
 }
+
+/// Order the participants' turns and write them out without starting the app.
+fn export(participants: Vec<Participant>, path: &Path, format: Option<ExportFormat>) -> Result<()> {
+    let encounter = EditingEncounterState {
+        participants,
+        ..Default::default()
+    };
+    let combat = RunningCombatState::new(&encounter)
+        .context("Nobody has rolled initiative, so there's no order to export.")?;
+
+    let format = format.unwrap_or_else(|| ExportFormat::from_path(path));
+    let order = ExportedOrder::new(&combat).render(format);
+    match path.to_str() {
+        Some("-") => {
+            print!("{}", order);
+            Ok(())
+        }
+        _ => fs::write(path, order).with_context(|| format!("Failed to write {}", path.display())),
+    }
+}
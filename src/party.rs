@@ -0,0 +1,197 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{app::Participant, campaign::Campaign};
+
+/// Set this to keep the party roster somewhere other than the usual data directory.
+pub const PARTY_FILE_VAR: &str = "ROLL_INITIATIVE_PARTY";
+
+/// A player character in the saved party.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PartyMember {
+    pub name: String,
+    pub initiative_modifier: i8,
+    pub max_hit_points: Option<u16>,
+    pub armor_class: Option<u8>,
+    pub level: Option<u8>,
+    /// Hit points left after the last encounter. Only kept for the session, so everyone starts
+    /// the next one at full health.
+    #[serde(skip)]
+    pub current_hit_points: Option<u16>,
+}
+
+impl PartyMember {
+    pub fn new(participant: &Participant) -> Self {
+        PartyMember {
+            name: participant.name().to_string(),
+            initiative_modifier: participant.initiative_modifier(),
+            max_hit_points: participant.hit_points(),
+            armor_class: participant.armor_class(),
+            level: participant.level(),
+            current_hit_points: participant.current_hit_points(),
+        }
+    }
+
+    /// The member, ready for their player to roll initiative.
+    pub fn to_participant(&self) -> Participant {
+        let mut participant = Participant::new(&self.name);
+        participant.set_initiative_modifier(self.initiative_modifier);
+        participant.set_hit_points(self.max_hit_points);
+        participant.set_current_hit_points(self.current_hit_points);
+        participant.set_armor_class(self.armor_class);
+        participant.set_level(self.level);
+        participant
+    }
+}
+
+/// The party roster, saved as a JSON list of members.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Party {
+    /// Where the roster is saved. `None` when there's nowhere to keep it.
+    pub path: Option<PathBuf>,
+    pub members: Vec<PartyMember>,
+    /// Set when the roster at `path` couldn't be read, so it isn't saved over.
+    pub unreadable: bool,
+}
+
+impl Party {
+    /// `$ROLL_INITIATIVE_PARTY`, or `party.json` in the campaign's workspace.
+    pub fn default_path() -> Option<PathBuf> {
+        match env::var_os(PARTY_FILE_VAR) {
+            Some(path) => Some(PathBuf::from(path)),
+            None => Campaign::default_dir().map(|dir| dir.join("party.json")),
+        }
+    }
+
+    /// Load the roster at `path`. A roster that hasn't been saved yet is an empty party.
+    pub fn open(path: &Path) -> Result<Self> {
+        let members = match path.exists() {
+            true => {
+                let json = fs::read_to_string(path)
+                    .with_context(|| format!("Couldn't read the party at {}", path.display()))?;
+                serde_json::from_str(&json)
+                    .with_context(|| format!("{} isn't a valid party", path.display()))?
+            }
+            false => vec![],
+        };
+
+        Ok(Party {
+            path: Some(path.to_path_buf()),
+            members,
+            unreadable: false,
+        })
+    }
+
+    /// An empty party in place of the roster at `path`, which couldn't be read. Saving it would
+    /// lose the roster, so it can't be saved.
+    pub fn unreadable(path: &Path) -> Self {
+        Party {
+            path: Some(path.to_path_buf()),
+            members: vec![],
+            unreadable: true,
+        }
+    }
+
+    /// Replace the roster with `members` and write it out.
+    pub fn save(&mut self, members: Vec<PartyMember>) -> Result<PathBuf> {
+        let path = self
+            .path
+            .as_ref()
+            .context("There's nowhere to save the party")?;
+        if self.unreadable {
+            bail!(
+                "{} couldn't be read, so it won't be saved over",
+                path.display()
+            );
+        }
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Couldn't create {}", dir.display()))?;
+        }
+
+        let json = serde_json::to_string_pretty(&members)?;
+        fs::write(path, json).with_context(|| format!("Couldn't write {}", path.display()))?;
+        self.members = members;
+
+        Ok(path.clone())
+    }
+
+    /// Remember how many hit points `name` has left, if they're in the party.
+    pub fn set_current_hit_points(&mut self, name: &str, current_hit_points: Option<u16>) {
+        if let Some(member) = self.members.iter_mut().find(|member| member.name == name) {
+            member.current_hit_points = current_hit_points;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cleric() -> Participant {
+        let mut participant = Participant::new("Shadowheart");
+        participant.set_initiative_modifier(1);
+        participant.set_hit_points(Some(27));
+        participant.set_armor_class(Some(18));
+        participant.set_level(Some(3));
+        participant
+    }
+
+    #[test]
+    fn test_save_and_reopen_party() {
+        let path = env::temp_dir().join(format!(
+            "roll-initiative-party-test-{}.json",
+            std::process::id()
+        ));
+        let _ = fs::remove_file(&path);
+        let mut party = Party::open(&path).unwrap();
+        assert!(party.members.is_empty());
+
+        let mut member = PartyMember::new(&cleric());
+        member.current_hit_points = Some(9);
+        party.save(vec![member]).unwrap();
+
+        let reopened = Party::open(&path).unwrap();
+        assert_eq!(reopened.members.len(), 1);
+        assert_eq!(reopened.members[0].max_hit_points, Some(27));
+        // Current hit points only last for the session
+        assert_eq!(reopened.members[0].current_hit_points, None);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_unreadable_party_is_not_saved_over() {
+        let path = env::temp_dir().join(format!(
+            "roll-initiative-unreadable-party-test-{}.json",
+            std::process::id()
+        ));
+        fs::write(&path, "[{\"name\": ").unwrap();
+        assert!(Party::open(&path).is_err());
+
+        let mut party = Party::unreadable(&path);
+        assert!(party.save(vec![PartyMember::new(&cleric())]).is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "[{\"name\": ");
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_members_keep_current_hit_points() {
+        let mut party = Party {
+            members: vec![PartyMember::new(&cleric())],
+            ..Default::default()
+        };
+
+        party.set_current_hit_points("Shadowheart", Some(4));
+        let participant = party.members[0].to_participant();
+
+        assert_eq!(participant.hit_points(), Some(27));
+        assert_eq!(participant.current_hit_points(), Some(4));
+        assert_eq!(participant.initiative_rolls(), &[None]);
+    }
+}
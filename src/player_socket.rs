@@ -0,0 +1,149 @@
+use std::{
+    fs,
+    io::{BufRead, BufReader, Write},
+    os::unix::{
+        fs::FileTypeExt,
+        net::{UnixListener, UnixStream},
+    },
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{self, Sender},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+use anyhow::{Context, Result};
+use crossterm::event::KeyCode;
+use ratatui::{prelude::CrosstermBackend, Terminal};
+
+use crate::{
+    app::poll_for_keypress,
+    player_view::{PlayerView, ViewBroadcast},
+    theme::Theme,
+    ui::elements,
+};
+
+/// How long the player view waits before trying the GM's socket again.
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+/// Send the player view to anyone who connects to a Unix domain socket at `path`, as one line
+/// of JSON for every change.
+pub fn share(path: &Path, views: Arc<ViewBroadcast>) -> Result<()> {
+    // A GM instance that didn't shut down cleanly leaves its socket behind
+    let is_socket = fs::metadata(path).is_ok_and(|metadata| metadata.file_type().is_socket());
+    if is_socket {
+        fs::remove_file(path).with_context(|| format!("Couldn't replace {}", path.display()))?;
+    }
+
+    let listener = UnixListener::bind(path)
+        .with_context(|| format!("Couldn't share the player view at {}", path.display()))?;
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let views = Arc::clone(&views);
+            thread::spawn(move || send_views(stream, &views));
+        }
+    });
+
+    Ok(())
+}
+
+/// Write every new view to `stream`, until the player view goes away.
+fn send_views(mut stream: UnixStream, views: &ViewBroadcast) -> Result<()> {
+    let mut sent = None;
+
+    loop {
+        let (version, json) = views.wait_for_change(sent);
+        writeln!(stream, "{}", json)?;
+        sent = Some(version);
+    }
+}
+
+/// Keep reading views from the GM's socket at `path`. `None` is sent whenever the connection
+/// is lost, and it's tried again until the player view quits.
+fn subscribe(path: PathBuf, updates: Sender<Option<PlayerView>>) {
+    loop {
+        if let Ok(stream) = UnixStream::connect(&path) {
+            for line in BufReader::new(stream).lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                if let Ok(view) = serde_json::from_str(&line) {
+                    if updates.send(Some(view)).is_err() {
+                        return;
+                    }
+                }
+            }
+        }
+
+        if updates.send(None).is_err() {
+            return;
+        }
+        thread::sleep(RECONNECT_DELAY);
+    }
+}
+
+/// Show the combat shared by the GM at `path`, read-only, until `q` is pressed.
+pub fn watch(path: &Path, theme: &Theme) -> Result<()> {
+    let (updates, received) = mpsc::channel();
+    let subscribed = path.to_path_buf();
+    thread::spawn(move || subscribe(subscribed, updates));
+
+    crossterm::terminal::enable_raw_mode().context("Failed to enable raw mode.")?;
+    crossterm::execute!(std::io::stderr(), crossterm::terminal::EnterAlternateScreen)
+        .context("Failed to enter alternate screen.")?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(std::io::stderr()))
+        .context("Failed to start new Terminal with CrosstermBackend.")?;
+
+    let mut view = None;
+    loop {
+        // Polling waits for up to a frame, which paces the loop
+        if let Some(KeyCode::Char('q') | KeyCode::Esc) = poll_for_keypress()? {
+            break;
+        }
+        while let Ok(update) = received.try_recv() {
+            view = update;
+        }
+
+        terminal.draw(|frame| {
+            elements::player_view_screen(frame, theme, view.as_ref(), path, frame.size());
+        })?;
+    }
+
+    crossterm::execute!(std::io::stderr(), crossterm::terminal::LeaveAlternateScreen)?;
+    crossterm::terminal::disable_raw_mode()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_views_are_sent_as_they_change() {
+        let path = std::env::temp_dir().join(format!(
+            "roll-initiative-socket-test-{}.sock",
+            std::process::id()
+        ));
+        let views = Arc::new(ViewBroadcast::new());
+        share(&path, Arc::clone(&views)).unwrap();
+        let (updates, received) = mpsc::channel();
+        let subscribed = path.clone();
+        thread::spawn(move || subscribe(subscribed, updates));
+
+        assert_eq!(received.recv().unwrap(), Some(PlayerView::default()));
+
+        let view = PlayerView {
+            round: Some(3),
+            turns: vec![],
+        };
+        views.publish(&view);
+        assert_eq!(received.recv().unwrap(), Some(view));
+
+        // Sharing again replaces the old socket, as a restarted GM would
+        share(&path, Arc::clone(&views)).unwrap();
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,255 @@
+use core::fmt;
+use std::{
+    fmt::{Display, Formatter},
+    sync::{Condvar, Mutex},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app::{AppMode, CombatTurn, RunningCombatState, Side, TurnKind},
+    combat::HitPoints,
+};
+
+/// How hurt a creature looks, for players who shouldn't see its hit points.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Health {
+    Unhurt,
+    Hurt,
+    /// At half its hit points or below.
+    Bloodied,
+    Down,
+    Dead,
+}
+
+impl Health {
+    pub fn new(hit_points: HitPoints, dead: bool) -> Self {
+        match hit_points {
+            _ if dead => Health::Dead,
+            HitPoints { current: 0, .. } => Health::Down,
+            HitPoints { current, max } if current as u32 * 2 <= max as u32 => Health::Bloodied,
+            HitPoints { current, max } if current < max => Health::Hurt,
+            _ => Health::Unhurt,
+        }
+    }
+}
+
+impl Display for Health {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Health::Unhurt => write!(f, "Unhurt"),
+            Health::Hurt => write!(f, "Hurt"),
+            Health::Bloodied => write!(f, "Bloodied"),
+            Health::Down => write!(f, "Down"),
+            Health::Dead => write!(f, "Dead"),
+        }
+    }
+}
+
+/// What the players are allowed to see of combat: the order of turns and how everyone looks,
+/// but not the hit points of anyone outside the party, or anyone hidden.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlayerView {
+    /// `None` until combat starts.
+    pub round: Option<u32>,
+    pub turns: Vec<PlayerTurn>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlayerTurn {
+    pub name: String,
+    pub current: bool,
+    pub delayed: bool,
+    pub health: Option<Health>,
+    /// Exact hit points, only for allies.
+    pub hit_points: Option<HitPoints>,
+    pub conditions: Vec<String>,
+}
+
+impl PlayerView {
+    /// The players' view of the combat running in any tab, whichever tab the GM is looking at.
+    /// Outside of combat there's nothing to see.
+    pub fn new(mode: &AppMode) -> Self {
+        match mode {
+            AppMode::Running(tabs) => tabs
+                .running_combat()
+                .map(PlayerView::from_combat)
+                .unwrap_or_default(),
+            _ => PlayerView::default(),
+        }
+    }
+
+    pub fn from_combat(combat: &RunningCombatState) -> Self {
+        let turn = |turn: &CombatTurn, current: bool, delayed: bool| {
+            let combatant = combat.combatant(turn.name());
+            let hit_points = combatant.and_then(|combatant| combatant.hit_points);
+            let is_ally = combat
+                .participants
+                .iter()
+                .find(|participant| participant.name() == turn.name())
+                .is_some_and(|participant| participant.side() == Some(Side::Ally));
+
+            PlayerTurn {
+                name: match turn.kind() {
+                    TurnKind::Combatant => turn.name().to_string(),
+                    // The lair's name might give away what's waiting there
+                    TurnKind::LairAction => String::from("Lair action"),
+                },
+                current,
+                delayed,
+                health: hit_points
+                    .map(|hit_points| Health::new(hit_points, turn.dead()))
+                    .or(turn.dead().then_some(Health::Dead)),
+                hit_points: hit_points.filter(|_| is_ally),
+                conditions: combatant
+                    .map(|combatant| combatant.conditions.clone())
+                    .unwrap_or_default(),
+            }
+        };
+
+        PlayerView {
+            round: Some(combat.round),
+            turns: combat
+                .turns
+                .iter()
+                .enumerate()
+                .map(|(index, t)| (t, index == combat.current_turn, false))
+                .chain(combat.delayed.iter().map(|t| (t, false, true)))
+                .filter(|(t, _, _)| !t.hidden())
+                .map(|(t, current, delayed)| turn(t, current, delayed))
+                .collect(),
+        }
+    }
+}
+
+/// The latest player view as JSON, numbered so that whoever is showing it can tell when it has
+/// changed. Shared between the app and the threads sending it to the players' screens.
+#[derive(Debug, Default)]
+pub struct ViewBroadcast {
+    latest: Mutex<(u64, String)>,
+    changed: Condvar,
+}
+
+impl ViewBroadcast {
+    pub fn new() -> Self {
+        let broadcast = ViewBroadcast::default();
+        broadcast.publish(&PlayerView::default());
+        broadcast
+    }
+
+    /// Pass `view` on to everyone waiting, if it's any different from the last one.
+    pub fn publish(&self, view: &PlayerView) {
+        let Ok(json) = serde_json::to_string(view) else {
+            return;
+        };
+
+        let mut latest = self.latest.lock().unwrap();
+        if latest.1 != json {
+            *latest = (latest.0 + 1, json);
+            self.changed.notify_all();
+        }
+    }
+
+    #[cfg(feature = "player-display")]
+    pub fn latest(&self) -> (u64, String) {
+        self.latest.lock().unwrap().clone()
+    }
+
+    /// Wait until there's a view other than number `seen`, and return it with its number.
+    pub fn wait_for_change(&self, seen: Option<u64>) -> (u64, String) {
+        self.changed
+            .wait_while(self.latest.lock().unwrap(), |(version, _)| {
+                Some(*version) == seen
+            })
+            .unwrap()
+            .clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::app::{EditingEncounterState, Participant, RunMode, Tabs};
+
+    use super::*;
+
+    #[test]
+    fn test_health_bands() {
+        let health = |current, max| Health::new(HitPoints { current, max }, false);
+
+        assert_eq!(health(20, 20), Health::Unhurt);
+        assert_eq!(health(11, 20), Health::Hurt);
+        assert_eq!(health(10, 20), Health::Bloodied);
+        assert_eq!(health(0, 20), Health::Down);
+        assert_eq!(
+            Health::new(
+                HitPoints {
+                    current: 0,
+                    max: 20
+                },
+                true
+            ),
+            Health::Dead
+        );
+    }
+
+    #[test]
+    fn test_only_allies_hit_points_are_shown() {
+        let mut fighter = Participant::new("Lae'zel");
+        fighter.set_side(Some(Side::Ally));
+        fighter.set_hit_points(Some(34));
+        fighter.set_initiative_roll(0, Some(15));
+        let mut orc = Participant::new("Orc");
+        orc.set_hit_points(Some(15));
+        orc.set_initiative_roll(0, Some(12));
+        let encounter = EditingEncounterState {
+            participants: vec![fighter, orc],
+            ..Default::default()
+        };
+        let combat = RunningCombatState::new(&encounter).unwrap();
+
+        let view = PlayerView::from_combat(&combat);
+
+        assert_eq!(view.round, Some(1));
+        assert!(view.turns[0].current);
+        assert_eq!(
+            view.turns[0].hit_points,
+            Some(HitPoints {
+                current: 34,
+                max: 34
+            })
+        );
+        assert_eq!(view.turns[1].hit_points, None);
+        assert_eq!(view.turns[1].health, Some(Health::Unhurt));
+
+        let json = serde_json::to_string(&view).unwrap();
+        assert!(!json.contains("\"max\":15"));
+        assert_eq!(PlayerView::new(&AppMode::default()), PlayerView::default());
+    }
+
+    #[test]
+    fn test_players_keep_seeing_combat_while_the_gm_switches_tabs() {
+        let mut orc = Participant::new("Orc");
+        orc.set_initiative_roll(0, Some(12));
+        let encounter = EditingEncounterState {
+            participants: vec![orc],
+            ..Default::default()
+        };
+        let combat = RunningCombatState::new(&encounter).unwrap();
+        let mut tabs = Tabs::from(RunMode::EditingEncounter(Box::default()));
+        tabs.tabs[0].mode = RunMode::RunningCombat(Box::new(combat.clone()));
+
+        tabs.new_tab();
+        assert_eq!(tabs.selected, 1);
+        assert_eq!(
+            PlayerView::new(&AppMode::Running(tabs.clone())),
+            PlayerView::from_combat(&combat)
+        );
+
+        tabs.open_browser();
+        assert_eq!(
+            PlayerView::new(&AppMode::Running(tabs)),
+            PlayerView::from_combat(&combat)
+        );
+    }
+}
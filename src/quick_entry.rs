@@ -0,0 +1,258 @@
+use std::str::FromStr;
+
+use crate::{
+    app::{self, ParseParticipantError, Participant},
+    difficulty::MAX_LEVEL,
+};
+
+/// The most copies of a participant one line can add.
+pub const MAX_GROUP_SIZE: u8 = 50;
+
+/// The fields that can follow a name, as `field:value`.
+pub const FIELDS: [&str; 7] = ["hp", "ac", "side", "init", "level", "cr", "legendary"];
+
+/// A participant typed in on one line, such as `Orc x3 +1 hp:15 ac:13 side:enemy init:14,9`.
+///
+/// The name comes first, then any of: `x3` for a group of identical creatures, `+1` for the
+/// initiative modifier, and `field:value` options. `init` takes a roll for each initiative
+/// slot, and every member of a group shares them, since groups act together.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct QuickEntry {
+    pub participant: Participant,
+    pub count: u8,
+}
+
+impl QuickEntry {
+    /// One participant per member of the group, named like "Orc", "Orc 2" and "Orc 3".
+    pub fn participants(&self) -> Vec<Participant> {
+        let mut members = vec![];
+        app::add_participants(
+            &mut members,
+            vec![self.participant.clone(); self.count as usize],
+        );
+        members
+    }
+}
+
+impl FromStr for QuickEntry {
+    type Err = ParseParticipantError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut name: Vec<&str> = vec![];
+        let mut count = None;
+        let mut modifier = None;
+        let mut options: Vec<(String, &str)> = vec![];
+
+        for word in s.split_whitespace() {
+            let seen_options = count.is_some() || modifier.is_some() || !options.is_empty();
+
+            if let Some(size) = group_size(word) {
+                if count.replace(size?).is_some() {
+                    return Err(ParseParticipantError::RepeatedField(String::from("x")));
+                }
+            } else if is_modifier(word) {
+                let value = word
+                    .parse()
+                    .map_err(|_| invalid("initiative modifier", word))?;
+                if modifier.replace(value).is_some() {
+                    return Err(ParseParticipantError::RepeatedField(String::from(
+                        "initiative modifier",
+                    )));
+                }
+            } else if let Some((field, value)) = word.split_once(':') {
+                let field = field.to_lowercase();
+                if options.iter().any(|(seen, _)| *seen == field) {
+                    return Err(ParseParticipantError::RepeatedField(field));
+                }
+                options.push((field, value));
+            } else if seen_options {
+                return Err(ParseParticipantError::UnexpectedWord(word.to_string()));
+            } else {
+                name.push(word);
+            }
+        }
+
+        if name.is_empty() {
+            return Err(ParseParticipantError::EmptyName);
+        }
+        let mut participant = Participant::new(&name.join(" "));
+        if let Some(modifier) = modifier {
+            participant.set_initiative_modifier(modifier);
+        }
+        for (field, value) in options {
+            set_field(&mut participant, &field, value)?;
+        }
+
+        Ok(QuickEntry {
+            participant,
+            count: count.unwrap_or(1),
+        })
+    }
+}
+
+/// `Some` if `word` looks like `x3`, with an error if the size is out of range.
+fn group_size(word: &str) -> Option<Result<u8, ParseParticipantError>> {
+    let digits = word.strip_prefix(['x', 'X'])?;
+    if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    Some(
+        digits
+            .parse()
+            .ok()
+            .filter(|size| (1..=MAX_GROUP_SIZE).contains(size))
+            .ok_or_else(|| ParseParticipantError::InvalidCount(word.to_string())),
+    )
+}
+
+fn is_modifier(word: &str) -> bool {
+    word.strip_prefix(['+', '-'])
+        .is_some_and(|digits| !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit()))
+}
+
+fn invalid(field: &'static str, value: &str) -> ParseParticipantError {
+    ParseParticipantError::InvalidValue {
+        field,
+        value: value.to_string(),
+    }
+}
+
+fn set_field(
+    participant: &mut Participant,
+    field: &str,
+    value: &str,
+) -> Result<(), ParseParticipantError> {
+    match field {
+        "hp" => {
+            let hit_points = value.parse().map_err(|_| invalid("hit points", value))?;
+            participant.set_hit_points(Some(hit_points));
+        }
+        "ac" => {
+            let armor_class = value.parse().map_err(|_| invalid("armor class", value))?;
+            participant.set_armor_class(Some(armor_class));
+        }
+        "side" => participant.set_side(Some(value.parse()?)),
+        "init" => {
+            for (slot, roll) in value.split(',').enumerate() {
+                let roll = roll
+                    .trim()
+                    .parse()
+                    .map_err(|_| invalid("initiative roll", roll))?;
+                if slot > 0 {
+                    participant.add_initiative_slot();
+                }
+                participant.set_initiative_roll(slot, Some(roll));
+            }
+        }
+        "level" => {
+            let level = value
+                .parse()
+                .ok()
+                .filter(|level| (1..=MAX_LEVEL).contains(level))
+                .ok_or_else(|| invalid("level", value))?;
+            participant.set_level(Some(level));
+        }
+        "cr" => {
+            let rating = value
+                .parse()
+                .map_err(|_| invalid("challenge rating", value))?;
+            participant.set_challenge_rating(Some(rating));
+        }
+        "legendary" => {
+            let actions = value
+                .parse()
+                .map_err(|_| invalid("number of legendary actions", value))?;
+            participant.set_legendary_actions(actions);
+        }
+        _ => return Err(ParseParticipantError::UnknownField(field.to_string())),
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::app::Side;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_full_entry() {
+        let entry: QuickEntry = "Orc War Chief x3 +1 hp:15 ac:13 side:enemy init:14,9 cr:1/2"
+            .parse()
+            .unwrap();
+        let orc = &entry.participant;
+
+        assert_eq!(entry.count, 3);
+        assert_eq!(orc.name(), "Orc War Chief");
+        assert_eq!(orc.initiative_modifier(), 1);
+        assert_eq!(orc.hit_points(), Some(15));
+        assert_eq!(orc.armor_class(), Some(13));
+        assert_eq!(orc.side(), Some(Side::Enemy));
+        assert_eq!(orc.initiative_rolls(), &[Some(14), Some(9)]);
+        assert_eq!(
+            orc.challenge_rating().map(|cr| cr.to_string()),
+            Some("1/2".into())
+        );
+
+        let names: Vec<String> = entry
+            .participants()
+            .iter()
+            .map(|p| p.name().to_string())
+            .collect();
+        assert_eq!(
+            names,
+            vec!["Orc War Chief", "Orc War Chief 2", "Orc War Chief 3"]
+        );
+    }
+
+    #[test]
+    fn test_a_plain_name_is_a_single_participant() {
+        let entry: QuickEntry = "  Gandalf the Grey ".parse().unwrap();
+
+        assert_eq!(entry.count, 1);
+        assert_eq!(entry.participant, Participant::new("Gandalf the Grey"));
+    }
+
+    #[test]
+    fn test_parse_errors_say_what_was_wrong() {
+        let error = |s: &str| s.parse::<QuickEntry>().unwrap_err();
+
+        assert_eq!(error("x3 hp:7"), ParseParticipantError::EmptyName);
+        assert_eq!(
+            error("Orc hp:lots"),
+            ParseParticipantError::InvalidValue {
+                field: "hit points",
+                value: "lots".to_string()
+            }
+        );
+        assert_eq!(
+            error("Orc speed:30"),
+            ParseParticipantError::UnknownField("speed".to_string())
+        );
+        assert_eq!(
+            error("Orc hp:7 HP:8"),
+            ParseParticipantError::RepeatedField("hp".to_string())
+        );
+        assert_eq!(
+            error("Orc +1 Chieftain"),
+            ParseParticipantError::UnexpectedWord("Chieftain".to_string())
+        );
+        assert_eq!(
+            error("Orc x0"),
+            ParseParticipantError::InvalidCount("x0".to_string())
+        );
+        assert_eq!(
+            error("Orc side:sneaky"),
+            ParseParticipantError::InvalidSide("sneaky".to_string())
+        );
+
+        let group = "Orc x2".parse::<Participant>().unwrap_err();
+        assert_eq!(group, ParseParticipantError::Group(2));
+        assert_eq!(
+            group.to_string(),
+            "this is a group of 2, not a single participant"
+        );
+    }
+}
@@ -0,0 +1,229 @@
+use std::time::Duration;
+
+use crate::combat::{CombatEvent, CombatLogEntry, Combatant, GameTime};
+
+/// How one creature fared over a whole combat.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ParticipantSummary {
+    pub name: String,
+    pub damage_dealt: u32,
+    pub damage_taken: u32,
+    /// Creatures dropped to 0 HP during this creature's turns.
+    pub knockouts: u32,
+    /// Creatures that died during this creature's turns.
+    pub kills: u32,
+    pub times_downed: u32,
+    pub turns_taken: u32,
+    pub time_spent: Duration,
+    /// Never revealed to the players, so left out of the saved summary.
+    pub hidden: bool,
+}
+
+impl ParticipantSummary {
+    pub fn average_turn_duration(&self) -> Option<Duration> {
+        match self.turns_taken {
+            0 => None,
+            turns => Some(self.time_spent / turns),
+        }
+    }
+}
+
+/// The statistics shown once combat is over.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CombatSummary {
+    pub rounds: u32,
+    pub participants: Vec<ParticipantSummary>,
+}
+
+impl CombatSummary {
+    /// Work out the statistics from the combat log. Damage, knockouts and kills are credited
+    /// to whoever's turn it was when they happened. The `hidden` participants were never
+    /// revealed.
+    pub fn new(log: &[CombatLogEntry], combatants: &[Combatant], hidden: &[String]) -> Self {
+        let mut participants: Vec<ParticipantSummary> = combatants
+            .iter()
+            .map(|combatant| ParticipantSummary {
+                name: combatant.name.clone(),
+                time_spent: combatant.time_spent.elapsed(),
+                hidden: hidden.contains(&combatant.name),
+                ..Default::default()
+            })
+            .collect();
+
+        let find = |participants: &mut Vec<ParticipantSummary>, name: &str| {
+            participants
+                .iter_mut()
+                .position(|participant| participant.name == name)
+        };
+
+        let mut acting: Option<usize> = None;
+        for entry in log {
+            match &entry.event {
+                CombatEvent::TurnStarted { name } => {
+                    acting = find(&mut participants, name);
+                    if let Some(index) = acting {
+                        participants[index].turns_taken += 1;
+                    }
+                }
+                CombatEvent::Damaged { name, amount } => {
+                    if let Some(index) = find(&mut participants, name) {
+                        participants[index].damage_taken += *amount as u32;
+                    }
+                    if let Some(index) = acting {
+                        participants[index].damage_dealt += *amount as u32;
+                    }
+                }
+                CombatEvent::KnockedOut { name } => {
+                    if let Some(index) = find(&mut participants, name) {
+                        participants[index].times_downed += 1;
+                    }
+                    if let Some(index) = acting {
+                        participants[index].knockouts += 1;
+                    }
+                }
+                CombatEvent::Died { .. } => {
+                    if let Some(index) = acting {
+                        participants[index].kills += 1;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        CombatSummary {
+            rounds: log.last().map(|entry| entry.round).unwrap_or_default(),
+            participants,
+        }
+    }
+
+    /// The in-game time the combat took, counting every round that was started.
+    pub fn elapsed(&self) -> GameTime {
+        GameTime::from_rounds(self.rounds)
+    }
+
+    /// The summary as a Markdown table, for saving. Hidden participants are left out.
+    pub fn to_markdown(&self) -> String {
+        let mut markdown = format!(
+            "# Combat summary\n\n{} rounds, {} of game time\n\n",
+            self.rounds,
+            self.elapsed()
+        );
+        markdown.push_str(
+            "| Name | Damage dealt | Damage taken | Knockouts | Kills | Downed | Avg. turn |\n",
+        );
+        markdown.push_str("| --- | --- | --- | --- | --- | --- | --- |\n");
+
+        for participant in self.participants.iter().filter(|p| !p.hidden) {
+            markdown.push_str(&format!(
+                "| {} | {} | {} | {} | {} | {} | {} |\n",
+                participant.name,
+                participant.damage_dealt,
+                participant.damage_taken,
+                participant.knockouts,
+                participant.kills,
+                participant.times_downed,
+                format_turn_duration(participant.average_turn_duration()),
+            ));
+        }
+
+        markdown
+    }
+}
+
+/// A turn duration in seconds, or a dash for someone who never took a turn.
+pub fn format_turn_duration(duration: Option<Duration>) -> String {
+    match duration {
+        Some(duration) => format!("{:.1}s", duration.as_secs_f32()),
+        None => String::from("-"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::combat::Stopwatch;
+
+    use super::*;
+
+    fn combatant(name: &str, frames: u64) -> Combatant {
+        Combatant {
+            name: name.to_string(),
+            hit_points: None,
+            concentration: None,
+            time_spent: Stopwatch { frames },
+            conditions: vec![],
+            reaction_used: false,
+            reaction_round: None,
+        }
+    }
+
+    #[test]
+    fn test_damage_is_credited_to_the_acting_combatant() {
+        let entry = |round, event| CombatLogEntry { round, event };
+        let log = vec![
+            entry(1, CombatEvent::RoundStarted),
+            entry(
+                1,
+                CombatEvent::TurnStarted {
+                    name: "Legolas".to_string(),
+                },
+            ),
+            entry(
+                1,
+                CombatEvent::Damaged {
+                    name: "Orc".to_string(),
+                    amount: 12,
+                },
+            ),
+            entry(
+                1,
+                CombatEvent::KnockedOut {
+                    name: "Orc".to_string(),
+                },
+            ),
+            entry(
+                1,
+                CombatEvent::Died {
+                    name: "Orc".to_string(),
+                },
+            ),
+            entry(2, CombatEvent::RoundStarted),
+            entry(
+                2,
+                CombatEvent::TurnStarted {
+                    name: "Legolas".to_string(),
+                },
+            ),
+        ];
+        let combatants = [
+            combatant("Legolas", 48 * crate::FRAMES_PER_SECOND),
+            combatant("Orc", 0),
+        ];
+
+        let summary = CombatSummary::new(&log, &combatants, &["Orc".to_string()]);
+
+        assert_eq!(summary.rounds, 2);
+        assert_eq!(
+            summary.participants[0],
+            ParticipantSummary {
+                name: "Legolas".to_string(),
+                damage_dealt: 12,
+                knockouts: 1,
+                kills: 1,
+                turns_taken: 2,
+                time_spent: Duration::from_secs(48),
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            summary.participants[0].average_turn_duration(),
+            Some(Duration::from_secs(24))
+        );
+        assert_eq!(summary.participants[1].damage_taken, 12);
+        assert_eq!(summary.participants[1].times_downed, 1);
+        assert_eq!(summary.participants[1].average_turn_duration(), None);
+        assert!(summary.participants[1].hidden);
+        let markdown = summary.to_markdown();
+        assert!(markdown.contains("| Legolas | 12 | 0 | 1 | 1 | 0 | 24.0s |"));
+        assert!(!markdown.contains("Orc"));
+    }
+}
@@ -1,5 +1,16 @@
-use ratatui::style::{Color, Style};
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use ratatui::style::{Color, Modifier, Style};
 
+use crate::{color_support::ColorSupport, library};
+
+// Not every color in the palette is in use yet
+#[allow(dead_code)]
 mod colors {
     use super::*;
 
@@ -17,8 +28,18 @@ mod colors {
     const LAVENDER: Color = Color::Rgb(230, 230, 250);
     const LILAC: Color = Color::Rgb(200, 162, 200);
 
+    const PAPER: Color = Color::Rgb(250, 250, 247);
+    const LINEN: Color = Color::Rgb(238, 234, 224);
+    const ASH: Color = Color::Rgb(150, 150, 150);
+    const INK: Color = Color::Rgb(30, 30, 40);
+    const NAVY: Color = Color::Rgb(28, 52, 110);
+    const MIST: Color = Color::Rgb(205, 215, 240);
+    const CORNFLOWER: Color = Color::Rgb(150, 175, 235);
+    const RUST: Color = Color::Rgb(170, 70, 20);
+
     const STRAW: Color = Color::Rgb(255, 204, 102);
     const AMBER: Color = Color::Rgb(255, 191, 0);
+    const CRIMSON: Color = Color::Rgb(220, 20, 60);
 
     // Theme palette
     pub const BG_DARKEST: Color = BLACK;
@@ -35,12 +56,34 @@ mod colors {
     pub const TEXT_IMPORTANT: Color = WHITE;
     pub const TEXT_HEADING: Color = AMBER;
     pub const TEXT_TITLE: Color = STRAW;
+    pub const TEXT_WARNING: Color = AMBER;
+    pub const TEXT_DANGER: Color = CRIMSON;
 
     // Light refers to light-background
     pub const TEXT_BODY_LIGHT: Color = GRANITE;
     pub const TEXT_IMPORTANT_LIGHT: Color = BLACK;
+
+    /// The palette of the light theme, for terminals with a light background.
+    pub mod light {
+        use super::*;
+
+        pub const BG: Color = PAPER;
+        pub const BG_SHADED: Color = LINEN;
+        pub const BG_FOCUSED: Color = MIST;
+        pub const BG_SELECTED: Color = CORNFLOWER;
+
+        pub const TEXT_BODY: Color = INK;
+        pub const TEXT_BODY_SUBTLE: Color = ASH;
+        pub const TEXT_IMPORTANT: Color = BLACK;
+        pub const TEXT_HEADING: Color = NAVY;
+        pub const TEXT_TITLE: Color = RUST;
+        pub const TEXT_WARNING: Color = RUST;
+        pub const TEXT_DANGER: Color = CRIMSON;
+        pub const TEXT_ON_DANGER: Color = WHITE;
+    }
 }
 
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Theme {
     pub root: Style,
     pub app_title: Style,
@@ -49,10 +92,13 @@ pub struct Theme {
     pub tab_selected: Style,
 
     pub edit_participants_tab: EditParticipantsTabTheme,
+    pub running_combat_tab: RunningCombatTabTheme,
+    pub difficulty: DifficultyTheme,
 
     pub controls_panel: ControlsPanelTheme,
 }
 
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct ControlsPanelTheme {
     pub bg: Style,
     pub text: Style,
@@ -60,6 +106,7 @@ pub struct ControlsPanelTheme {
     pub border_color: Color,
 }
 
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct EditParticipantsTabTheme {
     pub root: Style,
     pub column_heading: Style,
@@ -67,17 +114,37 @@ pub struct EditParticipantsTabTheme {
     pub participant_row_alt: Style,
     pub participant_row_focused: Style,
     pub participant_col_focused: Style,
+    /// Participants the players can't see yet.
+    pub participant_row_hidden: Style,
 }
 
-// TODO - Come back to this after edit participants tab works
-#[allow(dead_code)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DifficultyTheme {
+    pub easy: Style,
+    pub medium: Style,
+    pub hard: Style,
+    pub deadly: Style,
+    pub details: Style,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct RunningCombatTabTheme {
     pub root: Style,
+    pub column_heading: Style,
     pub participant: Style,
     pub participant_focused: Style,
+    pub participant_active: Style,
+    pub participant_down: Style,
+    pub participant_hidden: Style,
+    pub economy_available: Style,
+    pub economy_used: Style,
+    pub timer: Style,
+    pub timer_warning: Style,
+    pub timer_expired: Style,
 }
 
-pub const THEME: Theme = {
+/// The theme the app has always had, for terminals with a dark background.
+pub const DARK: Theme = {
     Theme {
         root: Style::new().fg(colors::TEXT_BODY),
         app_title: Style::new().fg(colors::TEXT_TITLE),
@@ -106,6 +173,659 @@ pub const THEME: Theme = {
             participant_col_focused: Style::new()
                 .fg(colors::TEXT_IMPORTANT_LIGHT)
                 .bg(colors::BG_LIGHTEST),
+            participant_row_hidden: Style::new()
+                .fg(colors::TEXT_BODY_SUBTLE)
+                .add_modifier(Modifier::ITALIC),
+        },
+        difficulty: DifficultyTheme {
+            easy: Style::new().fg(colors::TEXT_BODY),
+            medium: Style::new().fg(colors::TEXT_TITLE),
+            hard: Style::new().fg(colors::TEXT_WARNING),
+            deadly: Style::new()
+                .fg(colors::TEXT_IMPORTANT)
+                .bg(colors::TEXT_DANGER),
+            details: Style::new().fg(colors::TEXT_BODY_SUBTLE),
+        },
+        running_combat_tab: RunningCombatTabTheme {
+            root: Style::new().fg(colors::TEXT_BODY),
+            column_heading: Style::new()
+                .fg(colors::TEXT_HEADING)
+                .bg(colors::BG_DARK_IMPORTANT),
+            participant: Style::new().fg(colors::TEXT_BODY),
+            participant_focused: Style::new()
+                .fg(colors::TEXT_BODY_LIGHT)
+                .bg(colors::BG_LIGHT),
+            participant_active: Style::new()
+                .fg(colors::TEXT_IMPORTANT_LIGHT)
+                .bg(colors::BG_LIGHTEST),
+            participant_down: Style::new().fg(colors::TEXT_BODY_SUBTLE),
+            participant_hidden: Style::new()
+                .fg(colors::TEXT_BODY_SUBTLE)
+                .add_modifier(Modifier::ITALIC),
+            economy_available: Style::new().fg(colors::TEXT_HEADING),
+            economy_used: Style::new().fg(colors::TEXT_BODY_SUBTLE),
+            timer: Style::new().fg(colors::TEXT_BODY),
+            timer_warning: Style::new().fg(colors::TEXT_WARNING),
+            timer_expired: Style::new()
+                .fg(colors::TEXT_IMPORTANT)
+                .bg(colors::TEXT_DANGER),
         },
     }
 };
+
+pub const LIGHT: Theme = {
+    use colors::light::*;
+
+    Theme {
+        root: Style::new().fg(TEXT_BODY).bg(BG),
+        app_title: Style::new().fg(TEXT_TITLE),
+
+        tabs: Style::new().fg(TEXT_BODY_SUBTLE).bg(BG),
+        tab_selected: Style::new().fg(TEXT_IMPORTANT).bg(BG_FOCUSED),
+
+        controls_panel: ControlsPanelTheme {
+            bg: Style::new().fg(TEXT_BODY).bg(BG_SHADED),
+            text: Style::new().fg(TEXT_BODY),
+            delimiter: Style::new().fg(TEXT_BODY_SUBTLE),
+            border_color: TEXT_HEADING,
+        },
+        edit_participants_tab: EditParticipantsTabTheme {
+            root: Style::new().fg(TEXT_BODY).bg(BG),
+            column_heading: Style::new().fg(TEXT_HEADING).bg(BG_SHADED),
+            participant_row: Style::new().fg(TEXT_BODY),
+            participant_row_alt: Style::new().fg(TEXT_IMPORTANT),
+            participant_row_focused: Style::new().fg(TEXT_IMPORTANT).bg(BG_FOCUSED),
+            participant_col_focused: Style::new().fg(TEXT_IMPORTANT).bg(BG_SELECTED),
+            participant_row_hidden: Style::new()
+                .fg(TEXT_BODY_SUBTLE)
+                .add_modifier(Modifier::ITALIC),
+        },
+        difficulty: DifficultyTheme {
+            easy: Style::new().fg(TEXT_BODY),
+            medium: Style::new().fg(TEXT_HEADING),
+            hard: Style::new().fg(TEXT_WARNING),
+            deadly: Style::new().fg(TEXT_ON_DANGER).bg(TEXT_DANGER),
+            details: Style::new().fg(TEXT_BODY_SUBTLE),
+        },
+        running_combat_tab: RunningCombatTabTheme {
+            root: Style::new().fg(TEXT_BODY).bg(BG),
+            column_heading: Style::new().fg(TEXT_HEADING).bg(BG_SHADED),
+            participant: Style::new().fg(TEXT_BODY),
+            participant_focused: Style::new().fg(TEXT_IMPORTANT).bg(BG_FOCUSED),
+            participant_active: Style::new().fg(TEXT_IMPORTANT).bg(BG_SELECTED),
+            participant_down: Style::new().fg(TEXT_BODY_SUBTLE),
+            participant_hidden: Style::new()
+                .fg(TEXT_BODY_SUBTLE)
+                .add_modifier(Modifier::ITALIC),
+            economy_available: Style::new().fg(TEXT_HEADING),
+            economy_used: Style::new().fg(TEXT_BODY_SUBTLE),
+            timer: Style::new().fg(TEXT_BODY),
+            timer_warning: Style::new().fg(TEXT_WARNING),
+            timer_expired: Style::new().fg(TEXT_ON_DANGER).bg(TEXT_DANGER),
+        },
+    }
+};
+
+/// Only black, white and the brightest terminal colors, with focus shown in reverse so it
+/// stands out whatever the colors end up looking like.
+pub const HIGH_CONTRAST: Theme = {
+    const BODY: Style = Style::new().fg(Color::White).bg(Color::Black);
+    const HEADING: Style = Style::new()
+        .fg(Color::LightYellow)
+        .add_modifier(Modifier::BOLD);
+    const FOCUSED: Style = Style::new()
+        .fg(Color::Black)
+        .bg(Color::White)
+        .add_modifier(Modifier::BOLD);
+    const SELECTED: Style = Style::new()
+        .fg(Color::Black)
+        .bg(Color::LightYellow)
+        .add_modifier(Modifier::BOLD);
+    const SUBTLE: Style = Style::new().fg(Color::Gray);
+    const DANGER: Style = Style::new()
+        .fg(Color::White)
+        .bg(Color::Red)
+        .add_modifier(Modifier::BOLD);
+
+    Theme {
+        root: BODY,
+        app_title: HEADING,
+
+        tabs: BODY,
+        tab_selected: FOCUSED,
+
+        controls_panel: ControlsPanelTheme {
+            bg: BODY,
+            text: Style::new().fg(Color::White),
+            delimiter: Style::new().fg(Color::LightYellow),
+            border_color: Color::White,
+        },
+        edit_participants_tab: EditParticipantsTabTheme {
+            root: BODY,
+            column_heading: HEADING.add_modifier(Modifier::UNDERLINED),
+            participant_row: Style::new().fg(Color::White),
+            participant_row_alt: Style::new().fg(Color::White),
+            participant_row_focused: FOCUSED,
+            participant_col_focused: SELECTED,
+            participant_row_hidden: SUBTLE.add_modifier(Modifier::ITALIC),
+        },
+        difficulty: DifficultyTheme {
+            easy: Style::new().fg(Color::White),
+            medium: Style::new().fg(Color::LightYellow),
+            hard: HEADING,
+            deadly: DANGER,
+            details: Style::new().fg(Color::White),
+        },
+        running_combat_tab: RunningCombatTabTheme {
+            root: BODY,
+            column_heading: HEADING.add_modifier(Modifier::UNDERLINED),
+            participant: Style::new().fg(Color::White),
+            participant_focused: FOCUSED,
+            participant_active: SELECTED,
+            participant_down: SUBTLE.add_modifier(Modifier::CROSSED_OUT),
+            participant_hidden: SUBTLE.add_modifier(Modifier::ITALIC),
+            economy_available: Style::new().fg(Color::LightYellow),
+            economy_used: SUBTLE,
+            timer: Style::new().fg(Color::White),
+            timer_warning: HEADING,
+            timer_expired: DANGER,
+        },
+    }
+};
+
+/// For terminals without colors: focus is shown reversed, and headings and warnings in bold
+/// or underlined.
+pub const MONOCHROME: Theme = {
+    const PLAIN: Style = Style::new();
+    const REVERSED: Style = Style::new().add_modifier(Modifier::REVERSED);
+    const STRONG: Style = Style::new().add_modifier(Modifier::BOLD);
+    const HEADING: Style = Style::new()
+        .add_modifier(Modifier::BOLD)
+        .add_modifier(Modifier::UNDERLINED);
+    const DIM: Style = Style::new().add_modifier(Modifier::DIM);
+    const ALARM: Style = Style::new()
+        .add_modifier(Modifier::BOLD)
+        .add_modifier(Modifier::REVERSED);
+
+    Theme {
+        root: PLAIN,
+        app_title: STRONG,
+
+        tabs: PLAIN,
+        tab_selected: REVERSED,
+
+        controls_panel: ControlsPanelTheme {
+            bg: PLAIN,
+            text: PLAIN,
+            delimiter: DIM,
+            border_color: Color::Reset,
+        },
+        edit_participants_tab: EditParticipantsTabTheme {
+            root: PLAIN,
+            column_heading: HEADING,
+            participant_row: PLAIN,
+            participant_row_alt: PLAIN,
+            participant_row_focused: REVERSED,
+            participant_col_focused: ALARM.add_modifier(Modifier::UNDERLINED),
+            participant_row_hidden: DIM.add_modifier(Modifier::ITALIC),
+        },
+        difficulty: DifficultyTheme {
+            easy: PLAIN,
+            medium: PLAIN,
+            hard: STRONG,
+            deadly: ALARM,
+            details: DIM,
+        },
+        running_combat_tab: RunningCombatTabTheme {
+            root: PLAIN,
+            column_heading: HEADING,
+            participant: PLAIN,
+            participant_focused: REVERSED,
+            participant_active: ALARM,
+            participant_down: DIM.add_modifier(Modifier::CROSSED_OUT),
+            participant_hidden: DIM.add_modifier(Modifier::ITALIC),
+            economy_available: STRONG,
+            economy_used: DIM,
+            timer: PLAIN,
+            timer_warning: STRONG,
+            timer_expired: ALARM,
+        },
+    }
+};
+
+/// The themes that come with the app, by name.
+pub const BUILT_IN: [(&str, Theme); 3] = [
+    ("dark", DARK),
+    ("light", LIGHT),
+    ("high-contrast", HIGH_CONTRAST),
+];
+
+impl Theme {
+    /// Every style, by the name theme files call it, such as `root` or `controls_panel.text`.
+    fn styles_mut(&mut self) -> Vec<(&'static str, &mut Style)> {
+        vec![
+            ("root", &mut self.root),
+            ("app_title", &mut self.app_title),
+            ("tabs", &mut self.tabs),
+            ("tab_selected", &mut self.tab_selected),
+            ("controls_panel.bg", &mut self.controls_panel.bg),
+            ("controls_panel.text", &mut self.controls_panel.text),
+            (
+                "controls_panel.delimiter",
+                &mut self.controls_panel.delimiter,
+            ),
+            (
+                "edit_participants_tab.root",
+                &mut self.edit_participants_tab.root,
+            ),
+            (
+                "edit_participants_tab.column_heading",
+                &mut self.edit_participants_tab.column_heading,
+            ),
+            (
+                "edit_participants_tab.participant_row",
+                &mut self.edit_participants_tab.participant_row,
+            ),
+            (
+                "edit_participants_tab.participant_row_alt",
+                &mut self.edit_participants_tab.participant_row_alt,
+            ),
+            (
+                "edit_participants_tab.participant_row_focused",
+                &mut self.edit_participants_tab.participant_row_focused,
+            ),
+            (
+                "edit_participants_tab.participant_col_focused",
+                &mut self.edit_participants_tab.participant_col_focused,
+            ),
+            (
+                "edit_participants_tab.participant_row_hidden",
+                &mut self.edit_participants_tab.participant_row_hidden,
+            ),
+            ("difficulty.easy", &mut self.difficulty.easy),
+            ("difficulty.medium", &mut self.difficulty.medium),
+            ("difficulty.hard", &mut self.difficulty.hard),
+            ("difficulty.deadly", &mut self.difficulty.deadly),
+            ("difficulty.details", &mut self.difficulty.details),
+            ("running_combat_tab.root", &mut self.running_combat_tab.root),
+            (
+                "running_combat_tab.column_heading",
+                &mut self.running_combat_tab.column_heading,
+            ),
+            (
+                "running_combat_tab.participant",
+                &mut self.running_combat_tab.participant,
+            ),
+            (
+                "running_combat_tab.participant_focused",
+                &mut self.running_combat_tab.participant_focused,
+            ),
+            (
+                "running_combat_tab.participant_active",
+                &mut self.running_combat_tab.participant_active,
+            ),
+            (
+                "running_combat_tab.participant_down",
+                &mut self.running_combat_tab.participant_down,
+            ),
+            (
+                "running_combat_tab.participant_hidden",
+                &mut self.running_combat_tab.participant_hidden,
+            ),
+            (
+                "running_combat_tab.economy_available",
+                &mut self.running_combat_tab.economy_available,
+            ),
+            (
+                "running_combat_tab.economy_used",
+                &mut self.running_combat_tab.economy_used,
+            ),
+            (
+                "running_combat_tab.timer",
+                &mut self.running_combat_tab.timer,
+            ),
+            (
+                "running_combat_tab.timer_warning",
+                &mut self.running_combat_tab.timer_warning,
+            ),
+            (
+                "running_combat_tab.timer_expired",
+                &mut self.running_combat_tab.timer_expired,
+            ),
+        ]
+    }
+
+    fn style_mut(&mut self, name: &str) -> Option<&mut Style> {
+        self.styles_mut()
+            .into_iter()
+            .find(|(style_name, _)| *style_name == name)
+            .map(|(_, style)| style)
+    }
+
+    /// The theme as close as the terminal can show it. Without colors, that's the monochrome
+    /// theme, whatever this one looks like.
+    pub fn adapt(&self, support: ColorSupport) -> Theme {
+        if support == ColorSupport::Monochrome {
+            return MONOCHROME;
+        }
+
+        let mut theme = self.clone();
+        for (_, style) in theme.styles_mut() {
+            style.fg = style.fg.map(|color| support.adapt(color));
+            style.bg = style.bg.map(|color| support.adapt(color));
+        }
+        theme.controls_panel.border_color = support.adapt(theme.controls_panel.border_color);
+        theme
+    }
+
+    /// Read a theme file. It starts from the built-in theme named by `base` (dark unless it
+    /// says otherwise) and changes only the styles it lists, like this:
+    ///
+    /// ```toml
+    /// base = "light"
+    ///
+    /// [edit_participants_tab.participant_row_focused]
+    /// fg = "black"
+    /// bg = "#ffcc66"
+    /// modifiers = ["bold"]
+    ///
+    /// [controls_panel]
+    /// border_color = "blue"
+    /// ```
+    pub fn from_toml(text: &str) -> Result<Self> {
+        let table: toml::Table = text.parse()?;
+
+        let base = match table.get("base") {
+            Some(base) => base
+                .as_str()
+                .context("base should be the name of a theme")?,
+            None => "dark",
+        };
+        let mut theme = BUILT_IN
+            .iter()
+            .find(|(name, _)| *name == base)
+            .map(|(_, theme)| theme.clone())
+            .with_context(|| format!("There's no built-in theme called {}", base))?;
+
+        for (key, value) in &table {
+            match key.as_str() {
+                "base" => {}
+                "controls_panel"
+                | "edit_participants_tab"
+                | "difficulty"
+                | "running_combat_tab" => {
+                    let section = value
+                        .as_table()
+                        .with_context(|| format!("{} should be a table of styles", key))?;
+                    for (name, value) in section {
+                        theme.set(&format!("{}.{}", key, name), value)?;
+                    }
+                }
+                _ => theme.set(key, value)?,
+            }
+        }
+
+        Ok(theme)
+    }
+
+    /// Change the style called `name` to what `value` describes.
+    fn set(&mut self, name: &str, value: &toml::Value) -> Result<()> {
+        if name == "controls_panel.border_color" {
+            self.controls_panel.border_color = parse_color(value)?;
+            return Ok(());
+        }
+
+        let style = self
+            .style_mut(name)
+            .with_context(|| format!("There's no style called {}", name))?;
+        let table = value
+            .as_table()
+            .with_context(|| format!("{} should be a table with fg, bg or modifiers", name))?;
+        for (key, value) in table {
+            match key.as_str() {
+                "fg" => style.fg = Some(parse_color(value)?),
+                "bg" => style.bg = Some(parse_color(value)?),
+                "modifiers" => {
+                    style.add_modifier = parse_modifiers(value)?;
+                    style.sub_modifier = Modifier::empty();
+                }
+                _ => bail!("{} has fg, bg and modifiers, but not {}", name, key),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A color by name, like `"lightblue"`, or in hex, like `"#ffcc66"`.
+fn parse_color(value: &toml::Value) -> Result<Color> {
+    let text = value.as_str().context("Colors should be strings")?;
+    Color::from_str(text).map_err(|_| anyhow!("\"{}\" isn't a color", text))
+}
+
+/// A list of modifiers by name, like `["bold", "italic"]`.
+fn parse_modifiers(value: &toml::Value) -> Result<Modifier> {
+    let names = value
+        .as_array()
+        .context("Modifiers should be a list, like [\"bold\"]")?;
+    names.iter().try_fold(Modifier::empty(), |modifiers, name| {
+        let name = name.as_str().context("Modifiers should be strings")?;
+        let modifier = Modifier::from_name(&name.to_uppercase().replace('-', "_"))
+            .with_context(|| format!("\"{}\" isn't a modifier", name))?;
+        Ok(modifiers | modifier)
+    })
+}
+
+/// Set this to keep theme files somewhere other than the usual data directory.
+pub const THEMES_DIR_VAR: &str = "ROLL_INITIATIVE_THEMES";
+
+/// The themes to cycle through: the built-in ones, then any theme files.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Themes {
+    pub themes: Vec<(String, Theme)>,
+    pub selected: usize,
+}
+
+impl Default for Themes {
+    fn default() -> Self {
+        Themes {
+            themes: BUILT_IN
+                .iter()
+                .map(|(name, theme)| (name.to_string(), theme.clone()))
+                .collect(),
+            selected: 0,
+        }
+    }
+}
+
+impl Themes {
+    /// `$ROLL_INITIATIVE_THEMES`, or a `themes` directory in the app's data directory.
+    pub fn default_dir() -> Option<PathBuf> {
+        match env::var_os(THEMES_DIR_VAR) {
+            Some(dir) => Some(PathBuf::from(dir)),
+            None => library::data_dir().map(|dir| dir.join("themes")),
+        }
+    }
+
+    /// The built-in themes, along with every `.toml` theme file in `dir`, named after the
+    /// file. A directory that doesn't exist yet has no theme files.
+    pub fn open(dir: &Path) -> Result<Self> {
+        let mut themes = Themes::default();
+        if !dir.exists() {
+            return Ok(themes);
+        }
+
+        let entries = fs::read_dir(dir)
+            .with_context(|| format!("Couldn't read the themes in {}", dir.display()))?;
+        let mut paths: Vec<PathBuf> = entries
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<std::io::Result<_>>()?;
+        paths.sort();
+        for path in paths {
+            if path
+                .extension()
+                .is_some_and(|extension| extension == "toml")
+            {
+                let text = fs::read_to_string(&path)
+                    .with_context(|| format!("Couldn't read {}", path.display()))?;
+                let theme = Theme::from_toml(&text)
+                    .with_context(|| format!("{} isn't a valid theme", path.display()))?;
+                let name = path
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                themes.themes.push((name, theme));
+            }
+        }
+
+        Ok(themes)
+    }
+
+    pub fn current(&self) -> &Theme {
+        &self.themes[self.selected].1
+    }
+
+    pub fn current_name(&self) -> &str {
+        &self.themes[self.selected].0
+    }
+
+    /// Switch to the theme called `name`.
+    pub fn select(&mut self, name: &str) -> Result<()> {
+        self.selected = self
+            .themes
+            .iter()
+            .position(|(theme_name, _)| theme_name == name)
+            .with_context(|| {
+                let names: Vec<&str> = self.themes.iter().map(|(name, _)| name.as_str()).collect();
+                format!(
+                    "There's no theme called {} (try {})",
+                    name,
+                    names.join(", ")
+                )
+            })?;
+
+        Ok(())
+    }
+
+    pub fn cycle(&mut self) {
+        self.selected = (self.selected + 1) % self.themes.len();
+    }
+
+    /// Make every theme as close as the terminal can show. Without colors there's only the
+    /// monochrome theme left to pick.
+    pub fn adapt(&mut self, support: ColorSupport) {
+        if support == ColorSupport::Monochrome {
+            self.themes = vec![(String::from("monochrome"), MONOCHROME)];
+            self.selected = 0;
+            return;
+        }
+
+        for (_, theme) in self.themes.iter_mut() {
+            *theme = theme.adapt(support);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_theme_file_overrides_only_what_it_lists() {
+        let theme = Theme::from_toml(
+            r##"
+            base = "light"
+            root = { fg = "white" }
+
+            [edit_participants_tab.participant_row_focused]
+            bg = "#ffcc66"
+            modifiers = ["bold", "underlined"]
+
+            [controls_panel]
+            border_color = "blue"
+            "##,
+        )
+        .unwrap();
+
+        assert_eq!(theme.root, LIGHT.root.fg(Color::White));
+        assert_eq!(
+            theme.edit_participants_tab.participant_row_focused,
+            Style::new()
+                .fg(colors::light::TEXT_IMPORTANT)
+                .bg(Color::Rgb(255, 204, 102))
+                .add_modifier(Modifier::BOLD | Modifier::UNDERLINED)
+        );
+        assert_eq!(theme.controls_panel.border_color, Color::Blue);
+        assert_eq!(theme.running_combat_tab, LIGHT.running_combat_tab);
+    }
+
+    #[test]
+    fn test_theme_file_mistakes_are_explained() {
+        let error = |text| format!("{:#}", Theme::from_toml(text).unwrap_err());
+
+        assert_eq!(
+            error("[difficulty.lethal]\nfg = \"red\""),
+            "There's no style called difficulty.lethal"
+        );
+        assert_eq!(
+            error("root = { fg = \"mauve\" }"),
+            "\"mauve\" isn't a color"
+        );
+        assert_eq!(
+            error("base = \"solarized\""),
+            "There's no built-in theme called solarized"
+        );
+    }
+
+    #[test]
+    fn test_cycle_through_built_in_and_file_themes() {
+        let dir = env::temp_dir().join(format!(
+            "roll-initiative-themes-test-{}",
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("dusk.toml"), "app_title = { fg = \"magenta\" }").unwrap();
+
+        let mut themes = Themes::open(&dir).unwrap();
+        themes.select("high-contrast").unwrap();
+        assert_eq!(themes.current(), &HIGH_CONTRAST);
+        themes.cycle();
+        assert_eq!(themes.current_name(), "dusk");
+        assert_eq!(themes.current().app_title, Style::new().fg(Color::Magenta));
+        themes.cycle();
+        assert_eq!(themes.current_name(), "dark");
+        assert!(themes.select("sepia").is_err());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_adapt_themes_to_terminal_colors() {
+        let sixteen = DARK.adapt(ColorSupport::Ansi16);
+        let is_basic =
+            |color: Option<Color>| !matches!(color, Some(Color::Rgb(..)) | Some(Color::Indexed(_)));
+        assert!(sixteen
+            .clone()
+            .styles_mut()
+            .iter()
+            .all(|(_, style)| is_basic(style.fg) && is_basic(style.bg)));
+        assert_eq!(
+            sixteen
+                .edit_participants_tab
+                .participant_row_focused
+                .add_modifier,
+            DARK.edit_participants_tab
+                .participant_row_focused
+                .add_modifier
+        );
+        assert_eq!(DARK.adapt(ColorSupport::TrueColor), DARK);
+
+        let mut themes = Themes::default();
+        themes.select("light").unwrap();
+        themes.adapt(ColorSupport::Monochrome);
+        assert_eq!(themes.current_name(), "monochrome");
+        assert_eq!(themes.current(), &MONOCHROME);
+        themes.cycle();
+        assert_eq!(themes.current_name(), "monochrome");
+    }
+}
@@ -1,24 +1,38 @@
 use ratatui::{
-    prelude::{Buffer, Constraint, Direction, Layout, Rect},
-    widgets::{Block, Borders, Padding, Widget},
+    prelude::{Constraint, Direction, Layout, Rect},
+    widgets::{Block, Borders},
     Frame,
 };
 
 use crate::{
-    app::{AppMode, EditingEncounterState},
-    theme::THEME,
+    app::{EditingEncounterState, RunningCombatState},
+    theme::Theme,
 };
 
 pub mod elements {
+    use std::time::Duration;
+
     use ratatui::{
-        style::{Color, Style},
-        text::Line,
-        widgets::{BorderType, Paragraph},
+        layout::Alignment,
+        style::Style,
+        text::{Line, Span},
+        widgets::{
+            block::{Position, Title},
+            BorderType, Cell, Clear, Paragraph, Row, Table, Tabs as TabsWidget, Wrap,
+        },
     };
 
     use crate::{
-        app::{AppState, RunMode},
-        theme,
+        app::{
+            CombatSummaryState, CombatTurn, EncounterBrowserState, MonsterPicker, Participant,
+            ParticipantColumn, PickerEntry, RunMode, Tabs, TurnKind,
+        },
+        campaign::{self, Campaign},
+        combat::{CombatEvent, CombatLogEntry, EconomyResource, TimerStatus},
+        compendium::HitPointsMode,
+        difficulty::{Difficulty, EncounterDifficulty},
+        library::Library,
+        summary::format_turn_duration,
     };
 
     use super::*;
@@ -26,17 +40,63 @@ pub mod elements {
     // TODO - Instead of hard-coding this text, source it from a combination of
     //        the display trait for AppCommand & the keybindings (implementation of
     //        the From trait for the tuple (AppMode, Option<KeyCode>)).
-    pub const EDITING_PARTICIPANTS_CONTROLS_TEXT: &str = "[a] Add | [d (or) Del.] Delete 
-        | [e (or) Enter] Toggle editing 
-        | [q] Quit | [tab] Change tab | [↑ (or) k] Prev. row 
-        | [↓ (or) j] Next row | [← (or) h] Prev. column | [→ (or) l] Next column ";
+    pub const EDITING_PARTICIPANTS_CONTROLS_TEXT: &str = "[a] Add | [d (or) Del.] Delete \
+        | [e (or) Enter] Toggle editing | [+ (or) ]] Add roll slot | [- (or) [] Remove roll slot \
+        | [m] Add from library/compendium | [S] Save to library | [p] Add party \
+        | [P] Save party | [I] Import from file | [L] Add lair action | [H] Hide/unhide \
+        | [W] Save encounter | [B] Browse encounters \
+        | [i] Change initiative system | [t] Turn time limit \
+        | [s] Start combat | [q] Quit \
+        | [tab] Change tab | [N] New tab | [R] Rename tab | [X] Close tab | [T] Change theme \
+        | [↑ (or) k] Prev. row | [↓ (or) j] Next row | [← (or) h] Prev. column \
+        | [→ (or) l] Next column";
+
+    pub const ENCOUNTER_BROWSER_CONTROLS_TEXT: &str = "[o (or) Enter] Open encounter \
+        | [n] New encounter | [c] Duplicate as template | [↑ (or) k] Prev. row \
+        | [↓ (or) j] Next row | [tab] Change tab | [N] New tab | [R] Rename tab \
+        | [X] Close tab | [T] Change theme | [q] Quit";
+
+    pub const MONSTER_PICKER_CONTROLS_TEXT: &str =
+        "[Enter] Add selected | [Tab] Average/rolled HP \
+        | [↑] Prev. creature | [↓] Next creature | [Esc] Close";
+
+    pub const EDITING_CELL_CONTROLS_TEXT: &str = "[Enter] Save | [Esc] Cancel";
+
+    pub const PROMPT_CONTROLS_TEXT: &str = "[Enter] Submit | [Esc] Cancel";
+
+    pub const REPORT_CONTROLS_TEXT: &str = "[Enter (or) Esc] Close";
+
+    pub const CONFIRMATION_CONTROLS_TEXT: &str = "[y (or) Enter] Yes | [n (or) Esc] No";
+
+    pub const RUNNING_COMBAT_CONTROLS_TEXT: &str = "[n (or) Space] Next turn \
+        | [d] Delay | [e (or) Enter] Act now (delayed) | [r] Ready | [t] Trigger readied action \
+        | [1-4] Toggle action/bonus/reaction/movement | [-] Damage | [+] Heal | [c] Concentration \
+        | [o] Condition | [K] Mark dead | [v] Reveal | [PgUp/PgDn] Scroll history \
+        | [↑ (or) k] Prev. row | [↓ (or) j] Next row | [w] Export order | [x] End combat \
+        | [tab] Change tab | [N] New tab | [R] Rename tab | [X] Close tab | [T] Change theme | [q] Quit";
+
+    pub const POPCORN_COMBAT_CONTROLS_TEXT: &str = "[n (or) Space] Pass turn to selected \
+        | [d] Delay | [e (or) Enter] Act now (delayed) | [r] Ready | [t] Trigger readied action \
+        | [1-4] Toggle action/bonus/reaction/movement | [-] Damage | [+] Heal | [c] Concentration \
+        | [o] Condition | [K] Mark dead | [v] Reveal | [PgUp/PgDn] Scroll history \
+        | [↑ (or) k] Prev. row | [↓ (or) j] Next row | [w] Export order | [x] End combat \
+        | [tab] Change tab | [N] New tab | [R] Rename tab | [X] Close tab | [T] Change theme | [q] Quit";
 
-    pub fn splash_screen(frame: &mut Frame) {
-        let theme = THEME.root;
+    pub const COMBAT_SUMMARY_CONTROLS_TEXT: &str =
+        "[s] Save summary | [Enter (or) Esc] Back to encounter \
+        | [tab] Change tab | [N] New tab | [R] Rename tab | [X] Close tab | [T] Change theme | [q] Quit";
 
+    /// Tall enough for four lines of controls between the borders.
+    pub const CONTROLS_PANEL_HEIGHT: u16 = 6;
+
+    const NAME_COLUMN_WIDTH: u16 = 24;
+    const LOG_PANEL_WIDTH: u16 = 40;
+    const ROLL_COLUMN_WIDTH: u16 = 8;
+
+    pub fn splash_screen(frame: &mut Frame, theme: &Theme) {
         // Centered title which says "Roll Initiative"
         let splash = Block::default()
-            .style(theme)
+            .style(theme.root)
             .title("Roll Initiative")
             .borders(Borders::ALL);
 
@@ -45,28 +105,931 @@ pub mod elements {
         frame.render_widget(splash, area);
     }
 
-    pub fn editing_encounter_tab(frame: &mut Frame, state: &EditingEncounterState) {
-        let theme = THEME;
+    /// The campaign's saved encounters, with what's worth knowing before picking one.
+    pub fn encounter_browser_tab(
+        frame: &mut Frame,
+        theme: &Theme,
+        state: &EncounterBrowserState,
+        campaign: &Campaign,
+        area: Rect,
+    ) {
+        let location = match &campaign.dir {
+            Some(dir) => format!(" | {} ", dir.display()),
+            None => String::from(" | Nowhere to save "),
+        };
+        let block = Block::default()
+            .style(theme.edit_participants_tab.root)
+            .borders(Borders::ALL)
+            .title(Line::from(vec![
+                Span::styled("Campaign", theme.app_title),
+                Span::raw(location),
+            ]))
+            .title_style(theme.edit_participants_tab.root);
+        let block = match &state.message {
+            Some(message) => block.title(
+                Title::from(format!(" {} ", message))
+                    .position(Position::Bottom)
+                    .alignment(Alignment::Right),
+            ),
+            None => block,
+        };
 
+        if campaign.encounters.is_empty() {
+            let hint = Paragraph::new(
+                "No saved encounters yet. Press [n] to start one, and [W] in the editor to save it.",
+            )
+            .style(theme.edit_participants_tab.participant_row)
+            .centered()
+            .block(block);
+
+            frame.render_widget(hint, area);
+            return;
+        }
+
+        let now = campaign::now();
+        let rows = campaign
+            .encounters
+            .iter()
+            .enumerate()
+            .map(|(row, encounter)| {
+                let row_style = match (state.focused_row == row, row % 2) {
+                    (true, _) => theme.edit_participants_tab.participant_row_focused,
+                    (false, 0) => theme.edit_participants_tab.participant_row,
+                    (false, _) => theme.edit_participants_tab.participant_row_alt,
+                };
+
+                Row::new([
+                    encounter.name.clone(),
+                    encounter.combatant_count().to_string(),
+                    encounter
+                        .difficulty()
+                        .map(|difficulty| difficulty.to_string())
+                        .unwrap_or_else(|| String::from("-")),
+                    encounter.initiative_system.to_string(),
+                    encounter.last_played_text(now),
+                ])
+                .style(row_style)
+            });
+
+        let header = Row::new([
+            "Name",
+            "Participants",
+            "Difficulty",
+            "Initiative",
+            "Last played",
+        ])
+        .style(theme.edit_participants_tab.column_heading);
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Min(NAME_COLUMN_WIDTH),
+                Constraint::Length(12),
+                Constraint::Length(10),
+                Constraint::Length(16),
+                Constraint::Length(12),
+            ],
+        )
+        .header(header)
+        .block(block);
+
+        frame.render_widget(table, area);
+    }
+
+    pub fn editing_encounter_tab(
+        frame: &mut Frame,
+        theme: &Theme,
+        state: &EditingEncounterState,
+        area: Rect,
+    ) {
         let block = Block::default()
             .style(theme.edit_participants_tab.root)
             .borders(Borders::ALL)
+            .title(Line::from(vec![
+                Span::styled("Encounter", theme.app_title),
+                Span::raw(format!(
+                    " | Initiative: {} | Turn limit: {} ",
+                    state.initiative_system,
+                    match state.turn_time_limit {
+                        Some(limit) => clock(limit),
+                        None => String::from("None"),
+                    }
+                )),
+            ]))
             .title_style(theme.edit_participants_tab.root);
+        let block = match &state.message {
+            Some(message) => block.title(
+                Title::from(format!(" {} ", message))
+                    .position(Position::Bottom)
+                    .alignment(Alignment::Right),
+            ),
+            None => block,
+        };
+
+        if state.participants.is_empty() {
+            let hint = Paragraph::new("No participants yet. Press [a] to add one.")
+                .style(theme.edit_participants_tab.participant_row)
+                .centered()
+                .block(block);
+
+            frame.render_widget(hint, area);
+            return;
+        }
+
+        // Only as many roll columns as fit are drawn. Scroll horizontally so that the focused
+        // roll column is always the last visible one when it would otherwise be cut off.
+        let fixed_width: u16 = ParticipantColumn::FIXED
+            .iter()
+            .map(|column| column_width(*column) + 1)
+            .sum();
+        let slot_columns = state.initiative_slot_columns();
+        let inner_width = block.inner(area).width;
+        let visible_slots =
+            (inner_width.saturating_sub(fixed_width) / (ROLL_COLUMN_WIDTH + 1)).max(1) as usize;
+        let first_slot = match state.focused_column() {
+            ParticipantColumn::InitiativeRoll(slot) if slot >= visible_slots => {
+                slot + 1 - visible_slots
+            }
+            _ => 0,
+        };
+        let last_slot = (first_slot + visible_slots).min(slot_columns);
+
+        let columns: Vec<ParticipantColumn> = ParticipantColumn::FIXED
+            .into_iter()
+            .chain((first_slot..last_slot).map(ParticipantColumn::InitiativeRoll))
+            .collect();
+
+        let header = columns.iter().map(|column| {
+            let label = column_heading(*column);
+            // Hint that there are more roll columns scrolled out of view
+            match column {
+                ParticipantColumn::InitiativeRoll(slot)
+                    if *slot == first_slot && first_slot > 0 =>
+                {
+                    Cell::from(format!("◀ {}", label))
+                }
+                ParticipantColumn::InitiativeRoll(slot)
+                    if slot + 1 == last_slot && last_slot < slot_columns =>
+                {
+                    Cell::from(format!("{} ▶", label))
+                }
+                _ => Cell::from(label),
+            }
+        });
+
+        let rows = state
+            .participants
+            .iter()
+            .enumerate()
+            .map(|(row, participant)| {
+                let row_focused = state.focused_row == Some(row);
+                let row_style = match (row_focused, row % 2) {
+                    (true, _) => theme.edit_participants_tab.participant_row_focused,
+                    _ if participant.hidden() => theme.edit_participants_tab.participant_row_hidden,
+                    (false, 0) => theme.edit_participants_tab.participant_row,
+                    (false, _) => theme.edit_participants_tab.participant_row_alt,
+                };
+
+                let cells = columns.iter().map(|column| {
+                    if row_focused && state.focused_column() == *column {
+                        let text = match &state.input {
+                            Some(input) => format!("{}_", input),
+                            None => cell_text(participant, *column),
+                        };
+                        Cell::from(text).style(theme.edit_participants_tab.participant_col_focused)
+                    } else {
+                        Cell::from(cell_text(participant, *column))
+                    }
+                });
+
+                Row::new(cells).style(row_style)
+            });
+
+        let widths: Vec<Constraint> = columns
+            .iter()
+            .map(|column| Constraint::Length(column_width(*column)))
+            .collect();
+
+        let table = Table::new(rows, widths)
+            .header(Row::new(header).style(theme.edit_participants_tab.column_heading))
+            .block(block);
+
+        frame.render_widget(table, area);
+
+        if let Some(report) = &state.report {
+            prompt_popup(
+                frame,
+                theme,
+                &format!("Imported from {}", report.source),
+                &report.body(),
+                area,
+            );
+        }
+    }
+
+    fn column_heading(column: ParticipantColumn) -> String {
+        match column {
+            ParticipantColumn::Name => String::from("Name"),
+            ParticipantColumn::Side => String::from("Side"),
+            ParticipantColumn::HitPoints => String::from("HP"),
+            ParticipantColumn::ArmorClass => String::from("AC"),
+            ParticipantColumn::InitiativeModifier => String::from("Init"),
+            ParticipantColumn::Level => String::from("Level"),
+            ParticipantColumn::ChallengeRating => String::from("CR"),
+            ParticipantColumn::LegendaryActions => String::from("Legendary"),
+            ParticipantColumn::InitiativeRoll(slot) => format!("Roll {}", slot + 1),
+        }
+    }
+
+    fn column_width(column: ParticipantColumn) -> u16 {
+        match column {
+            ParticipantColumn::Name => NAME_COLUMN_WIDTH,
+            ParticipantColumn::Side => 8,
+            ParticipantColumn::HitPoints => 8,
+            ParticipantColumn::ArmorClass => 4,
+            ParticipantColumn::InitiativeModifier => 5,
+            ParticipantColumn::Level => 6,
+            ParticipantColumn::ChallengeRating => 5,
+            ParticipantColumn::LegendaryActions => 10,
+            ParticipantColumn::InitiativeRoll(_) => ROLL_COLUMN_WIDTH,
+        }
+    }
+
+    fn cell_text(participant: &Participant, column: ParticipantColumn) -> String {
+        match column {
+            ParticipantColumn::Name => participant.name().to_string(),
+            ParticipantColumn::Side => match (participant.kind(), participant.side()) {
+                (TurnKind::Combatant, Some(side)) => side.to_string(),
+                _ => String::from("-"),
+            },
+            ParticipantColumn::HitPoints => match (
+                participant.kind(),
+                participant.current_hit_points(),
+                participant.hit_points(),
+            ) {
+                (TurnKind::Combatant, Some(current), Some(max)) => format!("{}/{}", current, max),
+                (TurnKind::Combatant, None, Some(max)) => max.to_string(),
+                _ => String::from("-"),
+            },
+            ParticipantColumn::ArmorClass => {
+                match (participant.kind(), participant.armor_class()) {
+                    (TurnKind::Combatant, Some(armor_class)) => armor_class.to_string(),
+                    _ => String::from("-"),
+                }
+            }
+            ParticipantColumn::InitiativeModifier => match participant.kind() {
+                TurnKind::Combatant => format!("{:+}", participant.initiative_modifier()),
+                TurnKind::LairAction => String::from("-"),
+            },
+            ParticipantColumn::Level => match (participant.kind(), participant.level()) {
+                (TurnKind::Combatant, Some(level)) => level.to_string(),
+                _ => String::from("-"),
+            },
+            ParticipantColumn::ChallengeRating => {
+                match (participant.kind(), participant.challenge_rating()) {
+                    (TurnKind::Combatant, Some(rating)) => rating.to_string(),
+                    _ => String::from("-"),
+                }
+            }
+            ParticipantColumn::LegendaryActions => match participant.kind() {
+                TurnKind::Combatant => participant.legendary_actions().to_string(),
+                TurnKind::LairAction => String::from("-"),
+            },
+            ParticipantColumn::InitiativeRoll(slot) => {
+                match participant.initiative_rolls().get(slot) {
+                    Some(Some(roll)) => roll.to_string(),
+                    Some(None) => String::from("-"),
+                    // The participant has fewer slots than someone else in the encounter
+                    None => String::new(),
+                }
+            }
+        }
+    }
+
+    /// The library and monster compendium search, over the middle of `area`.
+    pub fn monster_picker(
+        frame: &mut Frame,
+        theme: &Theme,
+        picker: &MonsterPicker,
+        library: &Library,
+        hit_points_mode: HitPointsMode,
+        area: Rect,
+    ) {
+        let area = centered_rect(70, 80, area);
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Double)
+            .style(theme.root)
+            .title(Line::from(vec![
+                Span::styled("Library & compendium", theme.app_title),
+                Span::raw(format!(" | {} ", hit_points_mode)),
+            ]));
+
+        let rects =
+            Layout::vertical([Constraint::Length(1), Constraint::Min(1)]).split(block.inner(area));
+
+        let search = Paragraph::new(format!("Search: {}_", picker.query));
+
+        // Keep the selected creature in view
+        let results = picker.results(library);
+        let visible = rects[1].height.saturating_sub(1).max(1) as usize;
+        let first = (picker.selected + 1).saturating_sub(visible);
+        let rows = results
+            .iter()
+            .enumerate()
+            .skip(first)
+            .take(visible)
+            .map(|(index, entry)| {
+                let optional = |value: Option<String>| value.unwrap_or_else(|| String::from("-"));
+                let cells = match entry {
+                    PickerEntry::Template(template) => vec![
+                        template.name.clone(),
+                        match template.level {
+                            Some(level) => format!("Lv {}", level),
+                            None => optional(template.challenge_rating.map(|cr| cr.to_string())),
+                        },
+                        optional(template.armor_class.map(|ac| ac.to_string())),
+                        optional(template.hit_points.map(|hp| hp.to_string())),
+                        format!("{:+}", template.initiative_modifier),
+                        template.tags.join(", "),
+                    ],
+                    PickerEntry::Monster(monster) => vec![
+                        monster.name.clone(),
+                        monster.challenge_rating.to_string(),
+                        monster.armor_class.to_string(),
+                        monster.hit_points.to_string(),
+                        format!("{:+}", monster.initiative_modifier),
+                        String::from("SRD"),
+                    ],
+                };
+
+                Row::new(cells).style(match index == picker.selected {
+                    true => theme.edit_participants_tab.participant_row_focused,
+                    false => theme.edit_participants_tab.participant_row,
+                })
+            });
+
+        let widths = [
+            Constraint::Length(NAME_COLUMN_WIDTH),
+            Constraint::Length(5),
+            Constraint::Length(4),
+            Constraint::Length(10),
+            Constraint::Length(5),
+            Constraint::Min(5),
+        ];
+        let table = Table::new(rows, widths).header(
+            Row::new(vec!["Name", "CR", "AC", "HP", "Init", "Tags"])
+                .style(theme.edit_participants_tab.column_heading),
+        );
+
+        frame.render_widget(Clear, area);
+        frame.render_widget(block, area);
+        frame.render_widget(search, rects[0]);
+        frame.render_widget(table, rects[1]);
+    }
+
+    /// How hard the encounter is for the party in it, if there are both characters and monsters.
+    /// While the editor is asking for something, the answer being typed in instead.
+    pub fn difficulty_status_line(
+        frame: &mut Frame,
+        theme: &Theme,
+        state: &EditingEncounterState,
+        area: Rect,
+    ) {
+        if let Some(prompt) = &state.prompt {
+            let line = format!(" {}: {}_", prompt, prompt.input());
+            frame.render_widget(Paragraph::new(line).style(theme.root), area);
+            return;
+        }
+
+        let line = match EncounterDifficulty::rate(&state.participants) {
+            Some(rating) => Line::from(vec![
+                Span::styled(
+                    format!(" Difficulty: {} ", rating.difficulty),
+                    match rating.difficulty {
+                        Difficulty::Trivial | Difficulty::Easy => theme.difficulty.easy,
+                        Difficulty::Medium => theme.difficulty.medium,
+                        Difficulty::Hard => theme.difficulty.hard,
+                        Difficulty::Deadly => theme.difficulty.deadly,
+                    },
+                ),
+                Span::styled(
+                    format!(
+                        " {} adjusted XP | Party thresholds: easy {}, medium {}, hard {}, \
+                        deadly {}",
+                        rating.adjusted_xp,
+                        rating.thresholds[0],
+                        rating.thresholds[1],
+                        rating.thresholds[2],
+                        rating.thresholds[3],
+                    ),
+                    theme.difficulty.details,
+                ),
+            ]),
+            None => Line::styled(
+                " Give characters a level and monsters a CR to see the difficulty",
+                theme.difficulty.details,
+            ),
+        };
+
+        frame.render_widget(Paragraph::new(line), area);
+    }
+
+    pub fn running_combat_tab(
+        frame: &mut Frame,
+        theme: &Theme,
+        state: &RunningCombatState,
+        area: Rect,
+    ) {
+        let rects = Layout::horizontal([Constraint::Min(40), Constraint::Length(LOG_PANEL_WIDTH)])
+            .split(area);
+
+        let block = Block::default()
+            .style(theme.running_combat_tab.root)
+            .borders(Borders::ALL)
+            .title(Line::from(vec![
+                Span::styled(format!("Round {}", state.round), theme.app_title),
+                Span::raw(format!(
+                    " | {} elapsed | {} left this round | Initiative: {} ",
+                    state.elapsed(),
+                    match state.turns_remaining_in_round() {
+                        1 => String::from("1 turn"),
+                        turns => format!("{} turns", turns),
+                    },
+                    state.initiative_system
+                )),
+            ]))
+            .title(
+                Title::from(turn_timer_line(theme, state))
+                    .alignment(Alignment::Right)
+                    .position(Position::Bottom),
+            );
+
+        let picks_next = state
+            .initiative_system
+            .system()
+            .active_combatant_picks_next();
+
+        let turn_rows = state.turns.iter().enumerate().map(|(index, turn)| {
+            let marker = if index == state.current_turn {
+                "▶"
+            } else if picks_next && state.is_next_turn_candidate(index) {
+                "•"
+            } else {
+                ""
+            };
 
-        frame.render_widget(block, frame.size());
+            let mut status = Vec::new();
+            if turn.hidden() {
+                status.push(String::from("Hidden"));
+            }
+            if turn.dead() {
+                status.push(String::from("Dead"));
+            } else if turn.unconscious() {
+                status.push(String::from("Unconscious"));
+            }
+            if let Some(trigger) = turn.readied() {
+                status.push(format!("Readied: {}", trigger));
+            }
+            let combatant = state.combatant(turn.name());
+            if let Some(combatant) = combatant {
+                status.extend(combatant.conditions.iter().cloned());
+            }
+            if let Some(concentration) = combatant.and_then(|c| c.concentration.as_ref()) {
+                match concentration.rounds_remaining(state.round) {
+                    Some(rounds) => status.push(format!(
+                        "Concentrating: {} ({} rds)",
+                        concentration.spell, rounds
+                    )),
+                    None => status.push(format!("Concentrating: {}", concentration.spell)),
+                }
+            }
+            if let Some(pool) = state
+                .legendary_actions
+                .iter()
+                .find(|pool| pool.name == turn.name())
+            {
+                status.push(format!("Legendary {}/{}", pool.remaining, pool.max));
+            }
+            let status = status.join(", ");
+
+            let style = if index == state.focused_row {
+                theme.running_combat_tab.participant_focused
+            } else if index == state.current_turn {
+                theme.running_combat_tab.participant_active
+            } else if turn.hidden() {
+                theme.running_combat_tab.participant_hidden
+            } else if turn.dead() || turn.unconscious() {
+                theme.running_combat_tab.participant_down
+            } else {
+                theme.running_combat_tab.participant
+            };
+
+            Row::new(vec![
+                Cell::from(marker),
+                Cell::from(turn.initiative_roll_value().to_string()),
+                Cell::from(turn.name().to_string()),
+                Cell::from(hit_points_text(state, turn)),
+                Cell::from(economy_line(theme, state, turn)),
+                Cell::from(time_spent_text(state, turn)),
+                Cell::from(status),
+            ])
+            .style(style)
+        });
+
+        // Delayed combatants are listed after everyone in the order, with no initiative
+        // until they re-enter.
+        let delayed_rows = state.delayed.iter().enumerate().map(|(index, turn)| {
+            let style = if state.turns.len() + index == state.focused_row {
+                theme.running_combat_tab.participant_focused
+            } else {
+                theme.running_combat_tab.participant_down
+            };
+
+            Row::new(vec![
+                Cell::from(""),
+                Cell::from("-"),
+                Cell::from(turn.name().to_string()),
+                Cell::from(hit_points_text(state, turn)),
+                Cell::from(economy_line(theme, state, turn)),
+                Cell::from(time_spent_text(state, turn)),
+                Cell::from("Delayed"),
+            ])
+            .style(style)
+        });
+
+        let widths = [
+            Constraint::Length(2),
+            Constraint::Length(6),
+            Constraint::Length(NAME_COLUMN_WIDTH),
+            Constraint::Length(9),
+            Constraint::Length(8),
+            Constraint::Length(6),
+            Constraint::Min(12),
+        ];
+
+        let table = Table::new(turn_rows.chain(delayed_rows), widths)
+            .header(
+                Row::new(vec!["", "Init", "Name", "HP", "A B R M", "Time", "Status"])
+                    .style(theme.running_combat_tab.column_heading),
+            )
+            .block(block);
+
+        frame.render_widget(table, rects[0]);
+
+        combat_log_panel(frame, theme, &state.log, state.log_scroll, rects[1]);
+
+        if let Some(prompt) = &state.prompt {
+            prompt_popup(frame, theme, &prompt.to_string(), &prompt.body(), area);
+        }
+    }
+
+    /// How long the current turn has taken, coloured by how close it is to the limit.
+    fn turn_timer_line(theme: &Theme, state: &RunningCombatState) -> Line<'static> {
+        let style = match state.turn_timer_status() {
+            Some(TimerStatus::Expired) => theme.running_combat_tab.timer_expired,
+            Some(TimerStatus::Warning) => theme.running_combat_tab.timer_warning,
+            Some(TimerStatus::Running) | None => theme.running_combat_tab.timer,
+        };
+        let text = match state.turn_time_limit {
+            Some(limit) if state.turn_timer_status().is_some() => {
+                format!(" Turn {} / {} ", state.turn_timer, clock(limit))
+            }
+            _ => format!(" Turn {} ", state.turn_timer),
+        };
+
+        Line::from(Span::styled(text, style))
+    }
+
+    /// A duration as minutes and seconds.
+    fn clock(duration: Duration) -> String {
+        let seconds = duration.as_secs();
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    }
+
+    fn time_spent_text(state: &RunningCombatState, turn: &CombatTurn) -> String {
+        match state.combatant(turn.name()) {
+            Some(combatant) => combatant.time_spent.to_string(),
+            None => String::new(),
+        }
+    }
+
+    fn hit_points_text(state: &RunningCombatState, turn: &CombatTurn) -> String {
+        match state.combatant(turn.name()).and_then(|c| c.hit_points) {
+            Some(hit_points) => format!("{}/{}", hit_points.current, hit_points.max),
+            None => String::new(),
+        }
+    }
+
+    /// Which parts of their turn a combatant still has, with the used ones dotted out.
+    fn economy_line(theme: &Theme, state: &RunningCombatState, turn: &CombatTurn) -> Line<'static> {
+        if turn.kind() != TurnKind::Combatant {
+            return Line::default();
+        }
+
+        let economy = state.economy(turn);
+        let spans = EconomyResource::ALL
+            .iter()
+            .enumerate()
+            .flat_map(|(i, resource)| {
+                let separator = Span::raw(if i > 0 { " " } else { "" });
+                let resource = if economy.is_used(*resource) {
+                    Span::styled("·", theme.running_combat_tab.economy_used)
+                } else {
+                    Span::styled(
+                        resource.abbreviation(),
+                        theme.running_combat_tab.economy_available,
+                    )
+                };
+                [separator, resource]
+            });
+
+        Line::from(spans.collect::<Vec<_>>())
+    }
+
+    /// The combat events, newest at the bottom, scrolled back `scroll` entries from the newest.
+    pub fn combat_log_panel(
+        frame: &mut Frame,
+        theme: &Theme,
+        log: &[CombatLogEntry],
+        scroll: usize,
+        area: Rect,
+    ) {
+        let title = match scroll {
+            0 => String::from("History"),
+            scroll => format!("History (▼ {} newer)", scroll),
+        };
+        let block = Block::default()
+            .style(theme.running_combat_tab.root)
+            .borders(Borders::ALL)
+            .title(Span::styled(title, theme.app_title));
+
+        let visible_lines = block.inner(area).height as usize;
+        let end = log.len().saturating_sub(scroll);
+        let lines: Vec<Line> = log[..end]
+            .iter()
+            .skip(end.saturating_sub(visible_lines))
+            .map(|entry| match entry.event {
+                CombatEvent::RoundStarted => {
+                    Line::styled(format!("── Round {} ──", entry.round), theme.app_title)
+                }
+                _ => Line::from(entry.to_string()),
+            })
+            .collect();
+
+        let panel = Paragraph::new(lines).block(block);
+
+        frame.render_widget(panel, area);
+    }
+
+    /// A box over the middle of `area` asking a question.
+    pub fn prompt_popup(frame: &mut Frame, theme: &Theme, title: &str, body: &str, area: Rect) {
+        // Just tall enough for the body between the borders
+        let width = centered_rect(60, 100, area).width.saturating_sub(2).max(1);
+        let body_lines: u16 = body
+            .lines()
+            .map(|line| (line.chars().count() as u16).div_ceil(width).max(1))
+            .sum();
+        let height = (body_lines.max(1) + 2).min(area.height);
+        let area = Rect {
+            y: area.y + (area.height - height) / 2,
+            height,
+            ..centered_rect(60, 100, area)
+        };
+        let popup = Paragraph::new(body.to_string())
+            .style(theme.root)
+            .wrap(Wrap { trim: false })
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Double)
+                    .title(Span::styled(title.to_string(), theme.app_title)),
+            );
+
+        frame.render_widget(Clear, area);
+        frame.render_widget(popup, area);
     }
 
-    pub fn controls_panel(frame: &mut Frame, run_mode: &RunMode, area: Rect) {
-        let theme = THEME;
+    pub fn combat_summary_tab(
+        frame: &mut Frame,
+        theme: &Theme,
+        state: &CombatSummaryState,
+        area: Rect,
+    ) {
+        let summary = &state.summary;
+
+        let block = Block::default()
+            .style(theme.running_combat_tab.root)
+            .borders(Borders::ALL)
+            .title(Line::from(vec![
+                Span::styled("Combat summary", theme.app_title),
+                Span::raw(format!(
+                    " | {} | {} elapsed ",
+                    match summary.rounds {
+                        1 => String::from("1 round"),
+                        rounds => format!("{} rounds", rounds),
+                    },
+                    summary.elapsed()
+                )),
+            ]));
+        let block = match &state.message {
+            Some(message) => block.title(
+                Title::from(format!(" {} ", message))
+                    .alignment(Alignment::Right)
+                    .position(Position::Bottom),
+            ),
+            None => block,
+        };
+
+        let rows = summary.participants.iter().map(|participant| {
+            Row::new(vec![
+                participant.name.clone(),
+                participant.damage_dealt.to_string(),
+                participant.damage_taken.to_string(),
+                participant.knockouts.to_string(),
+                participant.kills.to_string(),
+                participant.times_downed.to_string(),
+                format_turn_duration(participant.average_turn_duration()),
+            ])
+            .style(theme.running_combat_tab.participant)
+        });
+
+        let widths = [
+            Constraint::Length(NAME_COLUMN_WIDTH),
+            Constraint::Length(8),
+            Constraint::Length(8),
+            Constraint::Length(6),
+            Constraint::Length(6),
+            Constraint::Length(7),
+            Constraint::Min(10),
+        ];
+
+        let table = Table::new(rows, widths)
+            .header(
+                Row::new(vec![
+                    "Name",
+                    "Dealt",
+                    "Taken",
+                    "KOs",
+                    "Kills",
+                    "Downed",
+                    "Avg. turn",
+                ])
+                .style(theme.running_combat_tab.column_heading),
+            )
+            .block(block);
+
+        frame.render_widget(table, area);
 
-        let controls_text = match run_mode {
+        if let Some(input) = &state.input {
+            prompt_popup(
+                frame,
+                theme,
+                "Save summary",
+                &format!("Save the summary to: {}_", input),
+                area,
+            );
+        }
+    }
+
+    /// The read-only combat screen for a second terminal, showing what the GM shares.
+    #[cfg(unix)]
+    pub fn player_view_screen(
+        frame: &mut Frame,
+        theme: &Theme,
+        view: Option<&crate::player_view::PlayerView>,
+        source: &std::path::Path,
+        area: Rect,
+    ) {
+        let heading = match view.and_then(|view| view.round) {
+            Some(round) => format!("Round {}", round),
+            None if view.is_some() => String::from("Waiting for combat"),
+            None => format!("Waiting for the GM at {}", source.display()),
+        };
+        let block = Block::default()
+            .style(theme.running_combat_tab.root)
+            .borders(Borders::ALL)
+            .title(Span::styled(heading, theme.app_title))
+            .title(
+                Title::from(" [q (or) Esc] Quit ")
+                    .alignment(Alignment::Right)
+                    .position(Position::Bottom),
+            );
+
+        let turns = view.map(|view| view.turns.as_slice()).unwrap_or_default();
+        let rows = turns.iter().map(|turn| {
+            let health = match (turn.hit_points, turn.health) {
+                (Some(hit_points), _) => format!("{}/{}", hit_points.current, hit_points.max),
+                (None, Some(health)) => health.to_string(),
+                (None, None) => String::new(),
+            };
+            let mut status = turn.conditions.clone();
+            if turn.delayed {
+                status.insert(0, String::from("Delayed"));
+            }
+            let down = matches!(
+                turn.health,
+                Some(crate::player_view::Health::Down | crate::player_view::Health::Dead)
+            );
+
+            Row::new(vec![
+                String::from(if turn.current { "▶" } else { "" }),
+                turn.name.clone(),
+                health,
+                status.join(", "),
+            ])
+            .style(if turn.current {
+                theme.running_combat_tab.participant_active
+            } else if down || turn.delayed {
+                theme.running_combat_tab.participant_down
+            } else {
+                theme.running_combat_tab.participant
+            })
+        });
+
+        let widths = [
+            Constraint::Length(2),
+            Constraint::Length(NAME_COLUMN_WIDTH),
+            Constraint::Length(9),
+            Constraint::Min(12),
+        ];
+        let table = Table::new(rows, widths)
+            .header(
+                Row::new(vec!["", "Name", "Health", "Status"])
+                    .style(theme.running_combat_tab.column_heading),
+            )
+            .block(block);
+
+        frame.render_widget(table, area);
+    }
+
+    /// One title per open encounter, with the current one picked out. While a tab is being
+    /// renamed its title is the name typed so far. The theme's name is on the right.
+    pub fn tab_bar(frame: &mut Frame, tabs: &Tabs, area: Rect) {
+        let theme = tabs.themes.current();
+
+        let titles = tabs
+            .tabs
+            .iter()
+            .enumerate()
+            .map(|(index, tab)| match &tabs.rename_input {
+                Some(input) if index == tabs.selected => format!("{}_", input),
+                _ => tab.name.clone(),
+            });
+        let bar = TabsWidget::new(titles)
+            .style(theme.tabs)
+            .highlight_style(theme.tab_selected)
+            .select(tabs.selected);
+        let theme_name = format!(" Theme: {} ", tabs.themes.current_name());
+        let rects = Layout::horizontal([
+            Constraint::Min(0),
+            Constraint::Length(theme_name.chars().count() as u16),
+        ])
+        .split(area);
+
+        frame.render_widget(bar, rects[0]);
+        frame.render_widget(Paragraph::new(theme_name).style(theme.tabs), rects[1]);
+    }
+
+    pub fn controls_panel(frame: &mut Frame, tabs: &Tabs, area: Rect) {
+        let theme = tabs.themes.current();
+
+        let controls_text = match tabs.current() {
+            _ if tabs.is_renaming() => PROMPT_CONTROLS_TEXT,
+            _ if tabs.is_closing() => CONFIRMATION_CONTROLS_TEXT,
+            RunMode::BrowsingEncounters(_) => ENCOUNTER_BROWSER_CONTROLS_TEXT,
+            RunMode::EditingEncounter(state) if state.is_picking() => MONSTER_PICKER_CONTROLS_TEXT,
+            RunMode::EditingEncounter(state) if state.is_reporting() => REPORT_CONTROLS_TEXT,
+            RunMode::EditingEncounter(state) if state.is_prompting() => PROMPT_CONTROLS_TEXT,
+            RunMode::EditingEncounter(state) if state.is_editing() => EDITING_CELL_CONTROLS_TEXT,
             RunMode::EditingEncounter(_) => EDITING_PARTICIPANTS_CONTROLS_TEXT,
-            _ => "",
+            RunMode::RunningCombat(state) => match &state.prompt {
+                Some(prompt) if prompt.is_confirmation() => CONFIRMATION_CONTROLS_TEXT,
+                Some(_) => PROMPT_CONTROLS_TEXT,
+                None if state
+                    .initiative_system
+                    .system()
+                    .active_combatant_picks_next() =>
+                {
+                    POPCORN_COMBAT_CONTROLS_TEXT
+                }
+                None => RUNNING_COMBAT_CONTROLS_TEXT,
+            },
+            RunMode::CombatSummary(state) if state.input.is_some() => PROMPT_CONTROLS_TEXT,
+            RunMode::CombatSummary(_) => COMBAT_SUMMARY_CONTROLS_TEXT,
         };
 
-        let panel = Paragraph::new(Line::from(controls_text))
-            .style(Style::new().fg(Color::White))
+        let mut spans = Vec::new();
+        for (i, control) in controls_text.split(" | ").enumerate() {
+            if i > 0 {
+                spans.push(Span::styled(" | ", theme.controls_panel.delimiter));
+            }
+            spans.push(Span::styled(control, theme.controls_panel.text));
+        }
+
+        let panel = Paragraph::new(Line::from(spans))
+            .style(theme.controls_panel.bg)
             .centered()
+            .wrap(Wrap { trim: true })
             .block(
                 Block::default()
                     .borders(Borders::ALL)
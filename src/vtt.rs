@@ -0,0 +1,402 @@
+use anyhow::{bail, Context, Result};
+use serde_json::{Map, Value};
+
+use crate::{app::Participant, import::Imported};
+
+/// Fields of a Foundry VTT actor that are only bookkeeping, and not worth a warning.
+const FOUNDRY_METADATA: [&str; 11] = [
+    "_id",
+    "_stats",
+    "flags",
+    "folder",
+    "img",
+    "ownership",
+    "permission",
+    "prototypeToken",
+    "sort",
+    "token",
+    "type",
+];
+
+/// Fields of a Roll20 character that are only bookkeeping, and not worth a warning.
+const ROLL20_METADATA: [&str; 9] = [
+    "archived",
+    "avatar",
+    "controlledby",
+    "defaulttoken",
+    "inplayerjournals",
+    "oldId",
+    "schema_version",
+    "tags",
+    "id",
+];
+
+/// Read characters exported from Foundry VTT (dnd5e) or Roll20 as JSON. A file can hold one
+/// character or a list of them. Anything that isn't imported is listed in the warnings.
+pub fn parse(text: &str) -> Result<Imported> {
+    let json: Value = serde_json::from_str(text).context("This isn't valid JSON")?;
+    let actors = match json {
+        Value::Array(actors) => actors,
+        actor => vec![actor],
+    };
+
+    let mut imported = Imported::default();
+    for (index, actor) in actors.iter().enumerate() {
+        let actor = actor
+            .as_object()
+            .with_context(|| format!("Entry {} isn't a character", index + 1))?;
+
+        let name = actor
+            .get("name")
+            .and_then(Value::as_str)
+            .filter(|name| !name.trim().is_empty())
+            .with_context(|| format!("Entry {} has no name", index + 1))?;
+        let mut participant = Participant::new(name.trim());
+
+        let warnings = if actor.contains_key("attribs") {
+            roll20_character(actor, &mut participant)
+        } else if actor.contains_key("system") || actor.contains_key("data") {
+            foundry_actor(actor, &mut participant)
+        } else {
+            bail!(
+                "{} isn't a Foundry VTT or Roll20 character export",
+                participant.name()
+            );
+        };
+
+        imported.warnings.extend(
+            warnings
+                .into_iter()
+                .map(|warning| format!("{}: {}", participant.name(), warning)),
+        );
+        imported.participants.push(participant);
+    }
+
+    Ok(imported)
+}
+
+/// A number that might have been saved as a string, as both VTTs do.
+fn number(value: Option<&Value>) -> Option<i64> {
+    match value? {
+        Value::Number(number) => number.as_f64().map(|number| number.floor() as i64),
+        Value::String(string) => string.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+fn ability_modifier(score: i64) -> i64 {
+    (score - 10).div_euclid(2)
+}
+
+/// Fill in `participant` from a Foundry actor, returning what couldn't be.
+fn foundry_actor(actor: &Map<String, Value>, participant: &mut Participant) -> Vec<String> {
+    let mut warnings = vec![];
+    // Before Foundry 10, the system data was under `data`
+    let system = actor
+        .get("system")
+        .or_else(|| actor.get("data"))
+        .cloned()
+        .unwrap_or_default();
+    let attributes = &system["attributes"];
+
+    match (
+        number(attributes["hp"].get("max")),
+        number(attributes["hp"].get("value")),
+    ) {
+        (Some(max), current) => {
+            let max = max.clamp(0, u16::MAX as i64) as u16;
+            participant.set_hit_points(Some(max));
+            participant.set_current_hit_points(
+                current
+                    .map(|current| current.clamp(0, max as i64) as u16)
+                    .filter(|current| *current < max),
+            );
+        }
+        (None, _) => warnings.push(String::from("No maximum hit points")),
+    }
+
+    // Armor worked out from equipment can't be, since items aren't imported
+    let ac = &attributes["ac"];
+    match ac.get("calc").and_then(Value::as_str) {
+        None | Some("flat") | Some("natural") => match number(ac.get("flat").or(ac.get("value"))) {
+            Some(armor_class) => {
+                participant.set_armor_class(Some(armor_class.clamp(0, u8::MAX as i64) as u8))
+            }
+            None => warnings.push(String::from("No armor class")),
+        },
+        Some(calc) => warnings.push(format!(
+            "Armor class is calculated from equipment (\"{}\"), so it wasn't imported",
+            calc
+        )),
+    }
+
+    match number(system["abilities"]["dex"].get("value")) {
+        Some(dexterity) => {
+            let bonus = number(attributes["init"].get("bonus")).unwrap_or_default();
+            let modifier = ability_modifier(dexterity) + bonus;
+            participant
+                .set_initiative_modifier(modifier.clamp(i8::MIN as i64, i8::MAX as i64) as i8);
+        }
+        None => warnings.push(String::from(
+            "No dexterity score, so the initiative modifier is +0",
+        )),
+    }
+
+    // Characters' levels come from their classes, which are items
+    let items = actor
+        .get("items")
+        .and_then(Value::as_array)
+        .cloned()
+        .unwrap_or_default();
+    let (classes, others): (Vec<&Value>, Vec<&Value>) = items
+        .iter()
+        .partition(|item| item.get("type").and_then(Value::as_str) == Some("class"));
+    let class_levels: i64 = classes
+        .iter()
+        .filter_map(|class| {
+            number(
+                class["system"]
+                    .get("levels")
+                    .or(class["data"].get("levels")),
+            )
+        })
+        .sum();
+    let level =
+        number(system["details"].get("level")).or((class_levels > 0).then_some(class_levels));
+    if let Some(level) = level {
+        participant.set_level(Some(level.clamp(1, u8::MAX as i64) as u8));
+    }
+    if let Some(rating) = system["details"].get("cr").filter(|cr| cr.is_number()) {
+        match rating.to_string().parse() {
+            Ok(rating) => participant.set_challenge_rating(Some(rating)),
+            Err(_) => warnings.push(format!("{} isn't a challenge rating", rating)),
+        }
+    }
+
+    // Effects were labelled rather than named before Foundry 11
+    let names = |entries: &[&Value]| -> String {
+        entries
+            .iter()
+            .filter_map(|entry| entry.get("name").or(entry.get("label")))
+            .filter_map(Value::as_str)
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    if !others.is_empty() {
+        warnings.push(format!("Ignored items: {}", names(&others)));
+    }
+    let effects: Vec<&Value> = actor
+        .get("effects")
+        .and_then(Value::as_array)
+        .map(|effects| effects.iter().collect())
+        .unwrap_or_default();
+    if !effects.is_empty() {
+        warnings.push(format!("Ignored effects: {}", names(&effects)));
+    }
+
+    let ignored_system: Vec<&str> = system
+        .as_object()
+        .map(|system| {
+            system
+                .keys()
+                .map(String::as_str)
+                .filter(|key| !["abilities", "attributes", "details"].contains(key))
+                .collect()
+        })
+        .unwrap_or_default();
+    if !ignored_system.is_empty() {
+        warnings.push(format!("Ignored fields: {}", ignored_system.join(", ")));
+    }
+
+    warnings.extend(unknown_fields(
+        actor,
+        &["name", "system", "data", "items", "effects"],
+        &FOUNDRY_METADATA,
+    ));
+    warnings
+}
+
+/// Fill in `participant` from a Roll20 character, returning what couldn't be.
+fn roll20_character(character: &Map<String, Value>, participant: &mut Participant) -> Vec<String> {
+    let mut warnings = vec![];
+    let attribs = character
+        .get("attribs")
+        .and_then(Value::as_array)
+        .cloned()
+        .unwrap_or_default();
+    let attrib = |name: &str| {
+        attribs
+            .iter()
+            .find(|attrib| attrib.get("name").and_then(Value::as_str) == Some(name))
+    };
+
+    match attrib("hp") {
+        Some(hp) => {
+            // The maximum is often left empty on NPC sheets
+            let current = number(hp.get("current"));
+            match number(hp.get("max")).or(current) {
+                Some(max) => {
+                    let max = max.clamp(0, u16::MAX as i64) as u16;
+                    participant.set_hit_points(Some(max));
+                    participant.set_current_hit_points(
+                        current
+                            .map(|current| current.clamp(0, max as i64) as u16)
+                            .filter(|current| *current < max),
+                    );
+                }
+                None => warnings.push(String::from("No maximum hit points")),
+            }
+        }
+        None => warnings.push(String::from("No hit points")),
+    }
+
+    match number(
+        attrib("ac")
+            .or(attrib("npc_ac"))
+            .and_then(|ac| ac.get("current")),
+    ) {
+        Some(armor_class) => {
+            participant.set_armor_class(Some(armor_class.clamp(0, u8::MAX as i64) as u8))
+        }
+        None => warnings.push(String::from("No armor class")),
+    }
+
+    let initiative = number(attrib("initiative_bonus").and_then(|bonus| bonus.get("current")))
+        .or_else(|| {
+            number(attrib("dexterity").and_then(|dex| dex.get("current"))).map(ability_modifier)
+        });
+    match initiative {
+        Some(modifier) => participant
+            .set_initiative_modifier(modifier.clamp(i8::MIN as i64, i8::MAX as i64) as i8),
+        None => warnings.push(String::from(
+            "No initiative bonus, so the initiative modifier is +0",
+        )),
+    }
+
+    if let Some(level) = number(attrib("level").and_then(|level| level.get("current"))) {
+        participant.set_level(Some(level.clamp(1, u8::MAX as i64) as u8));
+    }
+    if let Some(rating) = attrib("npc_challenge").and_then(|cr| cr.get("current")) {
+        match rating.as_str().unwrap_or_default().parse() {
+            Ok(rating) => participant.set_challenge_rating(Some(rating)),
+            Err(_) => warnings.push(format!("{} isn't a challenge rating", rating)),
+        }
+    }
+
+    let used = [
+        "hp",
+        "ac",
+        "npc_ac",
+        "initiative_bonus",
+        "dexterity",
+        "level",
+        "npc_challenge",
+    ];
+    let unused = attribs
+        .iter()
+        .filter(|attrib| {
+            !attrib
+                .get("name")
+                .and_then(Value::as_str)
+                .is_some_and(|name| used.contains(&name))
+        })
+        .count();
+    if unused > 0 {
+        warnings.push(format!("Ignored {} other attributes", unused));
+    }
+
+    // Empty text fields aren't worth mentioning
+    let filled: Map<String, Value> = character
+        .iter()
+        .filter(|(_, value)| !matches!(value, Value::String(s) if s.is_empty()))
+        .filter(|(_, value)| !matches!(value, Value::Array(a) if a.is_empty()))
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect();
+    warnings.extend(unknown_fields(
+        &filled,
+        &["name", "attribs"],
+        &ROLL20_METADATA,
+    ));
+    warnings
+}
+
+/// A warning listing the fields of `object` that are neither read nor bookkeeping.
+fn unknown_fields(object: &Map<String, Value>, read: &[&str], metadata: &[&str]) -> Option<String> {
+    let ignored: Vec<&str> = object
+        .keys()
+        .map(String::as_str)
+        .filter(|key| !read.contains(key) && !metadata.contains(key))
+        .collect();
+
+    (!ignored.is_empty()).then(|| format!("Ignored fields: {}", ignored.join(", ")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_foundry_character() {
+        let imported = parse(include_str!("../data/samples/foundry-character.json")).unwrap();
+        let laezel = &imported.participants[0];
+
+        assert_eq!(laezel.name(), "Lae'zel");
+        assert_eq!(laezel.hit_points(), Some(34));
+        assert_eq!(laezel.current_hit_points(), Some(22));
+        assert_eq!(laezel.armor_class(), Some(17));
+        // +1 from dexterity and +1 bonus
+        assert_eq!(laezel.initiative_modifier(), 2);
+        assert_eq!(laezel.level(), Some(3));
+        assert_eq!(
+            imported.warnings,
+            vec![
+                "Lae'zel: Ignored items: Greatsword, Splint Armor",
+                "Lae'zel: Ignored effects: Second Wind",
+                "Lae'zel: Ignored fields: currency, skills",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_foundry_npc() {
+        let imported = parse(include_str!("../data/samples/foundry-npc.json")).unwrap();
+        let devourer = &imported.participants[0];
+
+        assert_eq!(devourer.hit_points(), Some(21));
+        assert_eq!(devourer.current_hit_points(), None);
+        assert_eq!(devourer.armor_class(), Some(12));
+        assert_eq!(devourer.initiative_modifier(), 2);
+        assert_eq!(devourer.level(), None);
+        assert_eq!(
+            devourer.challenge_rating().map(|cr| cr.to_string()),
+            Some(String::from("2"))
+        );
+    }
+
+    #[test]
+    fn test_roll20_character() {
+        let imported = parse(include_str!("../data/samples/roll20-character.json")).unwrap();
+        let shadowheart = &imported.participants[0];
+
+        assert_eq!(shadowheart.name(), "Shadowheart");
+        assert_eq!(shadowheart.hit_points(), Some(24));
+        assert_eq!(shadowheart.current_hit_points(), Some(19));
+        assert_eq!(shadowheart.armor_class(), Some(18));
+        assert_eq!(shadowheart.initiative_modifier(), 1);
+        assert_eq!(shadowheart.level(), Some(3));
+        assert_eq!(
+            imported.warnings,
+            vec![
+                "Shadowheart: Ignored 4 other attributes",
+                "Shadowheart: Ignored fields: abilities, bio",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unrecognised_exports_are_errors() {
+        assert!(parse("{\"name\": \"Bob\"}").is_err());
+        assert!(parse("[{\"system\": {}}]").is_err());
+        assert!(parse("not json").is_err());
+    }
+}